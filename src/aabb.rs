@@ -1,12 +1,39 @@
-use crate::{ray::Ray, vec3::Vec3};
+use std::simd::{cmp::SimdPartialOrd, num::SimdFloat, Mask, Select, Simd};
 
+use crate::{
+    packet::{splat, RayPacket, Wide, WideMask},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+#[derive(Clone, Copy)]
 pub struct AABB {
     pub min: Vec3,
     pub max: Vec3,
 }
 
+// N boxes as structure of arrays, one lane each, for testing a ray against all of them at once.
+// Unused lanes hold an inverted box no ray can enter.
+#[derive(Clone, Copy)]
+pub struct WideAabb<const N: usize> {
+    pub min: [Simd<f32, N>; 3],
+    pub max: [Simd<f32, N>; 3],
+}
+
 impl AABB {
+    pub fn surrounding_box(b1: &AABB, b2: &AABB) -> AABB {
+        return AABB { min: b1.min.min(b2.min), max: b1.max.max(b2.max) };
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        return 2.0 * (d.x * d.y + d.y * d.z + d.z * d.x);
+    }
+
     pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        // The interval has to carry over between axes, the ray is only inside where all three slabs overlap
+        let mut mt_min = t_min;
+        let mut mt_max = t_max;
         for i in 0..3 {
             // let t0 = f32::min(
             //     (self.min.idx(i) - ray.orig.idx(i)) / ray.dir.idx(i),
@@ -22,9 +49,6 @@ impl AABB {
             //     return false;
             // };
 
-            let mut mt_min = t_min.clone();
-            let mut mt_max = t_max.clone();
-
             let invd = 1.0 / ray.dir.idx(i);
             let t0 = (self.min.idx(i) - ray.orig.idx(i))* invd;
             let t1 = (self.max.idx(i) - ray.orig.idx(i))* invd;
@@ -41,4 +65,97 @@ impl AABB {
         }
         return true;
     }
+
+    // hit() for a packet, the lanes in `lanes` that pass through the box before their own trace_len_max
+    pub fn hit_packet(&self, packet: &RayPacket, lanes: WideMask, t_min: f32, t_max: Wide) -> WideMask {
+        let (min, max) = (splat(&self.min), splat(&self.max));
+        let mut enter = Wide::splat(t_min);
+        let mut exit = t_max;
+        for i in 0..3 {
+            let t0 = (min[i] - packet.orig[i]) * packet.inv_dir[i];
+            let t1 = (max[i] - packet.orig[i]) * packet.inv_dir[i];
+            // Picked by direction like hit() does, so a NaN from a ray in a slab's plane is skipped the same way
+            let backwards = packet.inv_dir[i].simd_lt(Wide::splat(0.0));
+            enter = enter.simd_max(backwards.select(t1, t0));
+            exit = exit.simd_min(backwards.select(t0, t1));
+        }
+        return lanes & exit.simd_gt(enter);
+    }
+
+    // Same slab test as hit, but hands back the clipped [enter, exit] interval
+    pub fn hit_range(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        let mut mt_min = t_min;
+        let mut mt_max = t_max;
+        for i in 0..3 {
+            let invd = 1.0 / ray.dir.idx(i);
+            let t0 = (self.min.idx(i) - ray.orig.idx(i)) * invd;
+            let t1 = (self.max.idx(i) - ray.orig.idx(i)) * invd;
+            if invd < 0.0 {
+                mt_min = if t1 > mt_min { t1 } else { mt_min };
+                mt_max = if t0 < mt_max { t0 } else { mt_max };
+            } else {
+                mt_min = if t0 > mt_min { t0 } else { mt_min };
+                mt_max = if t1 < mt_max { t1 } else { mt_max };
+            }
+            if mt_max <= mt_min {
+                return None;
+            };
+        }
+        return Some((mt_min, mt_max));
+    }
+}
+
+impl<const N: usize> WideAabb<N> {
+    pub fn new(boxes: &[AABB]) -> WideAabb<N> {
+        assert!(boxes.len() <= N, "more boxes than lanes");
+        let mut min = [Simd::splat(f32::INFINITY); 3];
+        let mut max = [Simd::splat(f32::NEG_INFINITY); 3];
+        for (lane, b) in boxes.iter().enumerate() {
+            for i in 0..3 {
+                min[i][lane] = b.min.idx(i as i8);
+                max[i][lane] = b.max.idx(i as i8);
+            }
+        }
+        return WideAabb { min, max };
+    }
+
+    pub fn get(&self, lane: usize) -> AABB {
+        return AABB {
+            min: Vec3::new(self.min[0][lane], self.min[1][lane], self.min[2][lane]),
+            max: Vec3::new(self.max[0][lane], self.max[1][lane], self.max[2][lane]),
+        };
+    }
+
+    // AABB::hit against every box at once, `inv_dir` is 1 / ray.dir. Returns the boxes hit and the
+    // trace length each one is entered at.
+    pub fn hit(&self, ray: &Ray, inv_dir: &Vec3, t_min: f32, t_max: f32) -> (Mask<i32, N>, Simd<f32, N>) {
+        let mut enter = Simd::splat(t_min);
+        let mut exit = Simd::splat(t_max);
+        for i in 0..3 {
+            let (orig, invd) = (Simd::splat(ray.orig.idx(i as i8)), inv_dir.idx(i as i8));
+            let t0 = (self.min[i] - orig) * Simd::splat(invd);
+            let t1 = (self.max[i] - orig) * Simd::splat(invd);
+            let (near, far) = if invd < 0.0 { (t1, t0) } else { (t0, t1) };
+            enter = enter.simd_max(near);
+            exit = exit.simd_min(far);
+        }
+        return (exit.simd_gt(enter), enter);
+    }
+
+    // AABB::hit_packet against every box at once, the per axis work on the rays done a single time.
+    // Returns the lanes of `lanes` that enter each box.
+    pub fn hit_packet(&self, packet: &RayPacket, lanes: WideMask, t_min: f32, t_max: Wide) -> [WideMask; N] {
+        let backwards = packet.inv_dir.map(|d| d.simd_lt(Wide::splat(0.0)));
+        return std::array::from_fn(|child| {
+            let mut enter = Wide::splat(t_min);
+            let mut exit = t_max;
+            for i in 0..3 {
+                let t0 = (Wide::splat(self.min[i][child]) - packet.orig[i]) * packet.inv_dir[i];
+                let t1 = (Wide::splat(self.max[i][child]) - packet.orig[i]) * packet.inv_dir[i];
+                enter = enter.simd_max(backwards[i].select(t1, t0));
+                exit = exit.simd_min(backwards[i].select(t0, t1));
+            }
+            lanes & exit.simd_gt(enter)
+        });
+    }
 }
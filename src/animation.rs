@@ -0,0 +1,142 @@
+use std::f32::consts::PI;
+
+use crate::{camera::Camera, vec3::Vec3};
+
+// Camera pose at `time` seconds
+#[derive(Clone, Copy)]
+pub struct CameraKey {
+    pub time: f32,
+    pub look_from: Vec3,
+    pub look_to: Vec3,
+    pub vert_fov: f32,
+    pub focus_dist: f32,
+}
+
+impl CameraKey {
+    // Focused on the target
+    pub fn new(time: f32, look_from: Vec3, look_to: Vec3, vert_fov: f32) -> CameraKey {
+        return CameraKey { time, look_from, look_to, vert_fov, focus_dist: (look_from - look_to).length() };
+    }
+
+    fn to_array(&self) -> [f32; 8] {
+        let (f, t) = (self.look_from, self.look_to);
+        return [f.x, f.y, f.z, t.x, t.y, t.z, self.vert_fov, self.focus_dist];
+    }
+
+    fn from_array(time: f32, a: [f32; 8]) -> CameraKey {
+        return CameraKey {
+            time,
+            look_from: Vec3::new(a[0], a[1], a[2]),
+            look_to: Vec3::new(a[3], a[4], a[5]),
+            vert_fov: a[6],
+            focus_dist: a[7],
+        };
+    }
+}
+
+// Keyframed camera move. Every key value follows a Catmull-Rom spline through the keys, with the
+// tangents taken over the neighbouring key times so unevenly spaced keys still move smoothly.
+// A `looped` path treats the last key as the first again, for turntables that wrap without a hitch.
+#[derive(Clone)]
+pub struct CameraPath {
+    pub keys: Vec<CameraKey>,
+    pub view_up: Vec3,
+    pub fps: f32,
+    pub looped: bool,
+}
+
+impl CameraPath {
+    pub fn new(keys: Vec<CameraKey>, view_up: Vec3, fps: f32) -> CameraPath {
+        assert!(!keys.is_empty(), "Camera path has no keys");
+        return CameraPath { keys, view_up, fps, looped: false };
+    }
+
+    // Circles `center` once in `seconds`, `height` above it and `radius` out, starting on the +z side
+    pub fn turntable(center: Vec3, radius: f32, height: f32, vert_fov: f32, seconds: f32, fps: f32) -> CameraPath {
+        let steps = 8;
+        let keys = (0..=steps)
+            .map(|i| {
+                let angle = 2.0 * PI * i as f32 / steps as f32;
+                let look_from = center + Vec3::new(radius * angle.sin(), height, radius * angle.cos());
+                CameraKey::new(seconds * i as f32 / steps as f32, look_from, center, vert_fov)
+            })
+            .collect();
+        let mut path = CameraPath::new(keys, Vec3::newi(0, 1, 0), fps);
+        path.looped = true;
+        return path;
+    }
+
+    pub fn duration(&self) -> f32 {
+        return self.keys.last().unwrap().time - self.keys[0].time;
+    }
+
+    // A looped path leaves out its last frame, which would repeat the first
+    pub fn frame_count(&self) -> u32 {
+        let frames = (self.duration() * self.fps).floor() as u32;
+        return if self.looped { frames.max(1) } else { frames + 1 };
+    }
+
+    // Frames are numbered from 1, the first one shows the first key
+    pub fn frame_time(&self, frame: u32) -> f32 {
+        return self.keys[0].time + (frame.max(1) - 1) as f32 / self.fps;
+    }
+
+    // Key at `i`, stepping past either end wraps around a looped path and clamps otherwise
+    fn key(&self, i: isize) -> (f32, [f32; 8]) {
+        let n = self.keys.len() as isize;
+        if !self.looped || n < 2 {
+            let key = &self.keys[i.clamp(0, n - 1) as usize];
+            return (key.time, key.to_array());
+        }
+        let span = n - 1;
+        let wraps = i.div_euclid(span);
+        let key = &self.keys[i.rem_euclid(span) as usize];
+        return (key.time + wraps as f32 * self.duration(), key.to_array());
+    }
+
+    pub fn sample(&self, time: f32) -> CameraKey {
+        let n = self.keys.len();
+        let first = self.keys[0].time;
+        let time = if self.looped && self.duration() > 0.0 {
+            first + (time - first).rem_euclid(self.duration())
+        } else {
+            time.clamp(first, self.keys[n - 1].time)
+        };
+        if n == 1 {
+            return CameraKey::from_array(time, self.keys[0].to_array());
+        }
+
+        let i = self.keys[..n - 1].iter().rposition(|k| k.time <= time).unwrap_or(0) as isize;
+        let (t0, p0) = self.key(i - 1);
+        let (t1, p1) = self.key(i);
+        let (t2, p2) = self.key(i + 1);
+        let (t3, p3) = self.key(i + 2);
+        let dt = t2 - t1;
+        if dt <= 0.0 {
+            return CameraKey::from_array(time, p1);
+        }
+
+        // Cubic Hermite on [t1, t2], tangents scaled to the segment length
+        let s = (time - t1) / dt;
+        let (s2, s3) = (s * s, s * s * s);
+        let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+        let h10 = s3 - 2.0 * s2 + s;
+        let h01 = -2.0 * s3 + 3.0 * s2;
+        let h11 = s3 - s2;
+        let mut out = [0.0; 8];
+        for c in 0..8 {
+            let m1 = if t2 > t0 { (p2[c] - p0[c]) / (t2 - t0) } else { 0.0 };
+            let m2 = if t3 > t1 { (p3[c] - p1[c]) / (t3 - t1) } else { 0.0 };
+            out[c] = h00 * p1[c] + h10 * dt * m1 + h01 * p2[c] + h11 * dt * m2;
+        }
+        return CameraKey::from_array(time, out);
+    }
+
+    // `base` re-aimed for the frame, keeping its lens, shutter and projection
+    pub fn camera_at(&self, base: &Camera, frame: u32) -> Camera {
+        let key = self.sample(self.frame_time(frame));
+        let mut cam = base.clone();
+        cam.set_view(key.look_from, key.look_to, self.view_up, key.vert_fov, key.focus_dist);
+        return cam;
+    }
+}
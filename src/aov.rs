@@ -0,0 +1,271 @@
+use std::{io, simd::f32x4};
+
+use exr::prelude::{AnyChannel, AnyChannels, Encoding, FlatSamples, Layer, LayerAttributes, SmallVec, WritableImage};
+use image::{Rgb, RgbImage};
+
+use crate::{hittable::HitRecord, ray::Ray, vec3::Vec3};
+
+// Extra render passes. The surface passes come from the first thing a camera ray hits. The light is
+// split by path length: emission is what the camera sees directly, direct the light reaching it after
+// one bounce and indirect after more, so the three add up to the beauty image. Variance is the
+// estimated variance of the pixel's mean luminance, which the denoiser scales its filter by.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Aov {
+    Depth,
+    Normal,
+    Albedo,
+    MaterialId,
+    Uv,
+    ObjectId,
+    Emission,
+    Direct,
+    Indirect,
+    Variance,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AovFormat {
+    // <stem>_<pass>.png next to the beauty image, squeezed into 8 bits for a quick look
+    Png,
+    // <stem>.exr with the beauty as R, G, B and every pass as a layer of float channels
+    Exr,
+}
+
+impl Aov {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::MaterialId => "material_id",
+            Aov::Uv => "uv",
+            Aov::ObjectId => "object_id",
+            Aov::Emission => "emission",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Variance => "variance",
+        };
+    }
+
+    // EXR channel names, also the number of floats the pass takes per pixel
+    pub fn channels(&self) -> &'static [&'static str] {
+        return match self {
+            Aov::Depth => &["Z"],
+            Aov::Normal => &["X", "Y", "Z"],
+            Aov::Albedo | Aov::Emission | Aov::Direct | Aov::Indirect => &["R", "G", "B"],
+            Aov::MaterialId | Aov::ObjectId => &["id"],
+            Aov::Uv => &["U", "V"],
+            Aov::Variance => &["V"],
+        };
+    }
+}
+
+// Floats per pixel in the HDR buffer, the linear beauty RGB followed by every pass
+pub fn stride(passes: &[Aov]) -> usize {
+    return 3 + passes.iter().map(|p| p.channels().len()).sum::<usize>();
+}
+
+// Where `pass` starts within a pixel of the HDR buffer
+pub fn offset(passes: &[Aov], pass: Aov) -> Option<usize> {
+    let index = passes.iter().position(|p| *p == pass)?;
+    return Some(3 + passes[..index].iter().map(|p| p.channels().len()).sum::<usize>());
+}
+
+pub fn luminance(r: f32, g: f32, b: f32) -> f32 {
+    return 0.2126 * r + 0.7152 * g + 0.0722 * b;
+}
+
+// What one camera ray saw
+#[derive(Clone, Copy)]
+pub struct AovSample {
+    pub hit: bool,
+    // Distance from the ray origin, not the ray parameter, as camera rays aren't unit length
+    pub depth: f32,
+    // World space shading normal after normal and bump maps
+    pub normal: Vec3,
+    // Attenuation of the first scatter
+    pub albedo: f32x4,
+    pub material: i64,
+    pub uv: (f32, f32),
+    pub object_id: usize,
+    pub emission: f32x4,
+    pub direct: f32x4,
+    pub indirect: f32x4,
+}
+
+impl AovSample {
+    pub fn default() -> AovSample {
+        let zero = f32x4::splat(0.0);
+        return AovSample {
+            hit: false,
+            depth: 0.0,
+            normal: Vec3::new(0.0, 0.0, 0.0),
+            albedo: zero,
+            material: 0,
+            uv: (0.0, 0.0),
+            object_id: 0,
+            emission: zero,
+            direct: zero,
+            indirect: zero,
+        };
+    }
+
+    pub fn set_surface(&mut self, ray: &Ray, rec: &HitRecord) {
+        self.hit = true;
+        self.depth = rec.trace_len * ray.dir.length();
+        self.normal = rec.shading_normal;
+        self.material = rec.material;
+        self.uv = (rec.tex_u, rec.tex_v);
+        self.object_id = rec.object_id;
+    }
+}
+
+// Sums a pixel's samples. Depth, normal and UV are averaged over the samples that hit something,
+// the light passes and albedo over all of them like the beauty. IDs can't be blended, so they
+// come from the first sample that hit.
+pub struct AovPixel {
+    sum: AovSample,
+    hits: u32,
+    ids: Option<(i64, usize)>,
+    lum_sum: f32,
+    lum_sq_sum: f32,
+}
+
+impl AovPixel {
+    pub fn new() -> AovPixel {
+        return AovPixel { sum: AovSample::default(), hits: 0, ids: None, lum_sum: 0.0, lum_sq_sum: 0.0 };
+    }
+
+    pub fn add(&mut self, sample: &AovSample) {
+        let color = sample.emission + sample.direct + sample.indirect;
+        let lum = luminance(color[0], color[1], color[2]);
+        self.lum_sum += lum;
+        self.lum_sq_sum += lum * lum;
+        self.sum.albedo += sample.albedo;
+        self.sum.emission += sample.emission;
+        self.sum.direct += sample.direct;
+        self.sum.indirect += sample.indirect;
+        if !sample.hit {
+            return;
+        }
+        self.hits += 1;
+        self.sum.depth += sample.depth;
+        self.sum.normal = self.sum.normal + sample.normal;
+        self.sum.uv = (self.sum.uv.0 + sample.uv.0, self.sum.uv.1 + sample.uv.1);
+        if self.ids.is_none() {
+            self.ids = Some((sample.material, sample.object_id));
+        }
+    }
+
+    // Appends the pass channels in `passes` order. Pixels that never hit get an infinite depth
+    // and an ID of -1.
+    pub fn write(&self, passes: &[Aov], samples: u16, out: &mut Vec<f32>) {
+        let scale = 1.0 / samples as f32;
+        let hits = self.hits.max(1) as f32;
+        let rgb = |c: f32x4| [c[0] * scale, c[1] * scale, c[2] * scale];
+        for pass in passes {
+            match pass {
+                Aov::Depth => out.push(if self.hits == 0 { f32::INFINITY } else { self.sum.depth / hits }),
+                Aov::Normal => {
+                    let n = self.sum.normal;
+                    let n = if n.length_squared() > 0.0 { n.unit_vector() } else { n };
+                    out.extend([n.x, n.y, n.z]);
+                }
+                Aov::Albedo => out.extend(rgb(self.sum.albedo)),
+                Aov::MaterialId => out.push(self.ids.map_or(-1.0, |(mat, _)| mat as f32)),
+                Aov::Uv => out.extend([self.sum.uv.0 / hits, self.sum.uv.1 / hits]),
+                Aov::ObjectId => out.push(self.ids.map_or(-1.0, |(_, obj)| obj as f32)),
+                Aov::Emission => out.extend(rgb(self.sum.emission)),
+                Aov::Direct => out.extend(rgb(self.sum.direct)),
+                Aov::Indirect => out.extend(rgb(self.sum.indirect)),
+                Aov::Variance => {
+                    let mean = self.lum_sum * scale;
+                    out.push((self.lum_sq_sum * scale - mean * mean).max(0.0) * scale);
+                }
+            }
+        }
+    }
+}
+
+pub fn save_aovs(stem: &str, width: u32, height: u32, passes: &[Aov], format: AovFormat, hdr: &[f32]) -> io::Result<()> {
+    return match format {
+        AovFormat::Png => save_pngs(stem, width, height, passes, hdr),
+        AovFormat::Exr => save_exr(stem, width, height, passes, hdr),
+    };
+}
+
+// Channel `channel` of every pixel, `offset` being where the pass starts within a pixel
+fn plane(hdr: &[f32], stride: usize, offset: usize, channel: usize) -> Vec<f32> {
+    return hdr.chunks_exact(stride).map(|px| px[offset + channel]).collect();
+}
+
+fn save_exr(stem: &str, width: u32, height: u32, passes: &[Aov], hdr: &[f32]) -> io::Result<()> {
+    let stride = stride(passes);
+    let mut channels = vec![];
+    for (i, name) in ["R", "G", "B"].iter().enumerate() {
+        channels.push(AnyChannel::new(*name, FlatSamples::F32(plane(hdr, stride, 0, i))));
+    }
+    let mut offset = 3;
+    for pass in passes {
+        for (i, name) in pass.channels().iter().enumerate() {
+            let name = format!("{}.{}", pass.name(), name);
+            channels.push(AnyChannel::new(name.as_str(), FlatSamples::F32(plane(hdr, stride, offset, i))));
+        }
+        offset += pass.channels().len();
+    }
+
+    let layer = Layer::new(
+        (width as usize, height as usize),
+        LayerAttributes::default(),
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(channels)),
+    );
+    return exr::prelude::Image::from_layer(layer)
+        .write()
+        .to_file(format!("{stem}.exr"))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()));
+}
+
+// Spreads IDs over the hue circle so neighbouring IDs get different colours, -1 is black
+fn id_color(id: f32) -> [u8; 3] {
+    if id < 0.0 {
+        return [0, 0, 0];
+    }
+    let h = (id as u32 + 1).wrapping_mul(2654435761);
+    return [(h >> 24) as u8, (h >> 16) as u8, (h >> 8) as u8];
+}
+
+fn save_pngs(stem: &str, width: u32, height: u32, passes: &[Aov], hdr: &[f32]) -> io::Result<()> {
+    let stride = stride(passes);
+    let mut offset = 3;
+    for pass in passes {
+        // Depth is shown as nearest / depth, white on the closest surface and fading out with distance
+        // so ground planes running off to the horizon don't flatten everything else
+        let near = plane(hdr, stride, offset, 0).into_iter().filter(|d| *d > 0.0).fold(f32::INFINITY, f32::min);
+        let unorm = |v: f32| (v.clamp(0.0, 1.0) * 255.0) as u8;
+        let gamma = |v: f32| (v.max(0.0).sqrt() * 255.0) as u8;
+
+        let mut im = RgbImage::new(width, height);
+        for (px, out) in hdr.chunks_exact(stride).zip(im.pixels_mut()) {
+            let v = &px[offset..offset + pass.channels().len()];
+            *out = Rgb(match pass {
+                Aov::Depth => {
+                    let g = if v[0] > 0.0 && near.is_finite() { unorm(near / v[0]) } else { 0 };
+                    [g, g, g]
+                }
+                Aov::Normal => [unorm(0.5 * v[0] + 0.5), unorm(0.5 * v[1] + 0.5), unorm(0.5 * v[2] + 0.5)],
+                Aov::Albedo | Aov::Emission | Aov::Direct | Aov::Indirect => [gamma(v[0]), gamma(v[1]), gamma(v[2])],
+                // Shown as the standard deviation
+                Aov::Variance => {
+                    let g = gamma(v[0].sqrt());
+                    [g, g, g]
+                }
+                Aov::MaterialId | Aov::ObjectId => id_color(v[0]),
+                Aov::Uv => [unorm(v[0].rem_euclid(1.0)), unorm(v[1].rem_euclid(1.0)), 0],
+            });
+        }
+        im.save(format!("{stem}_{}.png", pass.name())).map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        offset += pass.channels().len();
+    }
+    return Ok(());
+}
@@ -1,8 +1,70 @@
+use std::sync::Arc;
+
 use rand_chacha::ChaCha20Rng;
 
-use crate::{ray::Ray, utils::{deg_to_rad, random_in_unit_sphere, random_in_unit_disk}, vec3::Vec3};
+use crate::{
+    hittable::{HitRecord, HittableList},
+    lens::LensSystem,
+    ray::Ray,
+    sampler::Sampler,
+    utils::deg_to_rad,
+    vec3::Vec3,
+    warps::{concentric_disk, uniform_polygon},
+};
 
+// Film or sensor gate in millimetres
 #[derive(Clone, Copy)]
+pub struct Sensor {
+    pub width_mm: f32,
+    pub height_mm: f32,
+}
+
+#[allow(dead_code)]
+impl Sensor {
+    pub const FULL_FRAME: Sensor = Sensor { width_mm: 36.0, height_mm: 24.0 };
+    pub const SUPER_35: Sensor = Sensor { width_mm: 24.89, height_mm: 18.66 };
+    pub const APS_C: Sensor = Sensor { width_mm: 23.6, height_mm: 15.6 };
+    pub const MICRO_FOUR_THIRDS: Sensor = Sensor { width_mm: 17.3, height_mm: 13.0 };
+
+    pub fn aspect_ratio(&self) -> f32 {
+        return self.width_mm / self.height_mm;
+    }
+}
+
+// Which way (s, t) in [0, 1] across the image turns into a ray
+#[derive(Clone)]
+pub enum Projection {
+    // Pinhole / thin lens, the default
+    Perspective,
+    // Parallel rays along the view direction, `height` world units tall at every depth
+    Orthographic { height: f32 },
+    // Equidistant fisheye, angle from the axis grows linearly with the distance from the centre.
+    // The image circle touches the top and bottom edges and covers `fov` degrees across.
+    Fisheye { fov: f32 },
+    // Full 360 x 180 latitude / longitude panorama, the view direction in the middle. Use a 2:1 image.
+    Equirect,
+    // One 90 degree face of a cube map around the camera. Use a square image.
+    CubeFace(CubeFace),
+    // Omni-directional stereo, over-under equirect panoramas with the left eye on top. Each eye sits
+    // `ipd` / 2 off the centre, perpendicular to the viewing direction. Use a square image.
+    OdsStereo { ipd: f32 },
+    // Traced through a lens prescription onto a film of `sensor` size. Distortion, vignetting and
+    // bokeh come from the lens, and rays blocked inside it are None.
+    Lens { system: Arc<LensSystem>, sensor: Sensor },
+}
+
+// Cube map faces relative to the camera: front looks along look_to, up along view_up
+#[derive(Clone, Copy)]
+pub enum CubeFace {
+    Front,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Clone)]
 pub struct Camera {
     pub origin: Vec3,
     pub lower_left_corner: Vec3,
@@ -11,7 +73,18 @@ pub struct Camera {
     pub u: Vec3,
     pub v: Vec3,
     pub w: Vec3,
-    pub lens_radius: f32
+    pub lens_radius: f32,
+    pub focus_dist: f32,
+    // Aperture blade count, below 3 gives a round aperture
+    pub blades: u32,
+    // Rotation of the aperture polygon in degrees
+    pub blade_rotation: f32,
+    // Anamorphic squeeze, the aperture is narrowed horizontally by this factor giving oval bokeh.
+    // The frame is assumed already desqueezed, so the field of view is left alone.
+    pub squeeze: f32,
+    pub shutter_open: f32,
+    pub shutter_close: f32,
+    pub projection: Projection
 }
 
 impl Camera {
@@ -48,20 +121,202 @@ impl Camera {
             w,
             u,
             v,
-            lens_radius: aperture/2.0
+            lens_radius: aperture/2.0,
+            focus_dist,
+            blades: 0,
+            blade_rotation: 0.0,
+            squeeze: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            projection: Projection::Perspective
         };
     }
 
-    pub fn get_ray(&self, s: f32, t: f32, rng: &mut ChaCha20Rng) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk(rng);
-        let offset = self.u * rd.x + self.v * rd.y;
+    // Camera from physical lens settings. Scene units are taken as metres, the field of view
+    // follows from the sensor height and focal length and the aperture diameter is focal_length / f_stop.
+    // Scenes using this should return sensor.aspect_ratio() from get_aspect_ratio.
+    pub fn new_physical(
+        look_from: Vec3,
+        look_to: Vec3,
+        view_up: Vec3,
+        sensor: Sensor,
+        focal_length_mm: f32,
+        f_stop: f32,
+        focus_dist: f32,
+        rng: &mut ChaCha20Rng
+    ) -> Camera {
+        let vert_fov = 2.0 * (sensor.height_mm / (2.0 * focal_length_mm)).atan() * 180.0 / std::f32::consts::PI;
+        let aperture = focal_length_mm / f_stop / 1000.0;
+        return Camera::new(look_from, look_to, view_up, vert_fov, sensor.aspect_ratio(), aperture, focus_dist, rng);
+    }
+
+    // Camera looking through a multi element lens, focused `focus_dist` metres from the film.
+    // The pinhole fields are set up from the film distance as a rough guide for focus_on_pixel.
+    pub fn new_lens(
+        look_from: Vec3,
+        look_to: Vec3,
+        view_up: Vec3,
+        mut system: LensSystem,
+        sensor: Sensor,
+        focus_dist: f32,
+        rng: &mut ChaCha20Rng
+    ) -> Option<Camera> {
+        // Nothing at `focus_dist` can be brought into focus, e.g. it is closer than the lens can focus
+        if !system.focus(focus_dist) {
+            return None;
+        }
+        let film_dist = system.elements.last().unwrap().thickness * 1000.0;
+        let vert_fov = 2.0 * (sensor.height_mm / (2.0 * film_dist)).atan() * 180.0 / std::f32::consts::PI;
+        let mut cam = Camera::new(look_from, look_to, view_up, vert_fov, sensor.aspect_ratio(), 0.0, focus_dist, rng);
+        cam.projection = Projection::Lens { system: Arc::new(system), sensor };
+        return Some(cam);
+    }
+
+    // Moves the focal plane to whatever the centre of pixel (x, y) sees, using the same pixel
+    // to (s, t) mapping as the render loop. Leaves the focus alone and returns false on a miss.
+    pub fn focus_on_pixel(&mut self, world: &HittableList, x: u32, y: u32, width: u32, height: u32) -> bool {
+        let s = (x as f32 + 0.5) / (width - 1) as f32;
+        let t = 1.0 - (y as f32 + 0.5) / (height - 1) as f32;
+        let dir = self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin;
+        let mut rec = HitRecord::default();
+        if !world.hit(&Ray::new(self.origin, dir, self.shutter_open), 0.0001, f32::INFINITY, &mut rec) {
+            return false;
+        }
+        // Distance along the view axis, the focal plane is perpendicular to it
+        let new_focus = (rec.point - self.origin).dot_prod(-self.w);
+        if new_focus <= 0.0 {
+            return false;
+        }
+        return self.set_focus_dist(new_focus);
+    }
+
+    // Re-aims the camera keeping the aspect ratio, lens, shutter and projection settings
+    pub fn set_view(&mut self, look_from: Vec3, look_to: Vec3, view_up: Vec3, vert_fov: f32, focus_dist: f32) {
+        let aspect_ratio = self.horizontal.length() / self.vertical.length();
+        let viewport_height = 2.0 * (deg_to_rad(vert_fov) / 2.0).tan();
+
+        self.w = (look_from - look_to).unit_vector();
+        self.u = view_up.cross_prod(self.w).unit_vector();
+        self.v = self.w.cross_prod(self.u);
+        self.origin = look_from;
+        self.horizontal = self.focus_dist * aspect_ratio * viewport_height * self.u;
+        self.vertical = self.focus_dist * viewport_height * self.v;
+        if !self.set_focus_dist(focus_dist) {
+            // Focus stays where it was, the viewport still has to follow the new view
+            self.lower_left_corner = self.origin - self.horizontal / 2 - self.vertical / 2 - self.focus_dist * self.w;
+        }
+    }
+
+    // Rays get times spread evenly over [open, close], moving objects are blurred across it
+    pub fn set_shutter(&mut self, open: f32, close: f32) {
+        self.shutter_open = open;
+        self.shutter_close = close;
+    }
+
+    // The viewport is scaled from the old focus distance to the new one, so both have to be positive
+    // and finite, and a lens system has to be able to focus there. Leaves the focus alone and returns false otherwise.
+    pub fn set_focus_dist(&mut self, focus_dist: f32) -> bool {
+        if !(focus_dist > 0.0 && focus_dist.is_finite() && self.focus_dist > 0.0 && self.focus_dist.is_finite()) {
+            return false;
+        }
+        if let Projection::Lens { system, .. } = &mut self.projection {
+            let mut refocused = (**system).clone();
+            if !refocused.focus(focus_dist) {
+                return false;
+            }
+            *system = Arc::new(refocused);
+        }
+        let scale = focus_dist / self.focus_dist;
+        self.horizontal = self.horizontal * scale;
+        self.vertical = self.vertical * scale;
+        self.focus_dist = focus_dist;
+        self.lower_left_corner = self.origin - self.horizontal / 2 - self.vertical / 2 - focus_dist * self.w;
+        return true;
+    }
+
+    // `lens` picks the point on the aperture
+    pub fn get_ray(&self, s: f32, t: f32, lens: (f32, f32), time: f32) -> Ray {
+        let lens = if self.blades >= 3 {
+            uniform_polygon(self.blades, deg_to_rad(self.blade_rotation), lens)
+        } else {
+            concentric_disk(lens)
+        };
+        let rd = self.lens_radius * lens;
+        let offset = self.u * (rd.x / self.squeeze) + self.v * rd.y;
 
         return Ray {
             orig: self.origin + offset,
             dir: self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
+            time,
         };
     }
 
+    // Ray for the current projection, None where the image has no rays (outside a fisheye circle)
+    // Always draws the lens and time dimensions, whether the projection uses them or not
+    pub fn generate_ray(&self, s: f32, t: f32, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let lens = sampler.get_2d();
+        let time = self.shutter_open + (self.shutter_close - self.shutter_open) * sampler.get_1d();
+        let forward = -self.w;
+        return match &self.projection {
+            Projection::Perspective => Some(self.get_ray(s, t, lens, time)),
+            Projection::Orthographic { height } => {
+                let height = *height;
+                let aspect = self.horizontal.length() / self.vertical.length();
+                let orig = self.origin + ((s - 0.5) * height * aspect) * self.u + ((t - 0.5) * height) * self.v;
+                Some(Ray::new(orig, forward, time))
+            }
+            Projection::Fisheye { fov } => {
+                let aspect = self.horizontal.length() / self.vertical.length();
+                let x = (2.0 * s - 1.0) * aspect;
+                let y = 2.0 * t - 1.0;
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+                let theta = r * deg_to_rad(*fov) / 2.0;
+                let phi = y.atan2(x);
+                let dir = theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) + theta.cos() * forward;
+                Some(Ray::new(self.origin, dir, time))
+            }
+            Projection::Equirect => Some(Ray::new(self.origin, self.panorama_dir(s, t), time)),
+            Projection::CubeFace(face) => {
+                let (f, up) = match face {
+                    CubeFace::Front => (forward, self.v),
+                    CubeFace::Back => (self.w, self.v),
+                    CubeFace::Left => (-self.u, self.v),
+                    CubeFace::Right => (self.u, self.v),
+                    CubeFace::Up => (self.v, self.w),
+                    CubeFace::Down => (-self.v, forward),
+                };
+                let right = f.cross_prod(up);
+                let dir = f + (2.0 * s - 1.0) * right + (2.0 * t - 1.0) * up;
+                Some(Ray::new(self.origin, dir, time))
+            }
+            Projection::OdsStereo { ipd } => {
+                let (eye, t) = if t >= 0.5 { (-1.0, 2.0 * t - 1.0) } else { (1.0, 2.0 * t) };
+                let dir = self.panorama_dir(s, t);
+                // Horizontal perpendicular to the view direction, pointing to the right eye
+                let lon = (s - 0.5) * 2.0 * std::f32::consts::PI;
+                let side = lon.cos() * self.u + lon.sin() * self.w;
+                Some(Ray::new(self.origin + (eye * ipd / 2.0) * side, dir, time))
+            }
+            Projection::Lens { system, sensor } => {
+                // The lens flips the image, so the right of the picture comes from the left of the film
+                let film_x = -(s - 0.5) * sensor.width_mm / 1000.0;
+                let film_y = -(t - 0.5) * sensor.height_mm / 1000.0;
+                let (orig, dir) = system.sample_ray(film_x, film_y, lens, sampler.get_1d())?;
+                let to_world = |p: Vec3| p.x * self.u + p.y * self.v + p.z * self.w;
+                Some(Ray::new(self.origin + to_world(orig), to_world(dir), time))
+            }
+        };
+    }
+
+    fn panorama_dir(&self, s: f32, t: f32) -> Vec3 {
+        let lon = (s - 0.5) * 2.0 * std::f32::consts::PI;
+        let lat = (t - 0.5) * std::f32::consts::PI;
+        return lat.cos() * (lon.sin() * self.u - lon.cos() * self.w) + lat.sin() * self.v;
+    }
+
     pub fn default(rng: &mut ChaCha20Rng) -> Self {
         let aspect_ratio = 16.0 / 9.0;
         let look_from = Vec3::newi(0, 0, 0);
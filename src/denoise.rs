@@ -0,0 +1,162 @@
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::aov::{self, luminance, Aov};
+
+// Passes the denoiser is guided by, rendered on top of any AOVs asked for
+pub const FEATURES: [Aov; 4] = [Aov::Albedo, Aov::Normal, Aov::Depth, Aov::Variance];
+
+// B3 spline taps of the 5x5 kernel
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// Edge-avoiding a-trous wavelet filter (Dammertz et al. 2010) with the SVGF edge-stopping weights
+// (Schied et al. 2017). Each iteration spreads the 5x5 kernel twice as wide, and every tap is weighted
+// down across normal, depth and albedo edges of the first hit and where its luminance is further
+// from the centre than the noise explains. `strength` widens that luminance tolerance.
+pub struct Denoiser {
+    pub strength: f32,
+    pub iterations: u32,
+    // Exponent on the normal dot product
+    pub sigma_normal: f32,
+    // Tolerance in multiples of the expected depth change across the tap
+    pub sigma_depth: f32,
+    pub sigma_albedo: f32,
+}
+
+struct Features {
+    albedo: [f32; 3],
+    normal: [f32; 3],
+    depth: f32,
+    // Screen space depth gradient
+    grad: (f32, f32),
+}
+
+impl Denoiser {
+    pub fn default() -> Denoiser {
+        return Denoiser { strength: 1.0, iterations: 5, sigma_normal: 128.0, sigma_depth: 1.0, sigma_albedo: 0.1 };
+    }
+
+    // Filters the linear beauty of an HDR buffer holding the FEATURES passes, returns RGB per pixel.
+    // Background pixels are left as they are.
+    pub fn denoise(&self, width: u32, height: u32, passes: &[Aov], hdr: &[f32]) -> Vec<f32> {
+        let (w, h) = (width as usize, height as usize);
+        let stride = aov::stride(passes);
+        let at = |pass: Aov| aov::offset(passes, pass).expect("the denoiser needs its FEATURES passes");
+        let (albedo_at, normal_at, depth_at, var_at) = (at(Aov::Albedo), at(Aov::Normal), at(Aov::Depth), at(Aov::Variance));
+
+        let pixels: Vec<&[f32]> = hdr.chunks_exact(stride).collect();
+        let depth: Vec<f32> = pixels.iter().map(|px| px[depth_at]).collect();
+        // One sided differences, the smaller of the two so silhouettes don't count as slopes
+        let slope = |i: usize, lo: bool, hi: bool, step: usize| {
+            let d = depth[i];
+            let back = if lo { (d - depth[i - step]).abs() } else { f32::INFINITY };
+            let fwd = if hi { (depth[i + step] - d).abs() } else { f32::INFINITY };
+            let g = back.min(fwd);
+            if g.is_finite() { g } else { 0.0 }
+        };
+        let features: Vec<Features> = (0..w * h)
+            .map(|i| {
+                let (x, y) = (i % w, i / w);
+                let px = pixels[i];
+                Features {
+                    albedo: [px[albedo_at], px[albedo_at + 1], px[albedo_at + 2]],
+                    normal: [px[normal_at], px[normal_at + 1], px[normal_at + 2]],
+                    depth: depth[i],
+                    grad: (slope(i, x > 0, x + 1 < w, 1), slope(i, y > 0, y + 1 < h, w)),
+                }
+            })
+            .collect();
+
+        let mut color: Vec<[f32; 3]> = pixels.iter().map(|px| [px[0], px[1], px[2]]).collect();
+        let mut variance: Vec<f32> = pixels.iter().map(|px| px[var_at]).collect();
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            let blurred = blur_variance(&variance, w, h);
+            let filtered: Vec<([f32; 3], f32)> = (0..w * h)
+                .into_par_iter()
+                .map(|i| self.filter_pixel(i, w, h, step, &color, &variance, &blurred, &features))
+                .collect();
+            color = filtered.iter().map(|f| f.0).collect();
+            variance = filtered.iter().map(|f| f.1).collect();
+        }
+        return color.concat();
+    }
+
+    fn filter_pixel(
+        &self,
+        i: usize,
+        w: usize,
+        h: usize,
+        step: usize,
+        color: &[[f32; 3]],
+        variance: &[f32],
+        blurred: &[f32],
+        features: &[Features],
+    ) -> ([f32; 3], f32) {
+        let p = &features[i];
+        if !p.depth.is_finite() {
+            return (color[i], variance[i]);
+        }
+        let (x, y) = ((i % w) as isize, (i / w) as isize);
+        let lum_p = luminance(color[i][0], color[i][1], color[i][2]);
+        let sigma_lum = 4.0 * self.strength * blurred[i].sqrt() + 1e-6;
+
+        let mut sum = [0.0; 3];
+        let mut var_sum = 0.0;
+        let mut weight_sum = 0.0;
+        for dy in -2..=2isize {
+            for dx in -2..=2isize {
+                let (qx, qy) = (x + dx * step as isize, y + dy * step as isize);
+                if qx < 0 || qy < 0 || qx >= w as isize || qy >= h as isize {
+                    continue;
+                }
+                let j = qy as usize * w + qx as usize;
+                let q = &features[j];
+                if !q.depth.is_finite() {
+                    continue;
+                }
+
+                let dot = p.normal[0] * q.normal[0] + p.normal[1] * q.normal[1] + p.normal[2] * q.normal[2];
+                let w_normal = dot.max(0.0).powf(self.sigma_normal);
+                let expected = (p.grad.0 * dx as f32).abs() + (p.grad.1 * dy as f32).abs();
+                let w_depth = (-(p.depth - q.depth).abs() / (self.sigma_depth * expected * step as f32 + 1e-3 * p.depth)).exp();
+                let albedo_dist = (0..3).map(|c| (p.albedo[c] - q.albedo[c]).powi(2)).sum::<f32>();
+                let w_albedo = (-albedo_dist / (self.sigma_albedo * self.sigma_albedo)).exp();
+                let lum_q = luminance(color[j][0], color[j][1], color[j][2]);
+                let w_lum = (-(lum_p - lum_q).abs() / sigma_lum).exp();
+
+                let weight = KERNEL[(dx + 2) as usize] * KERNEL[(dy + 2) as usize] * w_normal * w_depth * w_albedo * w_lum;
+                for c in 0..3 {
+                    sum[c] += weight * color[j][c];
+                }
+                var_sum += weight * weight * variance[j];
+                weight_sum += weight;
+            }
+        }
+        if weight_sum <= 0.0 {
+            return (color[i], variance[i]);
+        }
+        return ([sum[0] / weight_sum, sum[1] / weight_sum, sum[2] / weight_sum], var_sum / (weight_sum * weight_sum));
+    }
+}
+
+// 3x3 Gaussian over the variance so single noisy estimates don't decide the luminance tolerance
+fn blur_variance(variance: &[f32], w: usize, h: usize) -> Vec<f32> {
+    let taps = [0.25, 0.5, 0.25];
+    return (0..w * h)
+        .map(|i| {
+            let (x, y) = (i % w, i / w);
+            let (mut sum, mut weight) = (0.0, 0.0);
+            for dy in 0..3 {
+                for dx in 0..3 {
+                    if (x + dx == 0) || (y + dy == 0) || x + dx > w || y + dy > h {
+                        continue;
+                    }
+                    let k = taps[dx] * taps[dy];
+                    sum += k * variance[(y + dy - 1) * w + x + dx - 1];
+                    weight += k;
+                }
+            }
+            sum / weight
+        })
+        .collect();
+}
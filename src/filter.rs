@@ -0,0 +1,163 @@
+use std::f32::consts::PI;
+
+use parking_lot::Mutex;
+
+// Pixel reconstruction filters, all separable. Mitchell and Lanczos have negative lobes, which
+// sharpen but can ring around hard edges.
+#[derive(Clone, Copy)]
+pub enum Filter {
+    Box,
+    Tent,
+    // Gaussian shifted down so it reaches zero at the radius
+    Gaussian { sigma: f32 },
+    // Mitchell-Netravali cubic stretched over the radius, B = C = 1/3 is the recommended pair
+    Mitchell { b: f32, c: f32 },
+    // sinc windowed by a sinc as wide as the radius
+    Lanczos,
+}
+
+// A filter and how far from a pixel centre, in pixels, samples still count towards it
+#[derive(Clone, Copy)]
+pub struct PixelFilter {
+    pub filter: Filter,
+    pub radius: f32,
+}
+
+impl PixelFilter {
+    // One pixel box, what the renderer has always done
+    pub fn default() -> PixelFilter {
+        return PixelFilter { filter: Filter::Box, radius: 0.5 };
+    }
+
+    pub fn new(filter: Filter, radius: f32) -> PixelFilter {
+        return PixelFilter { filter, radius };
+    }
+
+    // Every sample only lands in its own pixel, so the plain average per pixel is already right
+    pub fn is_pixel_box(&self) -> bool {
+        return matches!(self.filter, Filter::Box) && self.radius <= 0.5;
+    }
+
+    // Rows above and below its own that a sample can reach
+    pub fn reach(&self) -> usize {
+        return (self.radius + 0.5).ceil() as usize;
+    }
+
+    pub fn eval(&self, dx: f32, dy: f32) -> f32 {
+        return self.eval_1d(dx) * self.eval_1d(dy);
+    }
+
+    fn eval_1d(&self, x: f32) -> f32 {
+        let x = x.abs();
+        let r = self.radius;
+        if x > r {
+            return 0.0;
+        }
+        return match self.filter {
+            Filter::Box => 1.0,
+            Filter::Tent => r - x,
+            Filter::Gaussian { sigma } => {
+                let g = |x: f32| (-x * x / (2.0 * sigma * sigma)).exp();
+                (g(x) - g(r)).max(0.0)
+            }
+            Filter::Mitchell { b, c } => mitchell(2.0 * x / r, b, c),
+            Filter::Lanczos => sinc(x) * sinc(x / r),
+        };
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    return (PI * x).sin() / (PI * x);
+}
+
+// Mitchell and Netravali, "Reconstruction Filters in Computer Graphics", on |x| in [0, 2]
+fn mitchell(x: f32, b: f32, c: f32) -> f32 {
+    let (x2, x3) = (x * x, x * x * x);
+    let v = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x3 + (-18.0 + 12.0 * b + 6.0 * c) * x2 + (6.0 - 2.0 * b)
+    } else if x < 2.0 {
+        (-b - 6.0 * c) * x3 + (6.0 * b + 30.0 * c) * x2 + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)
+    } else {
+        0.0
+    };
+    return v / 6.0;
+}
+
+// Filter weighted colour and weight sums for the whole image. Rows are locked one at a time so
+// threads only wait on each other when they finish neighbouring lines together.
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    rows: Vec<Mutex<Vec<f32>>>,
+}
+
+// A render line's splats, covering the rows its samples can reach
+pub struct FilmBlock {
+    first_row: isize,
+    rows: usize,
+    width: usize,
+    data: Vec<f32>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Film {
+        return Film { width, height, rows: (0..height).map(|_| Mutex::new(vec![0.0; width * 4])).collect() };
+    }
+
+    pub fn block(&self, row: usize, filter: &PixelFilter) -> FilmBlock {
+        let reach = filter.reach();
+        let rows = 2 * reach + 1;
+        return FilmBlock { first_row: row as isize - reach as isize, rows, width: self.width, data: vec![0.0; rows * self.width * 4] };
+    }
+
+    pub fn add(&self, block: &FilmBlock) {
+        for r in 0..block.rows {
+            let row = block.first_row + r as isize;
+            if row < 0 || row >= self.height as isize {
+                continue;
+            }
+            let src = &block.data[r * block.width * 4..(r + 1) * block.width * 4];
+            let mut dst = self.rows[row as usize].lock();
+            for (d, s) in dst.iter_mut().zip(src.iter()) {
+                *d += s;
+            }
+        }
+    }
+
+    // Linear RGB per pixel
+    pub fn resolve(&self) -> Vec<f32> {
+        let mut rgb = Vec::with_capacity(self.width * self.height * 3);
+        for row in &self.rows {
+            for px in row.lock().chunks_exact(4) {
+                let w = if px[3] != 0.0 { 1.0 / px[3] } else { 0.0 };
+                rgb.extend([px[0] * w, px[1] * w, px[2] * w]);
+            }
+        }
+        return rgb;
+    }
+}
+
+impl FilmBlock {
+    // Sample at (sx, sy) in pixel coordinates, pixel centres are at half integers
+    pub fn splat(&mut self, filter: &PixelFilter, sx: f32, sy: f32, color: [f32; 3]) {
+        let r = filter.radius;
+        let x0 = ((sx - 0.5 - r).ceil() as isize).max(0);
+        let x1 = ((sx - 0.5 + r).floor() as isize).min(self.width as isize - 1);
+        let y0 = ((sy - 0.5 - r).ceil() as isize).max(self.first_row);
+        let y1 = ((sy - 0.5 + r).floor() as isize).min(self.first_row + self.rows as isize - 1);
+        for py in y0..=y1 {
+            let wy = py as f32 + 0.5 - sy;
+            for px in x0..=x1 {
+                let w = filter.eval(px as f32 + 0.5 - sx, wy);
+                let i = ((py - self.first_row) as usize * self.width + px as usize) * 4;
+                self.data[i] += w * color[0];
+                self.data[i + 1] += w * color[1];
+                self.data[i + 2] += w * color[2];
+                self.data[i + 3] += w;
+            }
+        }
+    }
+}
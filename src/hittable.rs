@@ -1,21 +1,32 @@
 use std::{cell::Ref, sync::Arc};
 
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use smallvec::SmallVec;
 
-use crate::{ray::Ray, vec3::Vec3, aabb::AABB};
+use crate::{ray::Ray, vec3::Vec3, aabb::AABB, color::Color, hittables::wide_bvh::Bvh, packet::{self, PacketHit, RayPacket, WideMask}};
 
 #[derive(Clone, Copy)]
 pub struct HitRecord {
     pub point: Vec3,
+    // Geometric normal, against the ray. Scattered rays are kept on its side so they can't leak through.
     pub normal: Vec3,
+    // Interpolated or mapped normal the materials shade with, on the same side as `normal`
+    pub shading_normal: Vec3,
+    // Surface derivatives along the texture coordinates, zero when the shape has no tangent frame
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     pub trace_len: f32,
     pub front_face: bool,
     pub material: i64,
     pub tex_u: f32,
-    pub tex_v: f32
+    pub tex_v: f32,
+    pub vert_color: Option<Color>,
+    // Index of the top level object in the world list, for the object ID pass
+    pub object_id: usize
 }
 
 impl HitRecord {
+    // Also resets the shading normal and clears the tangent frame, shapes that have one set it afterwards
     pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: &Vec3) {
         self.front_face = ray.dir.dot_prod(*outward_normal) < 0.0;
         if self.front_face {
@@ -23,34 +34,61 @@ impl HitRecord {
         } else {
             self.normal = -*outward_normal;
         }
+        self.shading_normal = self.normal;
+        self.dpdu = Vec3::new(0.0, 0.0, 0.0);
+        self.dpdv = Vec3::new(0.0, 0.0, 0.0);
     }
 
     pub fn default() -> HitRecord {
         HitRecord {
-            point: Vec3 {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
-            },
-            normal: Vec3 {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
-            },
+            point: Vec3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(0.0, 0.0, 0.0),
+            shading_normal: Vec3::new(0.0, 0.0, 0.0),
+            dpdu: Vec3::new(0.0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, 0.0),
             trace_len: 0.0,
             front_face: false,
             material: 0,
             tex_u: 0.0,
-            tex_v: 0.0
+            tex_v: 0.0,
+            vert_color: None,
+            object_id: 0
         }
     }
 }
 
+// Surface crossings of one ray for CSG, kept on the stack unless a shape has a lot of them
+pub type Crossings = SmallVec<[HitRecord; 4]>;
+
 pub trait Hittable {
     fn hit(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool;
     fn bounds(&self, output_box: &mut AABB) -> bool;
+
+    // Every surface crossing in order of trace length. The default steps hit() past each crossing,
+    // which works for any closed shape that also reports its exit when the ray starts inside, but can
+    // step over a shell thinner than the step. Convex shapes override it with their exact entry and exit.
+    fn hit_all(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, out: &mut Crossings) {
+        let step = 1e-4 / r.dir.length();
+        let mut rec = HitRecord::default();
+        let mut t = trace_len_min;
+        for _ in 0..MAX_CROSSINGS {
+            if !self.hit(r, t, trace_len_max, &mut rec) {
+                break;
+            }
+            out.push(rec);
+            t = rec.trace_len + step;
+        }
+    }
+
+    // hit() for the lanes of a packet in `lanes`, each searched up to its hits.trace_len. Records the
+    // closer hits in `hits` and returns the lanes that got one. The default traces lane by lane.
+    fn hit_packet(&self, packet: &RayPacket, lanes: WideMask, trace_len_min: f32, hits: &mut PacketHit) -> WideMask {
+        return packet::hit_lanes(self, packet, lanes, trace_len_min, hits);
+    }
 }
 
+const MAX_CROSSINGS: usize = 32;
+
 pub struct HittableList {
     pub objs: Vec<Box<dyn Hittable + Sync + Send>>
 }
@@ -74,9 +112,9 @@ impl HittableList {
     ) -> bool {
         let mut hit_anything = false;
         let mut closest_so_far = trace_len_max;
-        let mut tmp_rec: HitRecord = HitRecord::default();
-
         for obj in &self.objs {
+            // Fresh record per object so fields one hittable doesn't fill can't leak from another
+            let mut tmp_rec: HitRecord = HitRecord::default();
             //let mut bounds = AABB { min: Vec3::newi(0,0,0), max: Vec3::newi(0,0,0) };
             //obj.bounds(&mut bounds);
             //if bounds.hit(ray, trace_len_min,trace_len_max) {
@@ -91,6 +129,14 @@ impl HittableList {
         return hit_anything;
     }
 
+    // hit() for every active lane of a packet, returns the lanes that hit anything
+    pub fn hit_packet(&self, packet: &RayPacket, trace_len_min: f32, hits: &mut PacketHit) -> WideMask {
+        for obj in &self.objs {
+            obj.hit_packet(packet, packet.active, trace_len_min, hits);
+        }
+        return hits.hit;
+    }
+
     pub fn bounds(&self, output: &mut AABB) -> bool {
         let mut tmp = AABB { min: Vec3::newi(0,0,0), max: Vec3::newi(0,0,0) };
         let mut fbox = true;
@@ -114,21 +160,68 @@ impl HittableList {
     }
 
     fn surrounding_box(&self, b1: &AABB, b2: &AABB) -> AABB {
-        return AABB {
-            min: Vec3::new(
-                f32::min(b1.min.x, b2.min.x),
-                f32::min(b1.min.y, b2.min.y),
-                f32::min(b1.min.z, b2.min.z)
-            ),
-            max: Vec3::new(
-                f32::max(b1.max.x, b2.max.x),
-                f32::max(b1.max.y, b2.max.y),
-                f32::max(b1.max.z, b2.max.z)
-            )
-        }
+        return AABB::surrounding_box(b1, b2);
     }
 
+    // Every object is tagged with the index it was added at, which its hits carry as object_id
     pub fn add(&mut self, obj: Box<dyn Hittable + Sync + Send>) {
-        self.objs.push(obj);
+        self.objs.push(Box::new(Tagged { id: self.objs.len(), obj }));
+    }
+
+    // Moves every object with bounds into one WideBvh, the ones without (planes) stay beside it and
+    // are tested one by one. The object IDs stay the same. Call once the scene is set up.
+    pub fn accelerate(&mut self) {
+        let mut bounded: Vec<Arc<Box<dyn Hittable + Sync + Send>>> = vec![];
+        let mut unbounded = vec![];
+        let mut tmp = AABB { min: Vec3::newi(0, 0, 0), max: Vec3::newi(0, 0, 0) };
+        for obj in self.objs.drain(..) {
+            if obj.bounds(&mut tmp) {
+                bounded.push(Arc::new(obj));
+            } else {
+                unbounded.push(obj);
+            }
+        }
+        if !bounded.is_empty() {
+            self.objs.push(Box::new(Bvh::new(&bounded, 0, bounded.len())));
+        }
+        self.objs.extend(unbounded);
+    }
+}
+
+// A top level object, stamps the index it was added to the world at into its hits
+struct Tagged {
+    id: usize,
+    obj: Box<dyn Hittable + Sync + Send>,
+}
+
+impl Hittable for Tagged {
+    fn hit(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        if !self.obj.hit(r, trace_len_min, trace_len_max, rec) {
+            return false;
+        }
+        rec.object_id = self.id;
+        return true;
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        return self.obj.bounds(output_box);
+    }
+
+    fn hit_all(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, out: &mut Crossings) {
+        let first = out.len();
+        self.obj.hit_all(r, trace_len_min, trace_len_max, out);
+        for rec in &mut out[first..] {
+            rec.object_id = self.id;
+        }
+    }
+
+    fn hit_packet(&self, packet: &RayPacket, lanes: WideMask, trace_len_min: f32, hits: &mut PacketHit) -> WideMask {
+        let found = self.obj.hit_packet(packet, lanes, trace_len_min, hits);
+        for lane in 0..packet::LANES {
+            if found.test(lane) {
+                hits.recs[lane].object_id = self.id;
+            }
+        }
+        return found;
     }
 }
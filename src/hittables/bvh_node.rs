@@ -3,12 +3,12 @@ use std::sync::Arc;
 
 use rand_chacha::ChaCha20Rng;
 
-use crate::hittable::Hittable;
+use crate::hittable::{HitRecord, Hittable};
+use crate::packet::{PacketHit, RayPacket, WideMask};
 use crate::aabb::AABB;
-use crate::rand_double::rand_double;
-use crate::vec3::{self, Vec3};
-
-use super::sphere::Sphere;
+use crate::rand_double::rand_double_range;
+use crate::stats;
+use crate::vec3::Vec3;
 
 pub struct BvhNode {
     left: Arc<Box<dyn Hittable + Sync + Send>>,
@@ -24,22 +24,40 @@ impl Hittable for BvhNode {
     }
 
     fn hit(&self, r: &crate::ray::Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut crate::hittable::HitRecord) -> bool {
-        if self.bound.hit(r, trace_len_min, trace_len_max) {
+        stats::count_bvh_node();
+        if !self.bound.hit(r, trace_len_min, trace_len_max) {
             return false
         };
 
         let hit_left = self.left.hit(r, trace_len_min, trace_len_max, rec);
-        let hit_right = self.right.hit(r, trace_len_min, if hit_left {rec.trace_len} else {trace_len_max}, rec);
+        let mut right_rec = HitRecord::default();
+        let hit_right = self.right.hit(r, trace_len_min, if hit_left {rec.trace_len} else {trace_len_max}, &mut right_rec);
+        if hit_right {
+            *rec = right_rec;
+        }
 
         return hit_left || hit_right;
     }
+
+    // Both children see only the lanes that made it into the box, the right one after the left has
+    // pulled their trace lengths in
+    fn hit_packet(&self, packet: &RayPacket, lanes: WideMask, trace_len_min: f32, hits: &mut PacketHit) -> WideMask {
+        stats::count_bvh_node();
+        let lanes = self.bound.hit_packet(packet, lanes, trace_len_min, hits.trace_len);
+        if !lanes.any() {
+            return lanes;
+        }
+        let hit_left = self.left.hit_packet(packet, lanes, trace_len_min, hits);
+        let hit_right = self.right.hit_packet(packet, lanes, trace_len_min, hits);
+        return hit_left | hit_right;
+    }
 }
 
 fn box_compare(a: &Arc<Box<dyn Hittable + Sync + Send>>, b: &Arc<Box<dyn Hittable + Sync + Send>>, axis: i8) -> Ordering {
     let mut box_a = AABB { min: Vec3::newi(0,0,0), max: Vec3::newi(0,0,0) };
     let mut box_b = AABB { min: Vec3::newi(0,0,0), max: Vec3::newi(0,0,0) };
 
-    if !a.bounds(&mut box_a) || !a.bounds(&mut box_b) {
+    if !a.bounds(&mut box_a) || !b.bounds(&mut box_b) {
         panic!("No bounding box in bvh_node constructor");
     };
 
@@ -54,44 +72,48 @@ fn box_compare(a: &Arc<Box<dyn Hittable + Sync + Send>>, b: &Arc<Box<dyn Hittabl
 
 impl BvhNode {
     pub fn new(src_objs: &Vec<Arc<Box<dyn Hittable + Sync + Send>>>, start: usize, end: usize, rng: &mut ChaCha20Rng) -> BvhNode {
-        let mut objs = src_objs.clone();
-
-        let axis = (rand_double(rng) as i8) % 3;
-
-        let comp = match axis {
-            0 => | a: &Arc<Box<dyn Hittable + Sync + Send>>, b: &Arc<Box<dyn Hittable + Sync + Send>> | { return box_compare(a, b, 0) },
-            1 => | a: &Arc<Box<dyn Hittable + Sync + Send>>, b: &Arc<Box<dyn Hittable + Sync + Send>> | { return box_compare(a, b, 1) },
-            2 => | a: &Arc<Box<dyn Hittable + Sync + Send>>, b: &Arc<Box<dyn Hittable + Sync + Send>> | { return box_compare(a, b, 2) },
-            _ => | a: &Arc<Box<dyn Hittable + Sync + Send>>, b: &Arc<Box<dyn Hittable + Sync + Send>> | { return box_compare(a, b, 2) },
-        };
+        // Copy the span once and partition it in place, rather than cloning the whole list at every level
+        let mut objs = src_objs[start..end].to_vec();
+        return BvhNode::build(&mut objs, rng);
+    }
 
-        let obj_span = end-start;
+    fn build(objs: &mut [Arc<Box<dyn Hittable + Sync + Send>>], rng: &mut ChaCha20Rng) -> BvhNode {
+        let axis = (rand_double_range(rng, 0.0, 3.0) as i8) % 3;
+        let comp = | a: &Arc<Box<dyn Hittable + Sync + Send>>, b: &Arc<Box<dyn Hittable + Sync + Send>> | { return box_compare(a, b, axis) };
 
-        let mut out = BvhNode {
-            left: Arc::new(Box::new(Sphere::new(Vec3::newi(0,0,0), 0.0, &0))),
-            right: Arc::new(Box::new(Sphere::new(Vec3::newi(0,0,0), 0.0, &0))),
-            bound: AABB { min: Vec3::newi(0,0,0), max: Vec3::newi(0,0,0) },
-        };
+        let obj_span = objs.len();
+        let left: Arc<Box<dyn Hittable + Sync + Send>>;
+        let right: Arc<Box<dyn Hittable + Sync + Send>>;
 
         if obj_span == 1 {
-            out.left = out.right;
-            out.right = objs[start].clone();
+            left = objs[0].clone();
+            right = objs[0].clone();
         } else if obj_span == 2 {
-            if comp(&objs[start], &objs[start+1]) == Ordering::Less {
-                out.left = objs[start].clone();
-                out.right = objs[start+1].clone();
+            if comp(&objs[0], &objs[1]) == Ordering::Less {
+                left = objs[0].clone();
+                right = objs[1].clone();
             } else {
-                out.left = objs[start+1].clone();
-                out.right = objs[start].clone();
+                left = objs[1].clone();
+                right = objs[0].clone();
             }
         } else {
             objs.sort_unstable_by(comp);
 
-            let mid = start + obj_span/2;
-            out.left = Arc::new(Box::new(BvhNode::new(&objs, start, mid, rng)));
-            out.right = Arc::new(Box::new(BvhNode::new(&objs, mid, end, rng)));
+            let (lower, upper) = objs.split_at_mut(obj_span/2);
+            left = Arc::new(Box::new(BvhNode::build(lower, rng)));
+            right = Arc::new(Box::new(BvhNode::build(upper, rng)));
         }
 
-        return out;
+        let mut box_left = AABB { min: Vec3::newi(0,0,0), max: Vec3::newi(0,0,0) };
+        let mut box_right = AABB { min: Vec3::newi(0,0,0), max: Vec3::newi(0,0,0) };
+        if !left.bounds(&mut box_left) || !right.bounds(&mut box_right) {
+            panic!("No bounding box in bvh_node constructor");
+        };
+
+        return BvhNode {
+            left,
+            right,
+            bound: AABB::surrounding_box(&box_left, &box_right)
+        };
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,97 @@
+use std::f32::consts::PI;
+
+use crate::{vec3::Vec3, hittable::Hittable, hittable::HitRecord, ray::Ray, aabb::AABB};
+
+// Capped cone with its base disk centred on `center` and the apex `height` above it along +y
+#[derive(Clone, Copy)]
+pub struct Cone {
+    pub center: Vec3,
+    pub radius: f32,
+    pub height: f32,
+    pub material: i64
+}
+
+impl Cone {
+    pub fn new_box(center: Vec3, radius: f32, height: f32, material: &i64) -> Box<Cone> {
+        return Box::new(Cone { center, radius, height, material: material.clone() })
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        let o = ray.orig - self.center;
+        let d = ray.dir;
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let mut closest = trace_len_max;
+        let mut outward_normal = Vec3::newi(0, 0, 0);
+        let mut found = false;
+
+        // Side, x^2 + z^2 = k^2 (h - y)^2 with 0 <= y <= h
+        let hy = self.height - o.y;
+        let a = d.x * d.x + d.z * d.z - k2 * d.y * d.y;
+        let b_half = o.x * d.x + o.z * d.z + k2 * hy * d.y;
+        let c = o.x * o.x + o.z * o.z - k2 * hy * hy;
+        let mut roots = [0.0; 4];
+        let mut count = 0;
+        if a.abs() > 1e-12 {
+            let discriminant = b_half * b_half - a * c;
+            if discriminant >= 0.0 {
+                let sqrt_disc = discriminant.sqrt();
+                roots[0] = (-b_half - sqrt_disc) / a;
+                roots[1] = (-b_half + sqrt_disc) / a;
+                count = 2;
+            }
+        } else if b_half.abs() > 1e-12 {
+            // Ray parallel to the slope crosses the double cone once
+            roots[0] = -c / (2.0 * b_half);
+            count = 1;
+        }
+        for &root in &roots[..count] {
+            let p = o + root * d;
+            if root >= trace_len_min && root < closest && p.y >= 0.0 && p.y <= self.height {
+                closest = root;
+                // Gradient of the implicit surface
+                outward_normal = Vec3::new(p.x, k2 * (self.height - p.y), p.z).unit_vector();
+                found = true;
+            }
+        }
+
+        // Base cap
+        if d.y.abs() > 1e-12 {
+            let root = -o.y / d.y;
+            let x = o.x + root * d.x;
+            let z = o.z + root * d.z;
+            if root >= trace_len_min && root < closest && x * x + z * z <= self.radius * self.radius {
+                closest = root;
+                outward_normal = Vec3::newi(0, -1, 0);
+                found = true;
+            }
+        }
+
+        if !found {
+            return false;
+        }
+
+        rec.trace_len = closest;
+        rec.point = ray.at(closest);
+        rec.set_face_normal(ray, &outward_normal);
+        rec.material = self.material;
+
+        let local = rec.point - self.center;
+        rec.tex_u = (f32::atan2(-local.z, local.x) + PI) / (2.0 * PI);
+        rec.tex_v = if outward_normal.y == -1.0 {
+            (local.x * local.x + local.z * local.z).sqrt() / self.radius
+        } else {
+            local.y / self.height
+        };
+        return true;
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        output_box.min = self.center - Vec3::new(self.radius, 0.0, self.radius);
+        output_box.max = self.center + Vec3::new(self.radius, self.height, self.radius);
+        return true;
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::AABB,
+    hittable::{Crossings, HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(&self, in_left: bool, in_right: bool) -> bool {
+        return match self {
+            CsgOp::Union => in_left || in_right,
+            CsgOp::Intersection => in_left && in_right,
+            CsgOp::Difference => in_left && !in_right,
+        };
+    }
+}
+
+// Boolean combination of two closed hittables. Surfaces keep their own material, the
+// right hand side of a difference is turned inside out so it faces into the carved hole.
+pub struct Csg {
+    pub left: Arc<dyn Hittable + Sync + Send>,
+    pub right: Arc<dyn Hittable + Sync + Send>,
+    pub op: CsgOp,
+}
+
+impl Csg {
+    pub fn new_box(left: Arc<dyn Hittable + Sync + Send>, right: Arc<dyn Hittable + Sync + Send>, op: CsgOp) -> Box<Csg> {
+        return Box::new(Csg { left, right, op });
+    }
+    pub fn union(left: Arc<dyn Hittable + Sync + Send>, right: Arc<dyn Hittable + Sync + Send>) -> Box<Csg> {
+        return Self::new_box(left, right, CsgOp::Union);
+    }
+    pub fn intersection(left: Arc<dyn Hittable + Sync + Send>, right: Arc<dyn Hittable + Sync + Send>) -> Box<Csg> {
+        return Self::new_box(left, right, CsgOp::Intersection);
+    }
+    pub fn difference(left: Arc<dyn Hittable + Sync + Send>, right: Arc<dyn Hittable + Sync + Send>) -> Box<Csg> {
+        return Self::new_box(left, right, CsgOp::Difference);
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        // Crossings are gathered past trace_len_max, the first one after it still tells us
+        // whether the ray was inside that child over the part we care about
+        let mut left_hits = Crossings::new();
+        let mut right_hits = Crossings::new();
+        self.left.hit_all(r, trace_len_min, f32::INFINITY, &mut left_hits);
+        self.right.hit_all(r, trace_len_min, f32::INFINITY, &mut right_hits);
+
+        // A child whose first crossing is an exit had the ray origin inside it
+        let mut in_left = left_hits.first().map_or(false, |h| !h.front_face);
+        let mut in_right = right_hits.first().map_or(false, |h| !h.front_face);
+        let (mut il, mut ir) = (0, 0);
+
+        while il < left_hits.len() || ir < right_hits.len() {
+            let from_left = ir >= right_hits.len()
+                || (il < left_hits.len() && left_hits[il].trace_len <= right_hits[ir].trace_len);
+            let event = if from_left { left_hits[il] } else { right_hits[ir] };
+            if event.trace_len > trace_len_max {
+                return false;
+            }
+
+            let was_inside = self.op.inside(in_left, in_right);
+            if from_left {
+                in_left = event.front_face;
+                il += 1;
+            } else {
+                in_right = event.front_face;
+                ir += 1;
+            }
+
+            if was_inside != self.op.inside(in_left, in_right) {
+                // The carved out side faces the other way, the normals already face the ray
+                // so only which side counts as outside changes
+                *rec = event;
+                if !from_left && self.op == CsgOp::Difference {
+                    rec.front_face = !rec.front_face;
+                }
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        let mut box_left = AABB { min: Vec3::newi(0, 0, 0), max: Vec3::newi(0, 0, 0) };
+        let mut box_right = AABB { min: Vec3::newi(0, 0, 0), max: Vec3::newi(0, 0, 0) };
+        if !self.left.bounds(&mut box_left) {
+            return false;
+        }
+
+        let out = match self.op {
+            CsgOp::Difference => box_left,
+            CsgOp::Union => {
+                if !self.right.bounds(&mut box_right) {
+                    return false;
+                }
+                AABB::surrounding_box(&box_left, &box_right)
+            }
+            CsgOp::Intersection => {
+                if !self.right.bounds(&mut box_right) {
+                    return false;
+                }
+                AABB {
+                    min: Vec3::new(
+                        box_left.min.x.max(box_right.min.x),
+                        box_left.min.y.max(box_right.min.y),
+                        box_left.min.z.max(box_right.min.z),
+                    ),
+                    max: Vec3::new(
+                        box_left.max.x.min(box_right.max.x),
+                        box_left.max.y.min(box_right.max.y),
+                        box_left.max.z.min(box_right.max.z),
+                    ),
+                }
+            }
+        };
+
+        output_box.min = out.min;
+        output_box.max = out.max;
+        return true;
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{vec3::Vec3, hittable::{Crossings, Hittable}, hittable::HitRecord, ray::Ray, aabb::AABB};
+
+// Axis aligned solid box, wrap it in an Instance to rotate it
+#[derive(Clone, Copy)]
+pub struct Cuboid {
+    pub min: Vec3,
+    pub max: Vec3,
+    pub material: i64
+}
+
+impl Cuboid {
+    pub fn new(min: Vec3, max: Vec3, material: &i64) -> Cuboid {
+        return Cuboid { min, max, material: material.clone() };
+    }
+    pub fn new_box(min: Vec3, max: Vec3, material: &i64) -> Box<Cuboid> {
+        return Box::new(Self::new(min, max, material));
+    }
+
+    // The hit record for the point at trace length `t`
+    fn fill(&self, ray: &Ray, t: f32, rec: &mut HitRecord) {
+        // The face hit is the one whose plane the point lies closest to, relative to the box size
+        let p = ray.at(t);
+        let center = (self.min + self.max) * 0.5;
+        let half = (self.max - self.min) * 0.5;
+        let rel = (p - center) / half;
+        let mut axis = 0;
+        for i in 1..3 {
+            if rel.idx(i).abs() > rel.idx(axis).abs() {
+                axis = i;
+            }
+        }
+        let sign = if rel.idx(axis) < 0.0 { -1.0 } else { 1.0 };
+        let outward_normal = match axis {
+            0 => Vec3::new(sign, 0.0, 0.0),
+            1 => Vec3::new(0.0, sign, 0.0),
+            _ => Vec3::new(0.0, 0.0, sign),
+        };
+
+        rec.trace_len = t;
+        rec.point = p;
+        rec.set_face_normal(ray, &outward_normal);
+        rec.material = self.material;
+        // Each face maps its two in-plane axes onto [0, 1]
+        let (ia, ib) = match axis {
+            0 => (1, 2),
+            1 => (0, 2),
+            _ => (0, 1),
+        };
+        rec.tex_u = (rel.idx(ia) + 1.0) * 0.5;
+        rec.tex_v = (rel.idx(ib) + 1.0) * 0.5;
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        let slab = AABB { min: self.min, max: self.max };
+        let (t_enter, t_exit) = match slab.hit_range(ray, f32::NEG_INFINITY, f32::INFINITY) {
+            Some(range) => range,
+            None => return false,
+        };
+        let t = if t_enter >= trace_len_min { t_enter } else { t_exit };
+        if t < trace_len_min || trace_len_max < t {
+            return false;
+        }
+        self.fill(ray, t, rec);
+        return true;
+    }
+
+    // The slab interval's ends are where the ray enters and leaves
+    fn hit_all(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, out: &mut Crossings) {
+        crate::stats::count_primitive_test();
+        let slab = AABB { min: self.min, max: self.max };
+        if let Some((t_enter, t_exit)) = slab.hit_range(ray, f32::NEG_INFINITY, f32::INFINITY) {
+            for t in [t_enter, t_exit] {
+                if trace_len_min <= t && t <= trace_len_max {
+                    let mut rec = HitRecord::default();
+                    self.fill(ray, t, &mut rec);
+                    out.push(rec);
+                }
+            }
+        }
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        output_box.min = self.min;
+        output_box.max = self.max;
+        return true;
+    }
+}
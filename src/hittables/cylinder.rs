@@ -0,0 +1,123 @@
+use std::f32::consts::PI;
+
+use crate::{vec3::Vec3, hittable::{Crossings, Hittable}, hittable::HitRecord, ray::Ray, aabb::AABB};
+
+// Capped cylinder standing on `center` (middle of the bottom cap) and rising `height` along +y
+#[derive(Clone, Copy)]
+pub struct Cylinder {
+    pub center: Vec3,
+    pub radius: f32,
+    pub height: f32,
+    pub material: i64
+}
+
+impl Cylinder {
+    pub fn new_box(center: Vec3, radius: f32, height: f32, material: &i64) -> Box<Cylinder> {
+        return Box::new(Cylinder { center, radius, height, material: material.clone() })
+    }
+
+    // Every root on the side wall and the caps with its outward normal, in no particular order
+    fn crossings(&self, ray: &Ray) -> ([(f32, Vec3); 4], usize) {
+        let o = ray.orig - self.center;
+        let d = ray.dir;
+        let mut out = [(0.0, Vec3::newi(0, 0, 0)); 4];
+        let mut count = 0;
+
+        // Side wall, x^2 + z^2 = r^2 with 0 <= y <= h
+        let a = d.x * d.x + d.z * d.z;
+        if a > 1e-12 {
+            let b_half = o.x * d.x + o.z * d.z;
+            let c = o.x * o.x + o.z * o.z - self.radius * self.radius;
+            let discriminant = b_half * b_half - a * c;
+            if discriminant >= 0.0 {
+                let sqrt_disc = discriminant.sqrt();
+                for root in [(-b_half - sqrt_disc) / a, (-b_half + sqrt_disc) / a] {
+                    let y = o.y + root * d.y;
+                    if y >= 0.0 && y <= self.height {
+                        out[count] = (root, Vec3::new(o.x + root * d.x, 0.0, o.z + root * d.z) / self.radius);
+                        count += 1;
+                    }
+                }
+            }
+        }
+
+        // Caps
+        if d.y.abs() > 1e-12 {
+            for (cap_y, cap_normal) in [(0.0, -1.0), (self.height, 1.0)] {
+                let root = (cap_y - o.y) / d.y;
+                let x = o.x + root * d.x;
+                let z = o.z + root * d.z;
+                if x * x + z * z <= self.radius * self.radius {
+                    out[count] = (root, Vec3::new(0.0, cap_normal, 0.0));
+                    count += 1;
+                }
+            }
+        }
+        return (out, count);
+    }
+
+    // The hit record for the point at trace length `t`
+    fn fill(&self, ray: &Ray, t: f32, outward_normal: &Vec3, rec: &mut HitRecord) {
+        rec.trace_len = t;
+        rec.point = ray.at(t);
+        rec.set_face_normal(ray, outward_normal);
+        rec.material = self.material;
+
+        let local = rec.point - self.center;
+        rec.tex_u = (f32::atan2(-local.z, local.x) + PI) / (2.0 * PI);
+        rec.tex_v = if outward_normal.y == 0.0 {
+            local.y / self.height
+        } else {
+            (local.x * local.x + local.z * local.z).sqrt() / self.radius
+        };
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        let (crossings, count) = self.crossings(ray);
+        let mut closest = trace_len_max;
+        let mut outward_normal = Vec3::newi(0, 0, 0);
+        let mut found = false;
+        for &(root, normal) in &crossings[..count] {
+            if root >= trace_len_min && root < closest {
+                closest = root;
+                outward_normal = normal;
+                found = true;
+            }
+        }
+        if !found {
+            return false;
+        }
+        self.fill(ray, closest, &outward_normal, rec);
+        return true;
+    }
+
+    // Convex, so the nearest crossing is the entry and the furthest the exit
+    fn hit_all(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, out: &mut Crossings) {
+        crate::stats::count_primitive_test();
+        let (crossings, count) = self.crossings(ray);
+        if count == 0 {
+            return;
+        }
+        let crossings = &crossings[..count];
+        let entry = *crossings.iter().min_by(|a, b| a.0.total_cmp(&b.0)).unwrap();
+        let exit = *crossings.iter().max_by(|a, b| a.0.total_cmp(&b.0)).unwrap();
+        // A ray through a rim finds the wall and the cap at the same point, that's still one crossing
+        let ends = if exit.0 > entry.0 { 2 } else { 1 };
+        for &(root, normal) in &[entry, exit][..ends] {
+            if trace_len_min <= root && root <= trace_len_max {
+                let mut rec = HitRecord::default();
+                self.fill(ray, root, &normal, &mut rec);
+                out.push(rec);
+            }
+        }
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        output_box.min = self.center - Vec3::new(self.radius, 0.0, self.radius);
+        output_box.max = self.center + Vec3::new(self.radius, self.height, self.radius);
+        return true;
+    }
+}
@@ -0,0 +1,68 @@
+use std::f32::consts::PI;
+
+use crate::{vec3::Vec3, hittable::Hittable, hittable::HitRecord, ray::Ray, aabb::AABB};
+
+use super::plane::plane_axes;
+
+#[derive(Clone, Copy)]
+pub struct Disk {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub radius: f32,
+    pub material: i64
+}
+
+impl Disk {
+    pub fn new_box(center: Vec3, normal: Vec3, radius: f32, material: &i64) -> Box<Disk> {
+        return Box::new(Disk {
+            center,
+            normal: normal.unit_vector(),
+            radius,
+            material: material.clone()
+        })
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        let denom = self.normal.dot_prod(ray.dir);
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+        let t = (self.center - ray.orig).dot_prod(self.normal) / denom;
+        if t < trace_len_min || trace_len_max < t {
+            return false;
+        }
+        let local = ray.at(t) - self.center;
+        let dist_squared = local.length_squared();
+        if dist_squared > self.radius * self.radius {
+            return false;
+        }
+
+        rec.trace_len = t;
+        rec.point = ray.at(t);
+        rec.set_face_normal(ray, &self.normal);
+        rec.material = self.material;
+
+        // Polar mapping, u around the rim and v outwards from the centre
+        let (u_axis, v_axis) = plane_axes(&self.normal);
+        let phi = f32::atan2(local.dot_prod(v_axis), local.dot_prod(u_axis));
+        rec.tex_u = (phi + PI) / (2.0 * PI);
+        rec.tex_v = dist_squared.sqrt() / self.radius;
+        return true;
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        // Extent of a tilted disk along each axis is r * sqrt(1 - n_i^2)
+        let n = self.normal;
+        let extent = Vec3::new(
+            self.radius * (1.0 - n.x * n.x).max(0.0).sqrt() + 1e-4,
+            self.radius * (1.0 - n.y * n.y).max(0.0).sqrt() + 1e-4,
+            self.radius * (1.0 - n.z * n.z).max(0.0).sqrt() + 1e-4,
+        );
+        output_box.min = self.center - extent;
+        output_box.max = self.center + extent;
+        return true;
+    }
+}
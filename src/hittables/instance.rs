@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    mat4::Mat4,
+    ray::Ray,
+    utils::deg_to_rad,
+    vec3::Vec3,
+};
+
+// Places a shared object in the world through an affine transform, so one mesh can be reused many times
+pub struct Instance {
+    pub obj: Arc<dyn Hittable + Sync + Send>,
+    pub transform: Mat4,
+    pub inverse: Mat4,
+    pub normal_mat: Mat4,
+}
+
+impl Instance {
+    pub fn new(obj: Arc<dyn Hittable + Sync + Send>, transform: Mat4) -> Instance {
+        return Self::try_new(obj, transform).expect("Instance transform is not invertible");
+    }
+    pub fn new_box(obj: Arc<dyn Hittable + Sync + Send>, transform: Mat4) -> Box<Instance> {
+        return Box::new(Self::new(obj, transform));
+    }
+
+    // None for a singular transform, e.g. a zero scale that imported scenes use to hide objects
+    pub fn try_new(obj: Arc<dyn Hittable + Sync + Send>, transform: Mat4) -> Option<Instance> {
+        let inverse = transform.inverse()?;
+        return Some(Instance {
+            obj,
+            transform,
+            inverse,
+            normal_mat: inverse.transpose(),
+        });
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        return hit_transformed(&self.obj, &self.transform, &self.inverse, &self.normal_mat, r, trace_len_min, trace_len_max, rec);
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        let mut local = AABB { min: Vec3::newi(0, 0, 0), max: Vec3::newi(0, 0, 0) };
+        if !self.obj.bounds(&mut local) {
+            return false;
+        }
+
+        let world = transform_bounds(&local, &self.transform);
+        output_box.min = world.min;
+        output_box.max = world.max;
+        return true;
+    }
+}
+
+fn hit_transformed(
+    obj: &Arc<dyn Hittable + Sync + Send>,
+    transform: &Mat4,
+    inverse: &Mat4,
+    normal_mat: &Mat4,
+    r: &Ray,
+    trace_len_min: f32,
+    trace_len_max: f32,
+    rec: &mut HitRecord,
+) -> bool {
+    // Direction is left unnormalized so trace lengths match between the two spaces
+    let local = Ray::new(
+        inverse.transform_point(&r.orig),
+        inverse.transform_vector(&r.dir),
+        r.time,
+    );
+    if !obj.hit(&local, trace_len_min, trace_len_max, rec) {
+        return false;
+    }
+
+    // n.d is invariant under (M^-1)^T n and M d, so front_face carries over untouched
+    rec.point = transform.transform_point(&rec.point);
+    rec.normal = normal_mat.transform_vector(&rec.normal).unit_vector();
+    rec.shading_normal = normal_mat.transform_vector(&rec.shading_normal).unit_vector();
+    rec.dpdu = transform.transform_vector(&rec.dpdu);
+    rec.dpdv = transform.transform_vector(&rec.dpdv);
+    return true;
+}
+
+fn transform_bounds(local: &AABB, transform: &Mat4) -> AABB {
+    let mut min = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut max = Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for i in 0..8 {
+        let corner = Vec3::new(
+            if i & 1 == 0 { local.min.x } else { local.max.x },
+            if i & 2 == 0 { local.min.y } else { local.max.y },
+            if i & 4 == 0 { local.min.z } else { local.max.z },
+        );
+        let p = transform.transform_point(&corner);
+        min = Vec3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+        max = Vec3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+    }
+    return AABB { min, max };
+}
+
+// Scale, then rotate (x, y, z euler degrees), then translate, at a point in shutter time
+#[derive(Clone, Copy)]
+pub struct TransformKey {
+    pub time: f32,
+    pub translate: Vec3,
+    pub rotate: Vec3,
+    pub scale: Vec3,
+}
+
+impl TransformKey {
+    pub fn matrix(&self) -> Mat4 {
+        return Mat4::translate(self.translate)
+            * Mat4::rotate_z(self.rotate.z)
+            * Mat4::rotate_y(self.rotate.y)
+            * Mat4::rotate_x(self.rotate.x)
+            * Mat4::scale(self.scale);
+    }
+
+    // Built from the inverted factors in reverse, cheaper and steadier than a general inverse per ray
+    pub fn inverse_matrix(&self) -> Mat4 {
+        return Mat4::scale(Vec3::new(1.0 / self.scale.x, 1.0 / self.scale.y, 1.0 / self.scale.z))
+            * Mat4::rotate_x(-self.rotate.x)
+            * Mat4::rotate_y(-self.rotate.y)
+            * Mat4::rotate_z(-self.rotate.z)
+            * Mat4::translate(-self.translate);
+    }
+
+    // Bound on how far any point within `reach` of the local origin moves between the two keys.
+    // Rotations differ by at most the sum of the euler angle changes, which moves a point by at most
+    // that angle times its distance from the centre.
+    fn max_move(a: &TransformKey, b: &TransformKey, reach: f32) -> f32 {
+        let abs_max = |v: Vec3| v.x.abs().max(v.y.abs()).max(v.z.abs());
+        let turn = b.rotate - a.rotate;
+        let angle = deg_to_rad(turn.x.abs() + turn.y.abs() + turn.z.abs());
+        let scale = abs_max(a.scale).max(abs_max(b.scale));
+        return (b.translate - a.translate).length() + (abs_max(b.scale - a.scale) + angle * scale) * reach;
+    }
+
+    fn lerp(a: &TransformKey, b: &TransformKey, time: f32) -> TransformKey {
+        let t = if b.time > a.time { ((time - a.time) / (b.time - a.time)).clamp(0.0, 1.0) } else { 0.0 };
+        return TransformKey {
+            time,
+            translate: a.translate + t * (b.translate - a.translate),
+            rotate: a.rotate + t * (b.rotate - a.rotate),
+            scale: a.scale + t * (b.scale - a.scale),
+        };
+    }
+}
+
+// Instance whose transform is keyframed over time, keys are linearly interpolated and held past either end
+pub struct MotionInstance {
+    pub obj: Arc<dyn Hittable + Sync + Send>,
+    pub keys: Vec<TransformKey>,
+}
+
+impl MotionInstance {
+    pub fn new(obj: Arc<dyn Hittable + Sync + Send>, mut keys: Vec<TransformKey>) -> MotionInstance {
+        assert!(!keys.is_empty(), "MotionInstance needs at least one key");
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        return MotionInstance { obj, keys };
+    }
+    pub fn new_box(obj: Arc<dyn Hittable + Sync + Send>, keys: Vec<TransformKey>) -> Box<MotionInstance> {
+        return Box::new(Self::new(obj, keys));
+    }
+
+    pub fn key_at(&self, time: f32) -> TransformKey {
+        let next = self.keys.iter().position(|k| k.time > time).unwrap_or(self.keys.len());
+        if next == 0 {
+            return self.keys[0];
+        }
+        if next == self.keys.len() {
+            return self.keys[self.keys.len() - 1];
+        }
+        return TransformKey::lerp(&self.keys[next - 1], &self.keys[next], time);
+    }
+}
+
+impl Hittable for MotionInstance {
+    fn hit(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        let key = self.key_at(r.time);
+        let inverse = key.inverse_matrix();
+        return hit_transformed(&self.obj, &key.matrix(), &inverse, &inverse.transpose(), r, trace_len_min, trace_len_max, rec);
+    }
+
+    // Covers the pose at every key and at steps in between, each padded by how far any point of the
+    // object can get from it in half a step. Rotations bulge out of the straight line between the
+    // sampled poses, the padding keeps that inside.
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        const STEPS: usize = 8;
+        let mut local = AABB { min: Vec3::newi(0, 0, 0), max: Vec3::newi(0, 0, 0) };
+        if !self.obj.bounds(&mut local) {
+            return false;
+        }
+        // Furthest the object's points are from its local origin
+        let reach = Vec3::new(
+            local.min.x.abs().max(local.max.x.abs()),
+            local.min.y.abs().max(local.max.y.abs()),
+            local.min.z.abs().max(local.max.z.abs()),
+        )
+        .length();
+
+        let mut out = transform_bounds(&local, &self.keys[0].matrix());
+        for pair in self.keys.windows(2) {
+            let mut prev = pair[0];
+            for step in 1..=STEPS {
+                let time = pair[0].time + (pair[1].time - pair[0].time) * step as f32 / STEPS as f32;
+                let key = TransformKey::lerp(&pair[0], &pair[1], time);
+                let pad = Vec3::splat(TransformKey::max_move(&prev, &key, reach) / 2.0);
+                for k in [&prev, &key] {
+                    let b = transform_bounds(&local, &k.matrix());
+                    out = AABB::surrounding_box(&out, &AABB { min: b.min - pad, max: b.max + pad });
+                }
+                prev = key;
+            }
+        }
+
+        output_box.min = out.min;
+        output_box.max = out.max;
+        return true;
+    }
+}
@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::AABB,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    packet::{PacketHit, RayPacket, WideMask},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+use super::{triangle, wide_bvh::Bvh};
+
+// Indexed triangle mesh, `normals`, `colors` and `uvs` are either empty or one entry per position
+pub struct Mesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub colors: Vec<Color>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<[u32; 3]>,
+    pub double_sided: bool,
+}
+
+// How an imported mesh is placed before rendering
+#[derive(Clone, Copy)]
+pub struct MeshImportOptions {
+    // Move the bounding box centre here
+    pub recenter: Option<Vec3>,
+    // Uniformly scale so the largest bounding box side has this length
+    pub fit_size: Option<f32>,
+    // Generate vertex normals when the file has none
+    pub smooth: bool,
+    // Off culls faces seen from behind, closed glass meshes need both sides
+    pub double_sided: bool,
+}
+
+impl MeshImportOptions {
+    pub fn default() -> MeshImportOptions {
+        return MeshImportOptions { recenter: None, fit_size: None, smooth: false, double_sided: true };
+    }
+}
+
+impl Mesh {
+    pub fn bounds(&self) -> AABB {
+        let mut min = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for p in &self.positions {
+            min = Vec3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Vec3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+        return AABB { min, max };
+    }
+
+    pub fn new() -> Mesh {
+        return Mesh { positions: vec![], normals: vec![], colors: vec![], uvs: vec![], indices: vec![], double_sided: true };
+    }
+
+    // Area weighted average of the faces around each vertex, the cross product length is twice the area
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::new(0.0, 0.0, 0.0); self.positions.len()];
+        for [i0, i1, i2] in &self.indices {
+            let (p0, p1, p2) = (self.positions[*i0 as usize], self.positions[*i1 as usize], self.positions[*i2 as usize]);
+            let face = (p1 - p0).cross_prod(p2 - p0);
+            for i in [i0, i1, i2] {
+                normals[*i as usize] = normals[*i as usize] + face;
+            }
+        }
+        for n in normals.iter_mut() {
+            if n.length_squared() > 0.0 {
+                *n = n.unit_vector();
+            }
+        }
+        self.normals = normals;
+    }
+
+    // Scales about the bounding box centre, then moves that centre to `recenter`
+    pub fn apply_import_options(&mut self, options: &MeshImportOptions) {
+        self.double_sided = options.double_sided;
+        if options.smooth && self.normals.is_empty() {
+            self.compute_normals();
+        }
+        if self.positions.is_empty() {
+            return;
+        }
+        let bound = self.bounds();
+        let center = (bound.min + bound.max) * 0.5;
+        let extent = bound.max - bound.min;
+        let largest = extent.x.max(extent.y).max(extent.z);
+
+        let scale = match options.fit_size {
+            Some(size) if largest > 0.0 => size / largest,
+            _ => 1.0,
+        };
+        let target = options.recenter.unwrap_or(center);
+        for p in self.positions.iter_mut() {
+            *p = target + (*p - center) * scale;
+        }
+    }
+
+    // One BVH over all faces, every face shares the mesh data through an Arc
+    pub fn into_bvh(self, material: &i64) -> Box<Bvh> {
+        assert!(!self.indices.is_empty(), "Mesh has no faces");
+        let mesh = Arc::new(self);
+        let tris: Vec<Arc<Box<dyn Hittable + Sync + Send>>> = (0..mesh.indices.len())
+            .map(|index| {
+                Arc::new(Box::new(MeshTriangle {
+                    mesh: mesh.clone(),
+                    index,
+                    material: material.clone(),
+                }) as Box<dyn Hittable + Sync + Send>)
+            })
+            .collect();
+        return Box::new(Bvh::new(&tris, 0, tris.len()));
+    }
+}
+
+pub struct MeshTriangle {
+    pub mesh: Arc<Mesh>,
+    pub index: usize,
+    pub material: i64,
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> (Vec3, Vec3, Vec3) {
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        let p = &self.mesh.positions;
+        return (p[i0 as usize], p[i1 as usize], p[i2 as usize]);
+    }
+
+    // The hit record at trace length `t` and barycentrics u, v of the second and third corner
+    fn fill(&self, r: &Ray, t: f32, u: f32, v: f32, rec: &mut HitRecord) {
+        let (v0, v1, v2) = self.vertices();
+        rec.tex_u = u;
+        rec.tex_v = v;
+        if !self.mesh.uvs.is_empty() {
+            let [i0, i1, i2] = self.mesh.indices[self.index];
+            let uv = &self.mesh.uvs;
+            let w = 1.0 - u - v;
+            rec.tex_u = w * uv[i0 as usize][0] + u * uv[i1 as usize][0] + v * uv[i2 as usize][0];
+            rec.tex_v = w * uv[i0 as usize][1] + u * uv[i1 as usize][1] + v * uv[i2 as usize][1];
+        }
+        rec.trace_len = t;
+        rec.material = self.material;
+        rec.point = r.at(t);
+
+        let shading = if self.mesh.normals.is_empty() {
+            None
+        } else {
+            let [i0, i1, i2] = self.mesh.indices[self.index];
+            let n = &self.mesh.normals;
+            Some((1.0 - u - v) * n[i0 as usize] + u * n[i1 as usize] + v * n[i2 as usize])
+        };
+        triangle::set_normals(rec, r, &(v1 - v0).cross_prod(v2 - v0), shading);
+        (rec.dpdu, rec.dpdv) = if self.mesh.uvs.is_empty() {
+            (v1 - v0, v2 - v0)
+        } else {
+            let [i0, i1, i2] = self.mesh.indices[self.index];
+            let uv = &self.mesh.uvs;
+            triangle::uv_derivatives([v0, v1, v2], [uv[i0 as usize], uv[i1 as usize], uv[i2 as usize]])
+        };
+
+        if !self.mesh.colors.is_empty() {
+            let [i0, i1, i2] = self.mesh.indices[self.index];
+            let c = &self.mesh.colors;
+            let w = 1.0 - u - v;
+            rec.vert_color = Some(Color::new_simd4(
+                c[i0 as usize].to_simd4() * std::simd::f32x4::splat(w)
+                    + c[i1 as usize].to_simd4() * std::simd::f32x4::splat(u)
+                    + c[i2 as usize].to_simd4() * std::simd::f32x4::splat(v),
+            ));
+        }
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        let (v0, v1, v2) = self.vertices();
+        let (t, u, v) = match triangle::intersect(&v0, &v1, &v2, r, trace_len_min, trace_len_max, self.mesh.double_sided) {
+            Some(hit) => hit,
+            None => return false,
+        };
+
+        self.fill(r, t, u, v, rec);
+        return true;
+    }
+
+    fn hit_packet(&self, packet: &RayPacket, lanes: WideMask, trace_len_min: f32, hits: &mut PacketHit) -> WideMask {
+        let (v0, v1, v2) = self.vertices();
+        return triangle::hit_packet(self, [&v0, &v1, &v2], self.mesh.double_sided, packet, lanes, trace_len_min, hits, |r, t, u, v, rec| {
+            self.fill(r, t, u, v, rec)
+        });
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        let (v0, v1, v2) = self.vertices();
+        let pad = Vec3::new(1e-4, 1e-4, 1e-4);
+        output_box.min = Vec3::new(v0.x.min(v1.x).min(v2.x), v0.y.min(v1.y).min(v2.y), v0.z.min(v1.z).min(v2.z)) - pad;
+        output_box.max = Vec3::new(v0.x.max(v1.x).max(v2.x), v0.y.max(v1.y).max(v2.y), v0.z.max(v1.z).max(v2.z)) + pad;
+        return true;
+    }
+}
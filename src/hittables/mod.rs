@@ -1,3 +1,17 @@
 pub mod sphere;
 pub mod triangle;
-pub mod bvh_node;
\ No newline at end of file
+pub mod bvh_node;
+pub mod wide_bvh;
+pub mod instance;
+pub mod moving_sphere;
+pub mod voxel_volume;
+pub mod plane;
+pub mod rect;
+pub mod disk;
+pub mod cuboid;
+pub mod cylinder;
+pub mod cone;
+pub mod torus;
+pub mod csg;
+pub mod sdf;
+pub mod mesh;
\ No newline at end of file
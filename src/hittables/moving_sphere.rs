@@ -0,0 +1,54 @@
+use crate::{vec3::Vec3, hittable::Hittable, hittable::HitRecord, ray::Ray, aabb::AABB};
+
+use super::sphere::Sphere;
+
+// Sphere whose center moves linearly from center0 at time0 to center1 at time1, held in place outside that range
+#[derive(Clone, Copy)]
+pub struct MovingSphere {
+    pub center0: Vec3,
+    pub center1: Vec3,
+    pub time0: f32,
+    pub time1: f32,
+    pub radius: f32,
+    pub material: i64
+}
+
+impl MovingSphere {
+    pub fn new_box(center0: Vec3, center1: Vec3, time0: f32, time1: f32, radius: f32, material: &i64) -> Box<MovingSphere> {
+        return Box::new(MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material: material.clone()
+        })
+    }
+
+    pub fn center(&self, time: f32) -> Vec3 {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+        let t = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        return self.center0 + t * (self.center1 - self.center0);
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        let frozen = Sphere::new(self.center(ray.time), self.radius, &self.material);
+        return frozen.hit(ray, trace_len_min, trace_len_max, rec);
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        let r = self.radius.abs();
+        let extent = Vec3::new(r, r, r);
+        let box0 = AABB { min: self.center0 - extent, max: self.center0 + extent };
+        let box1 = AABB { min: self.center1 - extent, max: self.center1 + extent };
+        let both = AABB::surrounding_box(&box0, &box1);
+        output_box.min = both.min;
+        output_box.max = both.max;
+        return true;
+    }
+}
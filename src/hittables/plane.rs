@@ -0,0 +1,60 @@
+use crate::{vec3::Vec3, hittable::Hittable, hittable::HitRecord, ray::Ray, aabb::AABB};
+
+// Infinite plane through `point`, has no bounds so it has to live outside any BvhNode
+#[derive(Clone, Copy)]
+pub struct Plane {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub material: i64
+}
+
+impl Plane {
+    pub fn new_box(point: Vec3, normal: Vec3, material: &i64) -> Box<Plane> {
+        return Box::new(Plane {
+            point,
+            normal: normal.unit_vector(),
+            material: material.clone()
+        })
+    }
+}
+
+// Any two unit vectors spanning the plane with normal n
+pub fn plane_axes(n: &Vec3) -> (Vec3, Vec3) {
+    let helper = if n.x.abs() > 0.9 { Vec3::newi(0, 1, 0) } else { Vec3::newi(1, 0, 0) };
+    let u = helper.cross_prod(*n).unit_vector();
+    let v = n.cross_prod(u);
+    return (u, v);
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        let denom = self.normal.dot_prod(ray.dir);
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+        let t = (self.point - ray.orig).dot_prod(self.normal) / denom;
+        if t < trace_len_min || trace_len_max < t {
+            return false;
+        }
+
+        rec.trace_len = t;
+        rec.point = ray.at(t);
+        rec.set_face_normal(ray, &self.normal);
+        rec.material = self.material;
+
+        // Tiles once per world unit
+        let (u_axis, v_axis) = plane_axes(&self.normal);
+        let local = rec.point - self.point;
+        let u = local.dot_prod(u_axis);
+        let v = local.dot_prod(v_axis);
+        rec.tex_u = u - u.floor();
+        rec.tex_v = v - v.floor();
+        return true;
+    }
+
+    #[allow(unused_variables)]
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        return false;
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{vec3::Vec3, hittable::Hittable, hittable::HitRecord, ray::Ray, aabb::AABB};
+
+// Axis aligned rectangle at `k` along `axis`, spanning [a0, a1] x [b0, b1] on the other two axes in x, y, z order
+#[derive(Clone, Copy)]
+pub struct AaRect {
+    pub axis: i8,
+    pub a0: f32,
+    pub a1: f32,
+    pub b0: f32,
+    pub b1: f32,
+    pub k: f32,
+    pub material: i64
+}
+
+impl AaRect {
+    pub fn xy(x0: f32, x1: f32, y0: f32, y1: f32, k: f32, material: &i64) -> Box<AaRect> {
+        return Box::new(AaRect { axis: 2, a0: x0, a1: x1, b0: y0, b1: y1, k, material: material.clone() });
+    }
+    pub fn xz(x0: f32, x1: f32, z0: f32, z1: f32, k: f32, material: &i64) -> Box<AaRect> {
+        return Box::new(AaRect { axis: 1, a0: x0, a1: x1, b0: z0, b1: z1, k, material: material.clone() });
+    }
+    pub fn yz(y0: f32, y1: f32, z0: f32, z1: f32, k: f32, material: &i64) -> Box<AaRect> {
+        return Box::new(AaRect { axis: 0, a0: y0, a1: y1, b0: z0, b1: z1, k, material: material.clone() });
+    }
+
+    fn plane_axes(&self) -> (i8, i8) {
+        return match self.axis {
+            0 => (1, 2),
+            1 => (0, 2),
+            _ => (0, 1),
+        };
+    }
+
+    fn point(&self, n: f32, a: f32, b: f32) -> Vec3 {
+        return match self.axis {
+            0 => Vec3::new(n, a, b),
+            1 => Vec3::new(a, n, b),
+            _ => Vec3::new(a, b, n),
+        };
+    }
+}
+
+impl Hittable for AaRect {
+    fn hit(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        let (ia, ib) = self.plane_axes();
+        let t = (self.k - ray.orig.idx(self.axis)) / ray.dir.idx(self.axis);
+        if !(t >= trace_len_min && t <= trace_len_max) {
+            return false;
+        }
+        let a = ray.orig.idx(ia) + t * ray.dir.idx(ia);
+        let b = ray.orig.idx(ib) + t * ray.dir.idx(ib);
+        if a < self.a0 || a > self.a1 || b < self.b0 || b > self.b1 {
+            return false;
+        }
+
+        rec.trace_len = t;
+        rec.point = ray.at(t);
+        rec.set_face_normal(ray, &self.point(1.0, 0.0, 0.0));
+        rec.material = self.material;
+        rec.tex_u = (a - self.a0) / (self.a1 - self.a0);
+        rec.tex_v = (b - self.b0) / (self.b1 - self.b0);
+        return true;
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        // Padded along the normal so the slab test has some thickness to work with
+        output_box.min = self.point(self.k - 1e-4, self.a0, self.b0);
+        output_box.max = self.point(self.k + 1e-4, self.a1, self.b1);
+        return true;
+    }
+}
@@ -0,0 +1,185 @@
+use std::f32::consts::PI;
+
+use crate::{vec3::Vec3, hittable::Hittable, hittable::HitRecord, ray::Ray, aabb::AABB};
+
+// Signed distance expression tree, leaves are centred on the origin and moved with Translate
+pub enum SdfNode {
+    Sphere { radius: f32 },
+    Cuboid { half: Vec3 },
+    Torus { major: f32, minor: f32 },
+    Translate { offset: Vec3, child: Box<SdfNode> },
+    Scale { factor: f32, child: Box<SdfNode> },
+    Union { a: Box<SdfNode>, b: Box<SdfNode> },
+    Intersection { a: Box<SdfNode>, b: Box<SdfNode> },
+    Subtract { a: Box<SdfNode>, b: Box<SdfNode> },
+    SmoothUnion { a: Box<SdfNode>, b: Box<SdfNode>, k: f32 },
+    // Rotation around y proportional to height, `rate` radians per unit
+    Twist { rate: f32, child: Box<SdfNode> },
+    // Infinite repetition, a zero period leaves that axis alone
+    Repeat { period: Vec3, child: Box<SdfNode> },
+    // Sinusoidal bumps added on top of the child surface
+    Displace { amplitude: f32, frequency: f32, child: Box<SdfNode> },
+}
+
+impl SdfNode {
+    pub fn sphere(radius: f32) -> SdfNode {
+        return SdfNode::Sphere { radius };
+    }
+    pub fn cuboid(half: Vec3) -> SdfNode {
+        return SdfNode::Cuboid { half };
+    }
+    pub fn torus(major: f32, minor: f32) -> SdfNode {
+        return SdfNode::Torus { major, minor };
+    }
+
+    pub fn translate(self, offset: Vec3) -> SdfNode {
+        return SdfNode::Translate { offset, child: Box::new(self) };
+    }
+    pub fn scale(self, factor: f32) -> SdfNode {
+        return SdfNode::Scale { factor, child: Box::new(self) };
+    }
+    pub fn union(self, other: SdfNode) -> SdfNode {
+        return SdfNode::Union { a: Box::new(self), b: Box::new(other) };
+    }
+    pub fn intersection(self, other: SdfNode) -> SdfNode {
+        return SdfNode::Intersection { a: Box::new(self), b: Box::new(other) };
+    }
+    pub fn subtract(self, other: SdfNode) -> SdfNode {
+        return SdfNode::Subtract { a: Box::new(self), b: Box::new(other) };
+    }
+    pub fn smooth_union(self, other: SdfNode, k: f32) -> SdfNode {
+        return SdfNode::SmoothUnion { a: Box::new(self), b: Box::new(other), k };
+    }
+    pub fn twist(self, rate: f32) -> SdfNode {
+        return SdfNode::Twist { rate, child: Box::new(self) };
+    }
+    pub fn repeat(self, period: Vec3) -> SdfNode {
+        return SdfNode::Repeat { period, child: Box::new(self) };
+    }
+    pub fn displace(self, amplitude: f32, frequency: f32) -> SdfNode {
+        return SdfNode::Displace { amplitude, frequency, child: Box::new(self) };
+    }
+
+    pub fn distance(&self, p: &Vec3) -> f32 {
+        return match self {
+            SdfNode::Sphere { radius } => p.length() - radius,
+            SdfNode::Cuboid { half } => {
+                let q = Vec3::new(p.x.abs() - half.x, p.y.abs() - half.y, p.z.abs() - half.z);
+                let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).length();
+                outside + q.x.max(q.y.max(q.z)).min(0.0)
+            }
+            SdfNode::Torus { major, minor } => {
+                let ring = (p.x * p.x + p.z * p.z).sqrt() - major;
+                (ring * ring + p.y * p.y).sqrt() - minor
+            }
+            SdfNode::Translate { offset, child } => child.distance(&(*p - *offset)),
+            SdfNode::Scale { factor, child } => child.distance(&(*p / *factor)) * factor,
+            SdfNode::Union { a, b } => a.distance(p).min(b.distance(p)),
+            SdfNode::Intersection { a, b } => a.distance(p).max(b.distance(p)),
+            SdfNode::Subtract { a, b } => a.distance(p).max(-b.distance(p)),
+            SdfNode::SmoothUnion { a, b, k } => {
+                // Polynomial smooth minimum (Quilez)
+                let da = a.distance(p);
+                let db = b.distance(p);
+                let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+                db + (da - db) * h - k * h * (1.0 - h)
+            }
+            SdfNode::Twist { rate, child } => {
+                let (s, c) = (rate * p.y).sin_cos();
+                child.distance(&Vec3::new(c * p.x - s * p.z, p.y, s * p.x + c * p.z))
+            }
+            SdfNode::Repeat { period, child } => {
+                let wrap = |v: f32, period: f32| if period > 0.0 { v - period * (v / period).round() } else { v };
+                child.distance(&Vec3::new(wrap(p.x, period.x), wrap(p.y, period.y), wrap(p.z, period.z)))
+            }
+            SdfNode::Displace { amplitude, frequency, child } => {
+                child.distance(p) + amplitude * (frequency * p.x).sin() * (frequency * p.y).sin() * (frequency * p.z).sin()
+            }
+        };
+    }
+}
+
+// Sphere traced SDF, clipped to `bound`. Twist and displacement stretch distances,
+// so lower `step_scale` below 1 for those to avoid stepping through the surface.
+pub struct SdfShape {
+    pub root: SdfNode,
+    pub bound: AABB,
+    pub material: i64,
+    pub step_scale: f32,
+    pub epsilon: f32,
+    pub max_steps: u32,
+}
+
+impl SdfShape {
+    pub fn new_box(root: SdfNode, bound: AABB, step_scale: f32, material: &i64) -> Box<SdfShape> {
+        return Box::new(SdfShape {
+            root,
+            bound,
+            material: material.clone(),
+            step_scale,
+            epsilon: 1e-4,
+            max_steps: 512,
+        });
+    }
+
+    // Tetrahedral central differences, four evaluations instead of six
+    pub fn normal(&self, p: &Vec3) -> Vec3 {
+        let h = self.epsilon * 2.0;
+        let k0 = Vec3::new(1.0, -1.0, -1.0);
+        let k1 = Vec3::new(-1.0, -1.0, 1.0);
+        let k2 = Vec3::new(-1.0, 1.0, -1.0);
+        let k3 = Vec3::new(1.0, 1.0, 1.0);
+        return (k0 * self.root.distance(&(*p + k0 * h))
+            + k1 * self.root.distance(&(*p + k1 * h))
+            + k2 * self.root.distance(&(*p + k2 * h))
+            + k3 * self.root.distance(&(*p + k3 * h)))
+            .unit_vector();
+    }
+}
+
+impl Hittable for SdfShape {
+    fn hit(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        let (t0, t1) = match self.bound.hit_range(ray, trace_len_min, trace_len_max) {
+            Some(range) => range,
+            None => return false,
+        };
+        let dir_len = ray.dir.length();
+        let mut t = t0;
+
+        // March on the side of the surface the ray starts on. A ray leaving the surface
+        // (after a bounce) has ~0 distance, so take the side from the normal and step off it.
+        let start = self.root.distance(&ray.at(t));
+        let side = if start.abs() < self.epsilon {
+            t += 2.0 * self.epsilon / dir_len;
+            if self.normal(&ray.at(t)).dot_prod(ray.dir) > 0.0 { 1.0 } else { -1.0 }
+        } else {
+            start.signum()
+        };
+
+        for _ in 0..self.max_steps {
+            if t > t1 {
+                return false;
+            }
+            let dist = side * self.root.distance(&ray.at(t));
+            if dist < self.epsilon {
+                rec.trace_len = t;
+                rec.point = ray.at(t);
+                let outward_normal = self.normal(&rec.point);
+                rec.set_face_normal(ray, &outward_normal);
+                rec.material = self.material;
+                rec.tex_u = 0.5 + f32::atan2(outward_normal.z, outward_normal.x) / (2.0 * PI);
+                rec.tex_v = 0.5 + outward_normal.y.clamp(-1.0, 1.0).asin() / PI;
+                return true;
+            }
+            t += dist * self.step_scale / dir_len;
+        }
+        return false;
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        output_box.min = self.bound.min;
+        output_box.max = self.bound.max;
+        return true;
+    }
+}
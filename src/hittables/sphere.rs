@@ -1,6 +1,7 @@
-use std::{f32::consts::PI, simd::f32x2};
+use std::{f32::consts::PI, simd::{f32x2, cmp::SimdPartialOrd, num::SimdFloat, Select, StdFloat}};
 
-use crate::{vec3::Vec3, hittable::Hittable, hittable::HitRecord, ray::Ray, aabb::AABB};
+use crate::{vec3::Vec3, hittable::{Crossings, Hittable}, hittable::HitRecord, ray::Ray, aabb::AABB};
+use crate::packet::{self, PacketHit, RayPacket, Wide, WideMask};
 
 #[derive(Clone, Copy)]
 pub struct Sphere {
@@ -11,6 +12,7 @@ pub struct Sphere {
 
 impl Hittable for Sphere {
     fn hit(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
         let bbox = AABB {
             min: self.center - Vec3::new(self.radius, self.radius, self.radius),
             max: self.center + Vec3::new(self.radius, self.radius, self.radius)
@@ -32,32 +34,111 @@ impl Hittable for Sphere {
 
         let mut root = (-b_half - sqrt_disc) / a;
         if root <  trace_len_min || trace_len_max < root {
-            root = (-b_half - sqrt_disc) / a;
+            root = (-b_half + sqrt_disc) / a;
             if root <  trace_len_min || trace_len_max < root {
                 return false
             };
         };
 
-        rec.trace_len = root;
-        rec.point = ray.at(rec.trace_len);
-        let outward_normal = (rec.point - self.center) / self.radius;
-        rec.set_face_normal(&ray, &outward_normal);
-        rec.material = self.material;
+        self.fill(ray, root, rec);
+        return true;
+    }
 
-        let theta = f32::acos(-outward_normal.y);
-        let phi = f32::atan2(-outward_normal.z, -outward_normal.x);
+    // Both roots, the ray enters at the near one and leaves at the far one
+    fn hit_all(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, out: &mut Crossings) {
+        crate::stats::count_primitive_test();
+        let oc = ray.orig - self.center;
+        let a = ray.dir.length_squared();
+        let b_half = oc.dot_prod(ray.dir);
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = b_half * b_half - a * c;
+        if discriminant < 0.0 {
+            return;
+        }
+        let sqrt_disc = f32::sqrt(discriminant);
+        for root in [(-b_half - sqrt_disc) / a, (-b_half + sqrt_disc) / a] {
+            if trace_len_min <= root && root <= trace_len_max {
+                let mut rec = HitRecord::default();
+                self.fill(ray, root, &mut rec);
+                out.push(rec);
+            }
+        }
+    }
 
-        let tmp = f32x2::from_array([phi, theta]) / f32x2::from_array([2.0*PI, PI]);
+    // hit() for all lanes at once. The box test and the roots are the same arithmetic, so every lane
+    // agrees with the single ray test, only the texture angles come from the wide polynomials.
+    fn hit_packet(&self, packet: &RayPacket, lanes: WideMask, trace_len_min: f32, hits: &mut PacketHit) -> WideMask {
+        crate::stats::count_primitive_tests(lanes.to_bitmask().count_ones() as u64);
+        let bbox = AABB {
+            min: self.center - Vec3::new(self.radius, self.radius, self.radius),
+            max: self.center + Vec3::new(self.radius, self.radius, self.radius)
+        };
+        let lanes = bbox.hit_packet(packet, lanes, trace_len_min, hits.trace_len);
+        if !lanes.any() {
+            return lanes;
+        }
 
-        rec.tex_u = tmp[0]; //phi / (2.0 * PI);
-        rec.tex_v = tmp[1]; //theta / PI;
+        let center = packet::splat(&self.center);
+        let oc = [packet.orig[0] - center[0], packet.orig[1] - center[1], packet.orig[2] - center[2]];
+        let a = packet::dot(&packet.dir, &packet.dir);
+        let b_half = packet::dot(&oc, &packet.dir);
+        let c = packet::dot(&oc, &oc) - Wide::splat(self.radius * self.radius);
 
-        return true;
+        let discriminant = b_half * b_half - a * c;
+        let sqrt_disc = discriminant.simd_max(Wide::splat(0.0)).sqrt();
+        let near = (-b_half - sqrt_disc) / a;
+        let far = (-b_half + sqrt_disc) / a;
+        let (t_min, t_max) = (Wide::splat(trace_len_min), hits.trace_len);
+        let near_in = near.simd_ge(t_min) & near.simd_le(t_max);
+        let far_in = far.simd_ge(t_min) & far.simd_le(t_max);
+        let found = lanes & discriminant.simd_ge(Wide::splat(0.0)) & (near_in | far_in);
+        if !found.any() {
+            return found;
+        }
+
+        // fill() a lane at a time
+        let root = near_in.select(near, far);
+        let point: [Wide; 3] = std::array::from_fn(|i| packet.orig[i] + root * packet.dir[i]);
+        let n: [Wide; 3] = std::array::from_fn(|i| (point[i] - center[i]) / Wide::splat(self.radius));
+        let front_face = packet::dot(&packet.dir, &n).simd_lt(Wide::splat(0.0));
+        let normal = n.map(|c| front_face.select(c, -c));
+
+        let theta = packet::acos(-n[1]);
+        let phi = packet::atan2(-n[2], -n[0]);
+        let tex_u = phi / Wide::splat(2.0 * PI);
+        let tex_v = theta / Wide::splat(PI);
+
+        let sin_theta = (Wide::splat(1.0) - n[1] * n[1]).simd_max(Wide::splat(0.0)).sqrt().simd_max(Wide::splat(1e-6));
+        let (du, dv) = (Wide::splat(2.0 * PI * self.radius), Wide::splat(PI * self.radius));
+        let dpdu = [du * -n[2], Wide::splat(0.0), du * n[0]];
+        let dpdv = [dv * (-n[0] * n[1] / sin_theta), dv * sin_theta, dv * (-n[2] * n[1] / sin_theta)];
+
+        for lane in 0..packet::LANES {
+            if !found.test(lane) {
+                continue;
+            }
+            let normal = packet::lane(&normal, lane);
+            hits.set(lane, HitRecord {
+                point: packet::lane(&point, lane),
+                normal,
+                shading_normal: normal,
+                dpdu: packet::lane(&dpdu, lane),
+                dpdv: packet::lane(&dpdv, lane),
+                trace_len: root[lane],
+                front_face: front_face.test(lane),
+                material: self.material,
+                tex_u: tex_u[lane],
+                tex_v: tex_v[lane],
+                ..HitRecord::default()
+            });
+        }
+        return found;
     }
 
     fn bounds(&self, output_box: &mut crate::aabb::AABB) -> bool {
-        output_box.max = self.center - Vec3::new(self.radius, self.radius, self.radius);
-        output_box.min = self.center + Vec3::new(self.radius, self.radius, self.radius);
+        let r = self.radius.abs();
+        output_box.min = self.center - Vec3::new(r, r, r);
+        output_box.max = self.center + Vec3::new(r, r, r);
         return true;
     }
 }
@@ -78,4 +159,27 @@ impl Sphere {
             material: material.clone()
         })
     }
+
+    // The hit record for the point at trace length `root`
+    fn fill(&self, ray: &Ray, root: f32, rec: &mut HitRecord) {
+        rec.trace_len = root;
+        rec.point = ray.at(rec.trace_len);
+        let outward_normal = (rec.point - self.center) / self.radius;
+        rec.set_face_normal(&ray, &outward_normal);
+        rec.material = self.material;
+
+        let theta = f32::acos(-outward_normal.y);
+        let phi = f32::atan2(-outward_normal.z, -outward_normal.x);
+
+        let tmp = f32x2::from_array([phi, theta]) / f32x2::from_array([2.0*PI, PI]);
+
+        rec.tex_u = tmp[0]; //phi / (2.0 * PI);
+        rec.tex_v = tmp[1]; //theta / PI;
+
+        // Derivatives of the point along phi and theta scaled to u and v, sin(theta) is the ring radius
+        let n = outward_normal;
+        let sin_theta = (1.0 - n.y * n.y).max(0.0).sqrt().max(1e-6);
+        rec.dpdu = 2.0 * PI * self.radius * Vec3::new(-n.z, 0.0, n.x);
+        rec.dpdv = PI * self.radius * Vec3::new(-n.x * n.y / sin_theta, sin_theta, -n.z * n.y / sin_theta);
+    }
 }
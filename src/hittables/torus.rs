@@ -0,0 +1,212 @@
+use std::f32::consts::PI;
+
+use crate::{vec3::Vec3, hittable::Hittable, hittable::HitRecord, ray::Ray, aabb::AABB};
+
+// Torus around the y axis through `center`, tube of radius `minor` swept along a circle of radius `major`
+#[derive(Clone, Copy)]
+pub struct Torus {
+    pub center: Vec3,
+    pub major: f32,
+    pub minor: f32,
+    pub material: i64
+}
+
+impl Torus {
+    pub fn new_box(center: Vec3, major: f32, minor: f32, material: &i64) -> Box<Torus> {
+        return Box::new(Torus { center, major, minor, material: material.clone() })
+    }
+
+    fn bound(&self) -> AABB {
+        let extent = Vec3::new(self.major + self.minor, self.minor, self.major + self.minor);
+        return AABB { min: self.center - extent, max: self.center + extent };
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, ray: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        // Start the quartic at the bounding box to keep the coefficients small, and work on a unit direction
+        let (t_start, _) = match self.bound().hit_range(ray, trace_len_min, trace_len_max) {
+            Some(range) => range,
+            None => return false,
+        };
+        let dir_len = ray.dir.length() as f64;
+        let o = ray.at(t_start) - self.center;
+        let (ox, oy, oz) = (o.x as f64, o.y as f64, o.z as f64);
+        let (dx, dy, dz) = (ray.dir.x as f64 / dir_len, ray.dir.y as f64 / dir_len, ray.dir.z as f64 / dir_len);
+        let big_r2 = (self.major as f64) * (self.major as f64);
+        let small_r2 = (self.minor as f64) * (self.minor as f64);
+
+        let e = ox * ox + oy * oy + oz * oz - big_r2 - small_r2;
+        let f = ox * dx + oy * dy + oz * dz;
+        let four_r2 = 4.0 * big_r2;
+        let coeffs = [
+            e * e - four_r2 * (small_r2 - oy * oy),
+            4.0 * f * e + 2.0 * four_r2 * oy * dy,
+            2.0 * e + 4.0 * f * f + four_r2 * dy * dy,
+            4.0 * f,
+            1.0,
+        ];
+
+        let mut closest = f64::INFINITY;
+        for root in solve_quartic(&coeffs) {
+            let root = polish_root(&coeffs, root);
+            let t = t_start as f64 + root / dir_len;
+            if t >= trace_len_min as f64 && t <= trace_len_max as f64 && t < closest {
+                closest = t;
+            }
+        }
+        if closest == f64::INFINITY {
+            return false;
+        }
+
+        rec.trace_len = closest as f32;
+        rec.point = ray.at(rec.trace_len);
+        let p = rec.point - self.center;
+        let sum_squared = p.length_squared();
+        let param = self.major * self.major + self.minor * self.minor;
+        let outward_normal = Vec3::new(
+            p.x * (sum_squared - param),
+            p.y * (sum_squared - param + 2.0 * self.major * self.major),
+            p.z * (sum_squared - param),
+        ).unit_vector();
+        rec.set_face_normal(ray, &outward_normal);
+        rec.material = self.material;
+
+        // u around the ring, v around the tube
+        let ring = (p.x * p.x + p.z * p.z).sqrt() - self.major;
+        rec.tex_u = (f32::atan2(-p.z, p.x) + PI) / (2.0 * PI);
+        rec.tex_v = (f32::atan2(p.y, ring) + PI) / (2.0 * PI);
+        return true;
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        let bound = self.bound();
+        output_box.min = bound.min;
+        output_box.max = bound.max;
+        return true;
+    }
+}
+
+// Closed form polynomial roots after Schwarze, "Cubic and Quartic Roots" (Graphics Gems I).
+// Coefficients are lowest order first.
+const EQN_EPS: f64 = 1e-9;
+
+fn is_zero(x: f64) -> bool {
+    return x > -EQN_EPS && x < EQN_EPS;
+}
+
+fn solve_quadric(c: &[f64; 3]) -> Vec<f64> {
+    let p = c[1] / (2.0 * c[2]);
+    let q = c[0] / c[2];
+    let d = p * p - q;
+
+    if is_zero(d) {
+        return vec![-p];
+    } else if d < 0.0 {
+        return vec![];
+    }
+    let sqrt_d = d.sqrt();
+    return vec![sqrt_d - p, -sqrt_d - p];
+}
+
+fn solve_cubic(c: &[f64; 4]) -> Vec<f64> {
+    let a = c[2] / c[3];
+    let b = c[1] / c[3];
+    let cc = c[0] / c[3];
+
+    // Substitute x = y - a/3 to eliminate the quadric term: y^3 + 3py + 2q = 0
+    let sq_a = a * a;
+    let p = 1.0 / 3.0 * (-1.0 / 3.0 * sq_a + b);
+    let q = 1.0 / 2.0 * (2.0 / 27.0 * a * sq_a - 1.0 / 3.0 * a * b + cc);
+    let cb_p = p * p * p;
+    let d = q * q + cb_p;
+
+    let mut roots = if is_zero(d) {
+        if is_zero(q) {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if d < 0.0 {
+        let phi = 1.0 / 3.0 * (-q / (-cb_p).sqrt()).acos();
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + std::f64::consts::PI / 3.0).cos(),
+            -t * (phi - std::f64::consts::PI / 3.0).cos(),
+        ]
+    } else {
+        let sqrt_d = d.sqrt();
+        vec![(sqrt_d - q).cbrt() - (sqrt_d + q).cbrt()]
+    };
+
+    let sub = 1.0 / 3.0 * a;
+    for root in roots.iter_mut() {
+        *root -= sub;
+    }
+    return roots;
+}
+
+fn solve_quartic(c: &[f64; 5]) -> Vec<f64> {
+    let a = c[3] / c[4];
+    let b = c[2] / c[4];
+    let cc = c[1] / c[4];
+    let d = c[0] / c[4];
+
+    // Substitute x = y - a/4 to eliminate the cubic term: y^4 + py^2 + qy + r = 0
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = 1.0 / 8.0 * sq_a * a - 1.0 / 2.0 * a * b + cc;
+    let r = -3.0 / 256.0 * sq_a * sq_a + 1.0 / 16.0 * sq_a * b - 1.0 / 4.0 * a * cc + d;
+
+    let mut roots;
+    if is_zero(r) {
+        // y(y^3 + py + q) = 0
+        roots = solve_cubic(&[q, p, 0.0, 1.0]);
+        roots.push(0.0);
+    } else {
+        // Take one root of the resolvent cubic to split into two quadrics
+        let z = solve_cubic(&[1.0 / 2.0 * r * p - 1.0 / 8.0 * q * q, -r, -1.0 / 2.0 * p, 1.0])[0];
+
+        let mut u = z * z - r;
+        let mut v = 2.0 * z - p;
+        if is_zero(u) {
+            u = 0.0;
+        } else if u > 0.0 {
+            u = u.sqrt();
+        } else {
+            return vec![];
+        }
+        if is_zero(v) {
+            v = 0.0;
+        } else if v > 0.0 {
+            v = v.sqrt();
+        } else {
+            return vec![];
+        }
+
+        roots = solve_quadric(&[z - u, if q < 0.0 { -v } else { v }, 1.0]);
+        roots.append(&mut solve_quadric(&[z + u, if q < 0.0 { v } else { -v }, 1.0]));
+    }
+
+    let sub = 1.0 / 4.0 * a;
+    for root in roots.iter_mut() {
+        *root -= sub;
+    }
+    return roots;
+}
+
+// A couple of Newton steps to clean up the cancellation error of the closed form
+fn polish_root(c: &[f64; 5], mut x: f64) -> f64 {
+    for _ in 0..2 {
+        let f = (((c[4] * x + c[3]) * x + c[2]) * x + c[1]) * x + c[0];
+        let df = ((4.0 * c[4] * x + 3.0 * c[3]) * x + 2.0 * c[2]) * x + c[1];
+        if df.abs() < 1e-12 {
+            break;
+        }
+        x -= f / df;
+    }
+    return x;
+}
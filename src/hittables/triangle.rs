@@ -1,16 +1,47 @@
-use std::f32::EPSILON;
+use std::simd::{cmp::{SimdPartialEq, SimdPartialOrd}, num::SimdFloat, Select};
+
 use crate::{vec3::Vec3, hittable::Hittable, hittable::HitRecord, ray::Ray};
+use crate::packet::{self, PacketHit, RayPacket, Wide, WideMask};
 
 #[derive(Clone, Copy)]
 pub struct Triangle {
     pub v0: Vec3,
     pub v1: Vec3,
     pub v2: Vec3,
+    // Per vertex normals for smooth shading, the flat face normal when None
+    pub normals: Option<[Vec3; 3]>,
+    // Single sided triangles only report hits from the side v0, v1, v2 wind counter clockwise
+    pub double_sided: bool,
     pub material: i64
 }
 
+impl Triangle {
+    pub fn new_box(v0: Vec3, v1: Vec3, v2: Vec3, material: &i64) -> Box<Triangle> {
+        return Box::new(Triangle { v0, v1, v2, normals: None, double_sided: true, material: material.clone() });
+    }
+
+    pub fn new_smooth_box(v0: Vec3, v1: Vec3, v2: Vec3, normals: [Vec3; 3], material: &i64) -> Box<Triangle> {
+        return Box::new(Triangle { v0, v1, v2, normals: Some(normals), double_sided: true, material: material.clone() });
+    }
+
+    // The hit record at trace length `t` and barycentrics u, v of v1 and v2
+    fn fill(&self, r: &Ray, t: f32, u: f32, v: f32, rec: &mut HitRecord) {
+        rec.tex_u = u;
+        rec.tex_v = v;
+        rec.trace_len = t;
+        rec.point = r.at(t);
+        rec.material = self.material;
+        let shading = self.normals.map(|[n0, n1, n2]| (1.0 - u - v) * n0 + u * n1 + v * n2);
+        set_normals(rec, r, &(self.v1 - self.v0).cross_prod(self.v2 - self.v0), shading);
+        // u, v are the barycentrics, so the edges are the derivatives
+        rec.dpdu = self.v1 - self.v0;
+        rec.dpdv = self.v2 - self.v0;
+    }
+}
+
 impl Hittable for Triangle {
     fn hit(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool { // https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
+        crate::stats::count_primitive_test();
         //let edge1 = self.v1 - self.v0;
         //let edge2 = self.v2 - self.v0;
         //let h = r.dir.cross_prod(edge2);
@@ -65,39 +96,212 @@ impl Hittable for Triangle {
         //let t = d*((-n).dot_prod(rov0));
         //if (u < 0.0 || v < 0.0 || (u+v) > 1.0) {return false;};
 
-        let v0v1 = self.v1 - self.v0;
-        let v0v2 = self.v2 - self.v0;
-        let pvec = r.dir.cross_prod(v0v2);
-        let det = v0v1.dot_prod(pvec);
-        if det < f32::EPSILON {
-                return false;
+        let (t, u, v) = match intersect(&self.v0, &self.v1, &self.v2, r, trace_len_min, trace_len_max, self.double_sided) {
+            Some(hit) => hit,
+            None => return false,
         };
-        let idet = 1.0/det;
-        let tvec = r.orig - self.v0;
-        let u = tvec.dot_prod(pvec) * idet;
-        if u < 0.0 || u > 1.0 {
-                return false;
-        }
-        let qvec = tvec.cross_prod(v0v1);
-        let v = r.dir.dot_prod(qvec) * idet;
-        if v < 0.0 || u+v > 1.0 {
-                return false;
-        }
-        let t = v0v2.dot_prod(qvec) * idet;
-        if t < trace_len_min || trace_len_max < t {
-                return false;
-        }
 
-        rec.tex_u = u;
-        rec.tex_v = v;
-        rec.trace_len = t;
-        rec.set_face_normal(r, &v0v1.cross_prod(v0v2));
-        rec.material = self.material;
-        rec.point = r.at(t);
+        self.fill(r, t, u, v, rec);
         return true;
     }
 
+    fn hit_packet(&self, packet: &RayPacket, lanes: WideMask, trace_len_min: f32, hits: &mut PacketHit) -> WideMask {
+        return hit_packet(self, [&self.v0, &self.v1, &self.v2], self.double_sided, packet, lanes, trace_len_min, hits, |r, t, u, v, rec| {
+            self.fill(r, t, u, v, rec)
+        });
+    }
+
     fn bounds(&self, output_box: &mut crate::aabb::AABB) -> bool {
-        return false;
+        // Padded so axis aligned triangles don't produce a zero width slab
+        let pad = Vec3::new(1e-4, 1e-4, 1e-4);
+        output_box.min = Vec3::new(
+            self.v0.x.min(self.v1.x).min(self.v2.x),
+            self.v0.y.min(self.v1.y).min(self.v2.y),
+            self.v0.z.min(self.v1.z).min(self.v2.z)
+        ) - pad;
+        output_box.max = Vec3::new(
+            self.v0.x.max(self.v1.x).max(self.v2.x),
+            self.v0.y.max(self.v1.y).max(self.v2.y),
+            self.v0.z.max(self.v1.z).max(self.v2.z)
+        ) + pad;
+        return true;
+    }
+}
+
+// The geometric normal stays in `normal` and decides the front face, so it agrees with the winding
+// the intersection saw. The shading normal is turned into the geometric hemisphere first, which keeps
+// files whose normals disagree with their winding from shading inside out.
+pub fn set_normals(rec: &mut HitRecord, r: &Ray, geometric: &Vec3, shading: Option<Vec3>) {
+    let geometric = geometric.unit_vector();
+    rec.set_face_normal(r, &geometric);
+    if let Some(shading) = shading {
+        let mut n = shading.unit_vector();
+        if n.dot_prod(geometric) < 0.0 {
+            n = -n;
+        }
+        rec.shading_normal = if rec.front_face { n } else { -n };
+    }
+}
+
+// dp/du and dp/dv from the texture coordinates at the corners, solving
+// e1 = du1 dpdu + dv1 dpdv and e2 = du2 dpdu + dv2 dpdv. Falls back to the edges for degenerate UVs.
+pub fn uv_derivatives(p: [Vec3; 3], uv: [[f32; 2]; 3]) -> (Vec3, Vec3) {
+    let (e1, e2) = (p[1] - p[0], p[2] - p[0]);
+    let (du1, dv1) = (uv[1][0] - uv[0][0], uv[1][1] - uv[0][1]);
+    let (du2, dv2) = (uv[2][0] - uv[0][0], uv[2][1] - uv[0][1]);
+    let det = du1 * dv2 - dv1 * du2;
+    if det.abs() < 1e-12 {
+        return (e1, e2);
+    }
+    let inv = 1.0 / det;
+    return ((dv2 * e1 - dv1 * e2) * inv, (du1 * e2 - du2 * e1) * inv);
+}
+
+// hit_packet() of either kind of triangle. The lanes intersect_packet() settles get their record from
+// `fill`, the few it leaves to the single ray test go through `obj.hit()`.
+#[allow(clippy::too_many_arguments)]
+pub fn hit_packet<H: Hittable + ?Sized>(
+    obj: &H,
+    [v0, v1, v2]: [&Vec3; 3],
+    double_sided: bool,
+    packet: &RayPacket,
+    lanes: WideMask,
+    trace_len_min: f32,
+    hits: &mut PacketHit,
+    fill: impl Fn(&Ray, f32, f32, f32, &mut HitRecord),
+) -> WideMask {
+    let (found, on_edge, t, u, v) = intersect_packet(v0, v1, v2, packet, lanes, trace_len_min, hits.trace_len, double_sided);
+    crate::stats::count_primitive_tests((lanes & !on_edge).to_bitmask().count_ones() as u64);
+    for lane in 0..packet::LANES {
+        if found.test(lane) {
+            let mut rec = HitRecord::default();
+            fill(&packet.rays[lane], t[lane], u[lane], v[lane], &mut rec);
+            hits.set(lane, rec);
+        }
+    }
+    if !on_edge.any() {
+        return found;
     }
-}
\ No newline at end of file
+    return found | packet::hit_lanes(obj, packet, on_edge, trace_len_min, hits);
+}
+
+// intersect() for all lanes of a packet at once, each lane with its own axes, and the same arithmetic
+// so the hits agree bit for bit. Returns the lanes that hit with their (trace_len, u, v), and the lanes
+// that landed exactly on an edge, which intersect() settles in double precision and so are left to it.
+#[allow(clippy::too_many_arguments)]
+pub fn intersect_packet(
+    v0: &Vec3,
+    v1: &Vec3,
+    v2: &Vec3,
+    packet: &RayPacket,
+    lanes: WideMask,
+    trace_len_min: f32,
+    trace_len_max: Wide,
+    double_sided: bool,
+) -> (WideMask, WideMask, Wide, Wide, Wide) {
+    let zero = Wide::splat(0.0);
+    let abs = packet.dir.map(|d| d.abs());
+    let x_over_y = abs[0].simd_gt(abs[1]);
+    let kz_x = x_over_y & abs[0].simd_gt(abs[2]);
+    let kz_y = !x_over_y & abs[1].simd_gt(abs[2]);
+    // Component (kz + k) % 3 of every lane
+    let pick = |v: &[Wide; 3], k: usize| kz_x.select(v[k % 3], kz_y.select(v[(k + 1) % 3], v[(k + 2) % 3]));
+    let backwards = pick(&packet.dir, 0).simd_lt(zero);
+    // [x, y, z] in the ray's frame
+    let axes = |v: &[Wide; 3]| [backwards.select(pick(v, 2), pick(v, 1)), backwards.select(pick(v, 1), pick(v, 2)), pick(v, 0)];
+
+    let dir = axes(&packet.dir);
+    let sx = dir[0] / dir[2];
+    let sy = dir[1] / dir[2];
+    let sz = Wide::splat(1.0) / dir[2];
+
+    let rel = |v: &Vec3| {
+        let p = packet::splat(v);
+        return axes(&[p[0] - packet.orig[0], p[1] - packet.orig[1], p[2] - packet.orig[2]]);
+    };
+    let (a, b, c) = (rel(v0), rel(v1), rel(v2));
+    let (ax, ay) = (a[0] - sx * a[2], a[1] - sy * a[2]);
+    let (bx, by) = (b[0] - sx * b[2], b[1] - sy * b[2]);
+    let (cx, cy) = (c[0] - sx * c[2], c[1] - sy * c[2]);
+
+    let e0 = cx * by - cy * bx;
+    let e1 = ax * cy - ay * cx;
+    let e2 = bx * ay - by * ax;
+    let on_edge = lanes & (e0.simd_eq(zero) | e1.simd_eq(zero) | e2.simd_eq(zero));
+    let outside = (e0.simd_lt(zero) | e1.simd_lt(zero) | e2.simd_lt(zero)) & (e0.simd_gt(zero) | e1.simd_gt(zero) | e2.simd_gt(zero));
+
+    let det = e0 + e1 + e2;
+    let facing = if double_sided { det.simd_ne(zero) } else { det.simd_gt(zero) };
+    let t_scaled = e0 * sz * a[2] + e1 * sz * b[2] + e2 * sz * c[2];
+    let t = t_scaled / det;
+    let in_range = t.simd_ge(Wide::splat(trace_len_min)) & t.simd_le(trace_len_max);
+    let found = lanes & !on_edge & !outside & facing & in_range;
+    return (found, on_edge, t, e1 / det, e2 / det);
+}
+
+// Watertight ray/triangle test (Woop, Benthin, Wald 2013). The vertices are moved into a space
+// where the ray runs along +z from the origin, so the edge tests for a shared edge are computed
+// from the same numbers in both triangles and a ray can't slip between them.
+// Returns (trace_len, u, v) with u, v the barycentrics of v1 and v2.
+pub fn intersect(
+    v0: &Vec3,
+    v1: &Vec3,
+    v2: &Vec3,
+    r: &Ray,
+    trace_len_min: f32,
+    trace_len_max: f32,
+    double_sided: bool,
+) -> Option<(f32, f32, f32)> {
+    let dir = [r.dir.x, r.dir.y, r.dir.z];
+    let kz = if dir[0].abs() > dir[1].abs() {
+        if dir[0].abs() > dir[2].abs() { 0 } else { 2 }
+    } else if dir[1].abs() > dir[2].abs() {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    // Keep the winding when the dominant axis points backwards
+    if dir[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+    let sx = dir[kx] / dir[kz];
+    let sy = dir[ky] / dir[kz];
+    let sz = 1.0 / dir[kz];
+
+    let rel = |v: &Vec3| {
+        let p = *v - r.orig;
+        return [p.x, p.y, p.z];
+    };
+    let (a, b, c) = (rel(v0), rel(v1), rel(v2));
+    let (ax, ay) = (a[kx] - sx * a[kz], a[ky] - sy * a[kz]);
+    let (bx, by) = (b[kx] - sx * b[kz], b[ky] - sy * b[kz]);
+    let (cx, cy) = (c[kx] - sx * c[kz], c[ky] - sy * c[kz]);
+
+    let mut e0 = cx * by - cy * bx;
+    let mut e1 = ax * cy - ay * cx;
+    let mut e2 = bx * ay - by * ax;
+    // Exactly on an edge, redo the edge functions in double precision
+    if e0 == 0.0 || e1 == 0.0 || e2 == 0.0 {
+        e0 = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+        e1 = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+        e2 = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+    }
+
+    if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+        return None;
+    }
+    let det = e0 + e1 + e2;
+    // A positive determinant is a front face hit
+    if det == 0.0 || (!double_sided && det < 0.0) {
+        return None;
+    }
+
+    let t_scaled = e0 * sz * a[kz] + e1 * sz * b[kz] + e2 * sz * c[kz];
+    let t = t_scaled / det;
+    if t < trace_len_min || trace_len_max < t {
+        return None;
+    }
+    return Some((t, e1 / det, e2 / det));
+}
@@ -0,0 +1,112 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    rand_double::rand_double,
+    ray::Ray,
+    vec3::Vec3,
+    voxel_grid::VoxelGrid,
+};
+
+// hit() has no sampler, so free flight sampling draws from a stream keyed by the scene seed and the
+// ray. The same ray gets the same collisions whichever thread or integrator traces it.
+static VOLUME_SEED: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_seed(seed: u64) {
+    VOLUME_SEED.store(seed, Ordering::Relaxed);
+}
+
+fn ray_rng(r: &Ray) -> ChaCha20Rng {
+    let mut key: u64 = 0;
+    for v in [r.orig.x, r.orig.y, r.orig.z, r.dir.x, r.dir.y, r.dir.z, r.time] {
+        key = (key ^ v.to_bits() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(29);
+    }
+    let mut rng = ChaCha20Rng::seed_from_u64(VOLUME_SEED.load(Ordering::Relaxed));
+    rng.set_stream(key);
+    return rng;
+}
+
+// Heterogeneous participating medium filling `bound`, material should be a phase function (IsotropicMat)
+pub struct VoxelVolume {
+    pub grid: Arc<VoxelGrid>,
+    pub bound: AABB,
+    pub density_scale: f32,
+    pub material: i64,
+}
+
+impl VoxelVolume {
+    pub fn new_box(grid: Arc<VoxelGrid>, bound: AABB, density_scale: f32, material: &i64) -> Box<VoxelVolume> {
+        return Box::new(VoxelVolume {
+            grid,
+            bound,
+            density_scale,
+            material: material.clone(),
+        });
+    }
+
+    // Extinction coefficient at a world space point
+    pub fn sigma_t(&self, p: &Vec3) -> f32 {
+        let local = (*p - self.bound.min) / (self.bound.max - self.bound.min);
+        return self.grid.sample(&local) * self.density_scale;
+    }
+
+    fn majorant(&self) -> f32 {
+        return self.grid.max_density * self.density_scale;
+    }
+}
+
+impl Hittable for VoxelVolume {
+    // Delta tracking: step through the majorant medium and accept real collisions with p = sigma_t / majorant
+    fn hit(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        crate::stats::count_primitive_test();
+        let majorant = self.majorant();
+        let (t0, t1) = match self.bound.hit_range(r, trace_len_min, trace_len_max) {
+            Some(range) => range,
+            None => return false,
+        };
+        if majorant <= 0.0 {
+            return false;
+        }
+
+        let inv_step = 1.0 / (majorant * r.dir.length());
+        let mut rng = ray_rng(r);
+        let mut t = t0;
+        let collision = loop {
+            t -= (1.0 - rand_double(&mut rng)).ln() * inv_step;
+            if t >= t1 {
+                break None;
+            }
+            if self.sigma_t(&r.at(t)) / majorant > rand_double(&mut rng) {
+                break Some(t);
+            }
+        };
+
+        return match collision {
+            Some(t) => {
+                rec.trace_len = t;
+                rec.point = r.at(t);
+                rec.normal = Vec3::newi(1, 0, 0); // arbitrary, media have no surface
+                rec.shading_normal = rec.normal;
+                rec.front_face = true;
+                rec.material = self.material;
+                rec.tex_u = 0.0;
+                rec.tex_v = 0.0;
+                true
+            }
+            None => false,
+        };
+    }
+
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        output_box.min = self.bound.min;
+        output_box.max = self.bound.max;
+        return true;
+    }
+}
@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use crate::aabb::{WideAabb, AABB};
+use crate::hittable::{HitRecord, Hittable};
+use crate::packet::{PacketHit, RayPacket, WideMask, LANES};
+use crate::ray::Ray;
+use crate::stats;
+use crate::vec3::Vec3;
+
+// Up to this many objects share a leaf
+const LEAF_SIZE: usize = 2;
+
+// BVH with N children per node, their boxes stored side by side so one slab test covers all of them.
+// Built as a binary tree split at the median along the widest spread of centres, then collapsed by
+// pulling the children of the largest child up until a node is full. Children are visited nearest
+// first, and skipped once a closer hit has been found.
+pub struct WideBvh<const N: usize> {
+    nodes: Vec<WideNode<N>>,
+    objs: Vec<Arc<Box<dyn Hittable + Sync + Send>>>,
+    bound: AABB,
+}
+
+// As wide as the packets, 8 with AVX2 and 4 otherwise
+pub type Bvh = WideBvh<LANES>;
+#[allow(dead_code)]
+pub type Bvh4 = WideBvh<4>;
+#[allow(dead_code)]
+pub type Bvh8 = WideBvh<8>;
+
+struct WideNode<const N: usize> {
+    bounds: WideAabb<N>,
+    children: [Child; N],
+}
+
+#[derive(Clone, Copy)]
+enum Child {
+    Empty,
+    Node(u32),
+    // Range of `objs`
+    Leaf(u32, u32),
+}
+
+// The binary tree the wide one is collapsed from
+enum BuildNode {
+    Leaf(AABB, usize, usize),
+    Inner(AABB, Box<BuildNode>, Box<BuildNode>),
+}
+
+impl BuildNode {
+    fn bound(&self) -> &AABB {
+        return match self {
+            BuildNode::Leaf(b, _, _) => b,
+            BuildNode::Inner(b, _, _) => b,
+        };
+    }
+}
+
+impl<const N: usize> Hittable for WideBvh<N> {
+    fn bounds(&self, output_box: &mut AABB) -> bool {
+        *output_box = self.bound;
+        return true;
+    }
+
+    fn hit(&self, r: &Ray, trace_len_min: f32, trace_len_max: f32, rec: &mut HitRecord) -> bool {
+        if !self.bound.hit(r, trace_len_min, trace_len_max) {
+            return false;
+        }
+        let inv_dir = Vec3::splat(1.0) / r.dir;
+        let mut closest = trace_len_max;
+        return self.hit_node(0, r, &inv_dir, trace_len_min, &mut closest, rec);
+    }
+
+    fn hit_packet(&self, packet: &RayPacket, lanes: WideMask, trace_len_min: f32, hits: &mut PacketHit) -> WideMask {
+        let lanes = self.bound.hit_packet(packet, lanes, trace_len_min, hits.trace_len);
+        if !lanes.any() {
+            return lanes;
+        }
+        return self.hit_node_packet(0, packet, lanes, trace_len_min, hits);
+    }
+}
+
+impl<const N: usize> WideBvh<N> {
+    pub fn new(src_objs: &Vec<Arc<Box<dyn Hittable + Sync + Send>>>, start: usize, end: usize) -> WideBvh<N> {
+        assert!(N >= 2 && end > start, "WideBvh needs at least two lanes and one object");
+        let mut objs = src_objs[start..end].to_vec();
+        let mut boxes: Vec<AABB> = objs
+            .iter()
+            .map(|obj| {
+                let mut b = AABB { min: Vec3::newi(0, 0, 0), max: Vec3::newi(0, 0, 0) };
+                if !obj.bounds(&mut b) {
+                    panic!("No bounding box in WideBvh constructor");
+                }
+                b
+            })
+            .collect();
+
+        let root = build(&mut objs, &mut boxes, 0);
+        let mut bvh = WideBvh { nodes: vec![], objs, bound: *root.bound() };
+        match root {
+            // A single leaf still gets a node so hit() always starts at node 0
+            BuildNode::Leaf(..) => {
+                bvh.collapse(vec![root]);
+            }
+            BuildNode::Inner(_, left, right) => {
+                bvh.collapse(vec![*left, *right]);
+            }
+        }
+        return bvh;
+    }
+
+    // Widens a node's children to up to N by opening the inner child with the largest surface
+    // area, then stores the node and recurses into the inner children that are left
+    fn collapse(&mut self, mut children: Vec<BuildNode>) -> u32 {
+        while children.len() < N {
+            let widest = children
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| matches!(c, BuildNode::Inner(..)))
+                .max_by(|a, b| a.1.bound().surface_area().total_cmp(&b.1.bound().surface_area()))
+                .map(|(i, _)| i);
+            match widest {
+                Some(i) => {
+                    if let BuildNode::Inner(_, left, right) = children.swap_remove(i) {
+                        children.push(*left);
+                        children.push(*right);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        let index = self.nodes.len();
+        let boxes: Vec<AABB> = children.iter().map(|c| *c.bound()).collect();
+        self.nodes.push(WideNode { bounds: WideAabb::new(&boxes), children: [Child::Empty; N] });
+        for (lane, child) in children.into_iter().enumerate() {
+            let slot = match child {
+                BuildNode::Leaf(_, first, last) => Child::Leaf(first as u32, last as u32),
+                BuildNode::Inner(_, left, right) => Child::Node(self.collapse(vec![*left, *right])),
+            };
+            self.nodes[index].children[lane] = slot;
+        }
+        return index as u32;
+    }
+
+    fn hit_node(&self, index: u32, r: &Ray, inv_dir: &Vec3, trace_len_min: f32, closest: &mut f32, rec: &mut HitRecord) -> bool {
+        stats::count_bvh_node();
+        let node = &self.nodes[index as usize];
+        let (mask, enter) = node.bounds.hit(r, inv_dir, trace_len_min, *closest);
+        if !mask.any() {
+            return false;
+        }
+
+        // Children hit, nearest entry first
+        let mut order = [0usize; N];
+        let mut count = 0;
+        for lane in 0..N {
+            if !mask.test(lane) {
+                continue;
+            }
+            let mut i = count;
+            while i > 0 && enter[order[i - 1]] > enter[lane] {
+                order[i] = order[i - 1];
+                i -= 1;
+            }
+            order[i] = lane;
+            count += 1;
+        }
+
+        let mut hit_anything = false;
+        for &lane in &order[..count] {
+            if enter[lane] > *closest {
+                break;
+            }
+            let found = match node.children[lane] {
+                Child::Empty => false,
+                Child::Node(child) => self.hit_node(child, r, inv_dir, trace_len_min, closest, rec),
+                Child::Leaf(first, last) => {
+                    let mut found = false;
+                    let mut temp_rec = HitRecord::default();
+                    for obj in &self.objs[first as usize..last as usize] {
+                        if obj.hit(r, trace_len_min, *closest, &mut temp_rec) {
+                            *closest = temp_rec.trace_len;
+                            *rec = temp_rec;
+                            found = true;
+                        }
+                    }
+                    found
+                }
+            };
+            hit_anything |= found;
+        }
+        return hit_anything;
+    }
+
+    // Packets go through the children in lane order, each child with the rays that enter its box.
+    // All the boxes are tested up front in one go, the objects still cut every ray off at its closest hit.
+    fn hit_node_packet(&self, index: u32, packet: &RayPacket, lanes: WideMask, trace_len_min: f32, hits: &mut PacketHit) -> WideMask {
+        stats::count_bvh_node();
+        let node = &self.nodes[index as usize];
+        let entered = node.bounds.hit_packet(packet, lanes, trace_len_min, hits.trace_len);
+        let mut found = WideMask::splat(false);
+        for (child, &entered) in node.children.iter().zip(entered.iter()) {
+            if !entered.any() {
+                continue;
+            }
+            match *child {
+                Child::Node(next) => found |= self.hit_node_packet(next, packet, entered, trace_len_min, hits),
+                Child::Leaf(first, last) => {
+                    for obj in &self.objs[first as usize..last as usize] {
+                        found |= obj.hit_packet(packet, entered, trace_len_min, hits);
+                    }
+                }
+                Child::Empty => {}
+            }
+        }
+        return found;
+    }
+}
+
+// Median split along the axis the centres spread furthest on, `offset` is where `objs` starts in
+// the final list
+fn build(objs: &mut [Arc<Box<dyn Hittable + Sync + Send>>], boxes: &mut [AABB], offset: usize) -> BuildNode {
+    let bound = boxes.iter().skip(1).fold(boxes[0], |acc, b| AABB::surrounding_box(&acc, b));
+    if objs.len() <= LEAF_SIZE {
+        return BuildNode::Leaf(bound, offset, offset + objs.len());
+    }
+
+    let centre = |b: &AABB| (b.min + b.max) * 0.5;
+    let (mut lo, mut hi) = (centre(&boxes[0]), centre(&boxes[0]));
+    for b in boxes.iter() {
+        lo = lo.min(centre(b));
+        hi = hi.max(centre(b));
+    }
+    let spread = hi - lo;
+    let axis = if spread.x > spread.y && spread.x > spread.z { 0 } else if spread.y > spread.z { 1 } else { 2 };
+
+    let mut order: Vec<usize> = (0..objs.len()).collect();
+    order.sort_unstable_by(|&a, &b| centre(&boxes[a]).idx(axis).total_cmp(&centre(&boxes[b]).idx(axis)));
+    let sorted_objs: Vec<_> = order.iter().map(|&i| objs[i].clone()).collect();
+    let sorted_boxes: Vec<_> = order.iter().map(|&i| boxes[i]).collect();
+    objs.clone_from_slice(&sorted_objs);
+    boxes.copy_from_slice(&sorted_boxes);
+
+    let mid = objs.len() / 2;
+    let (lower_objs, upper_objs) = objs.split_at_mut(mid);
+    let (lower_boxes, upper_boxes) = boxes.split_at_mut(mid);
+    let left = build(lower_objs, lower_boxes, offset);
+    let right = build(upper_objs, upper_boxes, offset + mid);
+    return BuildNode::Inner(bound, Box::new(left), Box::new(right));
+}
@@ -0,0 +1,209 @@
+use std::{fs, io, path::Path};
+
+use crate::{utils::refract, vec3::Vec3, warps::concentric_disk};
+
+// One spherical interface of a lens prescription, in metres. A zero radius is the aperture stop.
+// `ior` is the glass behind the interface (towards the film), 0 or 1 for air.
+#[derive(Clone, Copy)]
+pub struct LensElement {
+    pub radius: f32,
+    pub thickness: f32,
+    pub ior: f32,
+    pub aperture: f32,
+}
+
+// Elements run from the front (scene side) to the rear, the last thickness is the distance from the
+// rear element to the film and is what focus() moves. Traced after Kolb et al., "A Realistic Camera
+// Model for Computer Graphics" with the film on z = 0 and the scene towards -z.
+#[derive(Clone)]
+pub struct LensSystem {
+    pub elements: Vec<LensElement>,
+}
+
+// D-GAUSS F/2 22deg HFOV, US patent 2,673,491 (Tronnier), scaled to 50mm.
+// radius, thickness, ior, aperture diameter, all in millimetres.
+const DOUBLE_GAUSS_50MM: &str = "
+29.475  3.76    1.67    25.2
+84.83   0.12    1       25.2
+19.275  4.025   1.67    23
+40.77   3.275   1.699   23
+12.75   5.705   1       18
+0       4.5     0       17.1
+-14.495 1.18    1.603   17
+40.77   6.065   1.658   20
+-20.385 0.19    1       20
+437.065 3.22    1.717   20
+-39.73  40      1       20
+";
+
+impl LensSystem {
+    pub fn double_gauss_50mm() -> LensSystem {
+        return LensSystem::parse(DOUBLE_GAUSS_50MM).unwrap();
+    }
+
+    // Prescription table, one element per line as "radius thickness ior aperture" in millimetres.
+    // Blank lines and lines starting with # are skipped.
+    pub fn parse(table: &str) -> io::Result<LensSystem> {
+        let mut elements = vec![];
+        for line in table.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values = line
+                .split_whitespace()
+                .map(|v| v.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad number in lens table"))?;
+            if values.len() != 4 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "lens table rows need 4 columns"));
+            }
+            elements.push(LensElement {
+                radius: values[0] / 1000.0,
+                thickness: values[1] / 1000.0,
+                ior: values[2],
+                aperture: values[3] / 1000.0,
+            });
+        }
+        if elements.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "lens table has no elements"));
+        }
+        return Ok(LensSystem { elements });
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<LensSystem> {
+        return LensSystem::parse(&fs::read_to_string(path)?);
+    }
+
+    // Opens or closes the aperture stop to a diameter in millimetres
+    pub fn set_stop(&mut self, diameter_mm: f32) {
+        for element in self.elements.iter_mut().filter(|e| e.radius == 0.0) {
+            element.aperture = diameter_mm / 1000.0;
+        }
+    }
+
+    fn rear_z(&self) -> f32 {
+        return self.elements.last().unwrap().thickness;
+    }
+
+    fn rear_aperture(&self) -> f32 {
+        return self.elements.last().unwrap().aperture;
+    }
+
+    // Follows a ray from the film out of the front element. Returns None when an element
+    // or the stop blocks it, or on total internal reflection.
+    pub fn trace_from_film(&self, mut orig: Vec3, mut dir: Vec3) -> Option<(Vec3, Vec3)> {
+        let mut element_z = 0.0;
+        for i in (0..self.elements.len()).rev() {
+            let element = &self.elements[i];
+            element_z -= element.thickness;
+
+            let t;
+            let mut normal = Vec3::new(0.0, 0.0, 0.0);
+            if element.radius == 0.0 {
+                if dir.z == 0.0 {
+                    return None;
+                }
+                t = (element_z - orig.z) / dir.z;
+            } else {
+                (t, normal) = intersect_spherical(element.radius, element_z + element.radius, orig, dir)?;
+            }
+
+            let hit = orig + t * dir;
+            let half = element.aperture / 2.0;
+            if hit.x * hit.x + hit.y * hit.y > half * half {
+                return None;
+            }
+            orig = hit;
+
+            if element.radius != 0.0 {
+                let eta_i = if element.ior == 0.0 { 1.0 } else { element.ior };
+                let eta_t = if i > 0 && self.elements[i - 1].ior != 0.0 { self.elements[i - 1].ior } else { 1.0 };
+                let unit_dir = dir.unit_vector();
+                let ratio = eta_i / eta_t;
+                let cos_theta = f32::min((-unit_dir).dot_prod(normal), 1.0);
+                if ratio * ratio * (1.0 - cos_theta * cos_theta) > 1.0 {
+                    return None;
+                }
+                dir = refract(&unit_dir, &normal, ratio);
+            }
+        }
+        return Some((orig, dir));
+    }
+
+    // Where a ray leaving the film centre for a point near the rear element's axis crosses the
+    // axis again in front of the lens, as a distance from the film. None when it never converges.
+    fn conjugate_distance(&self) -> Option<f32> {
+        let target = Vec3::new(0.01 * self.rear_aperture(), 0.0, -self.rear_z());
+        let (orig, dir) = self.trace_from_film(Vec3::new(0.0, 0.0, 0.0), target)?;
+        if dir.x == 0.0 {
+            return None;
+        }
+        let t = -orig.x / dir.x;
+        if t <= 0.0 {
+            return None;
+        }
+        return Some(-(orig.z + t * dir.z));
+    }
+
+    // Moves the film so objects `focus_dist` metres from it are sharp. Bisects on the film distance:
+    // too short and the focus lies further out (or past infinity), too long and it comes in closer.
+    pub fn focus(&mut self, focus_dist: f32) -> bool {
+        let last = self.elements.len() - 1;
+        let length: f32 = self.elements.iter().map(|e| e.thickness).sum();
+        let (mut lo, mut hi) = (1e-4, 2.0 * length);
+        for _ in 0..64 {
+            let mid = 0.5 * (lo + hi);
+            self.elements[last].thickness = mid;
+            match self.conjugate_distance() {
+                Some(d) if d < focus_dist => hi = mid,
+                _ => lo = mid,
+            }
+        }
+        self.elements[last].thickness = 0.5 * (lo + hi);
+        return self.conjugate_distance().is_some();
+    }
+
+    // Ray leaving the lens for film point (x, y) in metres, through a uniform point on the rear element.
+    // Blocked rays give None, which is the mechanical vignetting. The cos^4 falloff of film irradiance
+    // is applied by dropping rays with that probability so every returned ray carries full weight.
+    // `lens` picks the point on the rear element and `keep` is the uniform number the falloff is tested against.
+    pub fn sample_ray(&self, film_x: f32, film_y: f32, lens: (f32, f32), keep: f32) -> Option<(Vec3, Vec3)> {
+        let film = Vec3::new(film_x, film_y, 0.0);
+        let lens = concentric_disk(lens) * (self.rear_aperture() / 2.0);
+        let rear = Vec3::new(lens.x, lens.y, -self.rear_z());
+        let dir = rear - film;
+
+        let cos_theta = -dir.z / dir.length();
+        if keep > cos_theta * cos_theta * cos_theta * cos_theta {
+            return None;
+        }
+        return self.trace_from_film(film, dir);
+    }
+}
+
+// Ray against a sphere centred on the axis at z_center. Picks the hit on the side of the sphere
+// the lens surface is on, and returns the normal facing back along the ray.
+fn intersect_spherical(radius: f32, z_center: f32, orig: Vec3, dir: Vec3) -> Option<(f32, Vec3)> {
+    let o = orig - Vec3::new(0.0, 0.0, z_center);
+    let a = dir.length_squared();
+    let b = 2.0 * dir.dot_prod(o);
+    let c = o.length_squared() - radius * radius;
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return None;
+    }
+    let sqrt_disc = disc.sqrt();
+    let (t0, t1) = ((-b - sqrt_disc) / (2.0 * a), (-b + sqrt_disc) / (2.0 * a));
+
+    let use_closer = (dir.z > 0.0) ^ (radius < 0.0);
+    let t = if use_closer { t0.min(t1) } else { t0.max(t1) };
+    if t < 0.0 {
+        return None;
+    }
+    let mut normal = (o + t * dir).unit_vector();
+    if normal.dot_prod(dir) > 0.0 {
+        normal = -normal;
+    }
+    return Some((t, normal));
+}
@@ -0,0 +1,301 @@
+use std::{collections::HashMap, f32::consts::PI, io, path::Path, simd::f32x4, sync::Arc};
+
+use gltf::{image::Format, khr_lights_punctual::Kind, texture::WrappingMode};
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    camera::Camera,
+    color::Color,
+    hittable::{Hittable, HittableList},
+    hittables::{
+        disk::Disk,
+        instance::Instance,
+        mesh::Mesh,
+        sphere::Sphere,
+    },
+    mat4::Mat4,
+    mats::{diffuse_light::DiffuseLight, pbr::PbrMat, MatManager},
+    texture::{ImageTexture, Wrap},
+    vec3::Vec3,
+};
+
+use super::invalid;
+
+// glTF light units (candela, lux) rarely match the scene scale, `light_scale` multiplies all of them.
+// Point and spot lights become emissive spheres of `light_radius`, directional lights a disk far away.
+#[derive(Clone, Copy)]
+pub struct GltfImportOptions {
+    pub light_scale: f32,
+    pub light_radius: f32,
+    pub sun_distance: f32,
+}
+
+impl GltfImportOptions {
+    pub fn default() -> GltfImportOptions {
+        return GltfImportOptions { light_scale: 1.0, light_radius: 0.05, sun_distance: 1000.0 };
+    }
+}
+
+pub struct GltfScene {
+    // First perspective camera found while walking the default scene
+    pub camera: Option<Camera>,
+    pub aspect_ratio: Option<f32>,
+}
+
+// Reads only the JSON, for picking the image size before the scene is set up
+pub fn gltf_aspect_ratio<P: AsRef<Path>>(path: P) -> Option<f32> {
+    let doc = gltf::Gltf::open(path).ok()?;
+    return doc.cameras().find_map(|cam| match cam.projection() {
+        gltf::camera::Projection::Perspective(p) => p.aspect_ratio(),
+        _ => None,
+    });
+}
+
+struct Importer<'a> {
+    buffers: Vec<gltf::buffer::Data>,
+    images: Vec<gltf::image::Data>,
+    options: GltfImportOptions,
+    mats: &'a mut MatManager,
+    textures: HashMap<(usize, bool, Wrap, Wrap), Arc<ImageTexture>>,
+    materials: HashMap<Option<usize>, i64>,
+    // Each primitive is built once and shared by every node that uses its mesh
+    meshes: HashMap<usize, Vec<Arc<dyn Hittable + Sync + Send>>>,
+    camera: Option<Camera>,
+    aspect_ratio: Option<f32>,
+}
+
+pub fn load_gltf<P: AsRef<Path>>(
+    path: P,
+    world: &mut HittableList,
+    mats: &mut MatManager,
+    options: &GltfImportOptions,
+    rng: &mut ChaCha20Rng,
+) -> io::Result<GltfScene> {
+    let (doc, buffers, images) = gltf::import(path).map_err(|e| invalid(&e.to_string()))?;
+    let scene = match doc.default_scene().or_else(|| doc.scenes().next()) {
+        Some(scene) => scene,
+        None => return Err(invalid("glTF file has no scene")),
+    };
+
+    let mut importer = Importer {
+        buffers,
+        images,
+        options: *options,
+        mats,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        meshes: HashMap::new(),
+        camera: None,
+        aspect_ratio: None,
+    };
+    for node in scene.nodes() {
+        importer.visit(&node, Mat4::identity(), world, rng)?;
+    }
+    return Ok(GltfScene { camera: importer.camera, aspect_ratio: importer.aspect_ratio });
+}
+
+// glTF stores matrices column major
+fn to_mat4(cols: [[f32; 4]; 4]) -> Mat4 {
+    return Mat4 { m: cols }.transpose();
+}
+
+fn to_vec3(v: [f32; 3]) -> Vec3 {
+    return Vec3::new(v[0], v[1], v[2]);
+}
+
+fn to_wrap(mode: WrappingMode) -> Wrap {
+    return match mode {
+        WrappingMode::Repeat => Wrap::Repeat,
+        WrappingMode::MirroredRepeat => Wrap::MirroredRepeat,
+        WrappingMode::ClampToEdge => Wrap::ClampToEdge,
+    };
+}
+
+fn texture_from_image(data: &gltf::image::Data, srgb: bool) -> ImageTexture {
+    let unorm8 = |bytes: &[u8]| bytes.iter().map(|b| *b as f32 / 255.0).collect::<Vec<f32>>();
+    let unorm16 = |bytes: &[u8]| {
+        bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0).collect::<Vec<f32>>()
+    };
+    let float32 = |bytes: &[u8]| {
+        bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect::<Vec<f32>>()
+    };
+    let (channels, values) = match data.format {
+        Format::R8 => (1, unorm8(&data.pixels)),
+        Format::R8G8 => (2, unorm8(&data.pixels)),
+        Format::R8G8B8 => (3, unorm8(&data.pixels)),
+        Format::R8G8B8A8 => (4, unorm8(&data.pixels)),
+        Format::R16 => (1, unorm16(&data.pixels)),
+        Format::R16G16 => (2, unorm16(&data.pixels)),
+        Format::R16G16B16 => (3, unorm16(&data.pixels)),
+        Format::R16G16B16A16 => (4, unorm16(&data.pixels)),
+        Format::R32G32B32FLOAT => (3, float32(&data.pixels)),
+        Format::R32G32B32A32FLOAT => (4, float32(&data.pixels)),
+    };
+    return ImageTexture::from_normalized(data.width, data.height, channels, &values, srgb);
+}
+
+impl<'a> Importer<'a> {
+    fn visit(&mut self, node: &gltf::Node, parent: Mat4, world: &mut HittableList, rng: &mut ChaCha20Rng) -> io::Result<()> {
+        let transform = parent * to_mat4(node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            for prim in self.mesh(&mesh)? {
+                // Zero scale is valid glTF for hiding a node, there's nothing to render
+                if let Some(instance) = Instance::try_new(prim, transform) {
+                    world.add(Box::new(instance));
+                }
+            }
+        }
+        if let Some(cam) = node.camera() {
+            self.camera(&cam, &transform, rng);
+        }
+        if let Some(light) = node.light() {
+            self.light(&light, &transform, world);
+        }
+        for child in node.children() {
+            self.visit(&child, transform, world, rng)?;
+        }
+        return Ok(());
+    }
+
+    // One copy per image, colour space and wrap modes, textures sharing all three share it
+    fn texture(&mut self, tex: Option<gltf::texture::Texture>, srgb: bool) -> Option<Arc<ImageTexture>> {
+        let tex = tex?;
+        let index = tex.source().index();
+        let (wrap_u, wrap_v) = (to_wrap(tex.sampler().wrap_s()), to_wrap(tex.sampler().wrap_t()));
+        let images = &self.images;
+        let texture = self.textures.entry((index, srgb, wrap_u, wrap_v)).or_insert_with(|| {
+            let mut texture = texture_from_image(&images[index], srgb);
+            texture.wrap_u = wrap_u;
+            texture.wrap_v = wrap_v;
+            Arc::new(texture)
+        });
+        return Some(texture.clone());
+    }
+
+    fn material(&mut self, mat: &gltf::Material) -> i64 {
+        if let Some(id) = self.materials.get(&mat.index()) {
+            return *id;
+        }
+        let pbr = mat.pbr_metallic_roughness();
+        let base_color_tex = self.texture(pbr.base_color_texture().map(|info| info.texture()), true);
+        let metallic_roughness_tex = self.texture(pbr.metallic_roughness_texture().map(|info| info.texture()), false);
+        let emissive_tex = self.texture(mat.emissive_texture().map(|info| info.texture()), true);
+        let normal_scale = mat.normal_texture().map_or(1.0, |info| info.scale());
+        let normal_tex = self.texture(mat.normal_texture().map(|info| info.texture()), false);
+        let emissive_strength = mat.emissive_strength().unwrap_or(1.0);
+        let [er, eg, eb] = mat.emissive_factor();
+
+        let id = self.mats.gen_mat(Box::new(PbrMat {
+            base_color: f32x4::from_array(pbr.base_color_factor()),
+            base_color_tex,
+            metallic: pbr.metallic_factor(),
+            roughness: pbr.roughness_factor(),
+            metallic_roughness_tex,
+            emissive: Color::new_01_range(er * emissive_strength, eg * emissive_strength, eb * emissive_strength),
+            emissive_tex,
+            normal_tex,
+            normal_scale,
+        }));
+        self.materials.insert(mat.index(), id);
+        return id;
+    }
+
+    fn mesh(&mut self, mesh: &gltf::Mesh) -> io::Result<Vec<Arc<dyn Hittable + Sync + Send>>> {
+        if let Some(prims) = self.meshes.get(&mesh.index()) {
+            return Ok(prims.clone());
+        }
+
+        let mut prims: Vec<Arc<dyn Hittable + Sync + Send>> = vec![];
+        for prim in mesh.primitives() {
+            if prim.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            let buffers = &self.buffers;
+            let reader = prim.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions: Vec<Vec3> = match reader.read_positions() {
+                Some(iter) => iter.map(to_vec3).collect(),
+                None => return Err(invalid("glTF primitive has no positions")),
+            };
+            let normals: Vec<Vec3> = match reader.read_normals() {
+                Some(iter) => iter.map(to_vec3).collect(),
+                None => vec![],
+            };
+            let colors: Vec<Color> = match reader.read_colors(0) {
+                Some(iter) => iter.into_rgb_f32().map(|c| Color::new_01_range(c[0], c[1], c[2])).collect(),
+                None => vec![],
+            };
+            let uvs: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+                Some(iter) => iter.into_f32().collect(),
+                None => vec![],
+            };
+            let flat: Vec<u32> = match reader.read_indices() {
+                Some(iter) => iter.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            let indices: Vec<[u32; 3]> = flat.chunks_exact(3).map(|f| [f[0], f[1], f[2]]).collect();
+            if indices.is_empty() {
+                continue;
+            }
+
+            let material = self.material(&prim.material());
+            let double_sided = prim.material().double_sided();
+            let mesh = Mesh { positions, normals, colors, uvs, indices, double_sided };
+            prims.push(Arc::from(mesh.into_bvh(&material) as Box<dyn Hittable + Sync + Send>));
+        }
+        self.meshes.insert(mesh.index(), prims.clone());
+        return Ok(prims);
+    }
+
+    // glTF cameras look down their local -z with +y up
+    fn camera(&mut self, cam: &gltf::Camera, transform: &Mat4, rng: &mut ChaCha20Rng) {
+        if self.camera.is_some() {
+            return;
+        }
+        let persp = match cam.projection() {
+            gltf::camera::Projection::Perspective(persp) => persp,
+            gltf::camera::Projection::Orthographic(_) => return,
+        };
+        let look_from = transform.transform_point(&Vec3::new(0.0, 0.0, 0.0));
+        let look_to = look_from + transform.transform_vector(&Vec3::new(0.0, 0.0, -1.0));
+        let view_up = transform.transform_vector(&Vec3::new(0.0, 1.0, 0.0));
+        let aspect_ratio = persp.aspect_ratio().unwrap_or(16.0 / 9.0);
+
+        self.camera = Some(Camera::new(
+            look_from,
+            look_to,
+            view_up,
+            persp.yfov() * 180.0 / PI,
+            aspect_ratio,
+            0.0,
+            1.0,
+            rng,
+        ));
+        self.aspect_ratio = Some(aspect_ratio);
+    }
+
+    // Point and spot intensity is in candela, a sphere of radius r shows pi r^2 of area to
+    // every direction so its radiance is I / (pi r^2). Spot cones are not modelled.
+    // Directional illuminance is in lux, a disk covering solid angle w needs radiance E / w.
+    fn light(&mut self, light: &gltf::khr_lights_punctual::Light, transform: &Mat4, world: &mut HittableList) {
+        let [r, g, b] = light.color();
+        let radius = self.options.light_radius;
+        let (position, area_scale) = match light.kind() {
+            Kind::Directional => {
+                let dir = transform.transform_vector(&Vec3::new(0.0, 0.0, -1.0)).unit_vector();
+                let distance = self.options.sun_distance;
+                let disk_radius = radius * distance;
+                let solid_angle = PI * disk_radius * disk_radius / (distance * distance);
+                let center = -dir * distance;
+                let emit = light.intensity() * self.options.light_scale / solid_angle;
+                let mat = self.mats.gen_mat(Box::new(DiffuseLight { emit: Color::new_01_range(r * emit, g * emit, b * emit) }));
+                world.add(Disk::new_box(center, dir, disk_radius, &mat));
+                return;
+            }
+            Kind::Point | Kind::Spot { .. } => (transform.transform_point(&Vec3::new(0.0, 0.0, 0.0)), PI * radius * radius),
+        };
+        let emit = light.intensity() * self.options.light_scale / area_scale;
+        let mat = self.mats.gen_mat(Box::new(DiffuseLight { emit: Color::new_01_range(r * emit, g * emit, b * emit) }));
+        world.add(Sphere::new_box(position, radius, &mat));
+    }
+}
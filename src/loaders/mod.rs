@@ -0,0 +1,9 @@
+pub mod gltf;
+pub mod ply;
+pub mod stl;
+
+use std::io;
+
+fn invalid(msg: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+}
@@ -0,0 +1,302 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    color::Color,
+    hittables::mesh::{Mesh, MeshImportOptions},
+    vec3::Vec3,
+};
+
+use super::invalid;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLe,
+    BinaryBe,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Scalar> {
+        return match name {
+            "char" | "int8" => Ok(Scalar::I8),
+            "uchar" | "uint8" => Ok(Scalar::U8),
+            "short" | "int16" => Ok(Scalar::I16),
+            "ushort" | "uint16" => Ok(Scalar::U16),
+            "int" | "int32" => Ok(Scalar::I32),
+            "uint" | "uint32" => Ok(Scalar::U32),
+            "float" | "float32" => Ok(Scalar::F32),
+            "double" | "float64" => Ok(Scalar::F64),
+            _ => Err(invalid("unknown PLY property type")),
+        };
+    }
+
+    fn size(&self) -> usize {
+        return match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        };
+    }
+}
+
+enum PropKind {
+    Single(Scalar),
+    List(Scalar, Scalar),
+}
+
+struct Property {
+    name: String,
+    kind: PropKind,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    props: Vec<Property>,
+}
+
+// Pulls numbers out of either the ASCII token stream or the binary body
+struct Body<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Body<'a> {
+    fn next_token(&mut self) -> io::Result<&'a str> {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.bytes.len() && !self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "PLY body ended early"));
+        }
+        return std::str::from_utf8(&self.bytes[start..self.pos]).map_err(|_| invalid("PLY body is not ASCII"));
+    }
+
+    fn read(&mut self, ty: Scalar) -> io::Result<f64> {
+        if self.format == Format::Ascii {
+            return self.next_token()?.parse::<f64>().map_err(|_| invalid("bad number in PLY body"));
+        }
+
+        let size = ty.size();
+        if self.pos + size > self.bytes.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "PLY body ended early"));
+        }
+        let mut raw = [0u8; 8];
+        raw[..size].copy_from_slice(&self.bytes[self.pos..self.pos + size]);
+        self.pos += size;
+        if self.format == Format::BinaryBe {
+            raw[..size].reverse();
+        }
+
+        return Ok(match ty {
+            Scalar::I8 => raw[0] as i8 as f64,
+            Scalar::U8 => raw[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(raw),
+        });
+    }
+}
+
+// ASCII and binary PLY. Reads x/y/z, nx/ny/nz, red/green/blue (as vertex colors) and vertex_indices,
+// polygons are fan triangulated and any other element is skipped.
+pub fn load_ply<P: AsRef<Path>>(path: P, options: &MeshImportOptions) -> io::Result<Mesh> {
+    let bytes = fs::read(path)?;
+
+    // Header is ASCII up to and including the "end_header" line
+    let marker = b"end_header";
+    let header_end = bytes
+        .windows(marker.len())
+        .position(|w| w == marker)
+        .ok_or_else(|| invalid("PLY has no end_header"))?;
+    let body_start = match bytes[header_end..].iter().position(|&b| b == b'\n') {
+        Some(nl) => header_end + nl + 1,
+        None => bytes.len(),
+    };
+    let header = String::from_utf8_lossy(&bytes[..header_end]);
+
+    let mut lines = header.lines();
+    if lines.next().map(|l| l.trim()) != Some("ply") {
+        return Err(invalid("missing ply magic"));
+    }
+
+    let mut format = Format::Ascii;
+    let mut elements: Vec<Element> = vec![];
+    for line in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["format", "ascii", ..] => format = Format::Ascii,
+            ["format", "binary_little_endian", ..] => format = Format::BinaryLe,
+            ["format", "binary_big_endian", ..] => format = Format::BinaryBe,
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid("bad PLY element count"))?,
+                props: vec![],
+            }),
+            ["property", "list", count_ty, item_ty, name] => {
+                let element = elements.last_mut().ok_or_else(|| invalid("PLY property before element"))?;
+                element.props.push(Property {
+                    name: name.to_string(),
+                    kind: PropKind::List(Scalar::parse(count_ty)?, Scalar::parse(item_ty)?),
+                });
+            }
+            ["property", ty, name] => {
+                let element = elements.last_mut().ok_or_else(|| invalid("PLY property before element"))?;
+                element.props.push(Property {
+                    name: name.to_string(),
+                    kind: PropKind::Single(Scalar::parse(ty)?),
+                });
+            }
+            _ => {} // comments, obj_info
+        }
+    }
+
+    let mut body = Body { format, bytes: &bytes, pos: body_start };
+    let mut mesh = Mesh::new();
+
+    for element in &elements {
+        let prop_index = |name: &str| element.props.iter().position(|p| p.name == name);
+        let xyz = [prop_index("x"), prop_index("y"), prop_index("z")];
+        let rgb = [prop_index("red"), prop_index("green"), prop_index("blue")];
+        let has_color = element.name == "vertex" && rgb.iter().all(|i| i.is_some());
+        let nxyz = [prop_index("nx"), prop_index("ny"), prop_index("nz")];
+        let has_normal = element.name == "vertex" && nxyz.iter().all(|i| i.is_some());
+
+        for _ in 0..element.count {
+            let mut values = vec![0.0; element.props.len()];
+            let mut list: Vec<u32> = vec![];
+            for (i, prop) in element.props.iter().enumerate() {
+                match prop.kind {
+                    PropKind::Single(ty) => values[i] = body.read(ty)?,
+                    PropKind::List(count_ty, item_ty) => {
+                        let count = body.read(count_ty)? as usize;
+                        let keep = prop.name == "vertex_indices" || prop.name == "vertex_index";
+                        for _ in 0..count {
+                            let item = body.read(item_ty)?;
+                            if keep {
+                                list.push(item as u32);
+                            }
+                        }
+                    }
+                }
+            }
+
+            if element.name == "vertex" {
+                let coord = |slot: Option<usize>| slot.map_or(0.0, |i| values[i]) as f32;
+                mesh.positions.push(Vec3::new(coord(xyz[0]), coord(xyz[1]), coord(xyz[2])));
+                if has_normal {
+                    mesh.normals.push(Vec3::new(coord(nxyz[0]), coord(nxyz[1]), coord(nxyz[2])));
+                }
+                if has_color {
+                    // Integer channels are 0-255, float channels already 0-1
+                    let channel = |slot: Option<usize>| {
+                        let i = slot.unwrap();
+                        match element.props[i].kind {
+                            PropKind::Single(Scalar::F32) | PropKind::Single(Scalar::F64) => values[i] as f32,
+                            _ => values[i] as f32 / 255.0,
+                        }
+                    };
+                    mesh.colors.push(Color::new_01_range(channel(rgb[0]), channel(rgb[1]), channel(rgb[2])));
+                }
+            } else if element.name == "face" {
+                for k in 1..list.len().saturating_sub(1) {
+                    mesh.indices.push([list[0], list[k], list[k + 1]]);
+                }
+            }
+        }
+    }
+
+    let vertex_count = mesh.positions.len() as u32;
+    if mesh.indices.iter().any(|tri| tri.iter().any(|&i| i >= vertex_count)) {
+        return Err(invalid("PLY face references a missing vertex"));
+    }
+    if mesh.indices.is_empty() {
+        return Err(invalid("PLY has no faces"));
+    }
+
+    mesh.apply_import_options(options);
+    return Ok(mesh);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn write_temp(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("ply_test_{}_{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        return path;
+    }
+
+    #[test]
+    fn reads_ascii_quad_with_colors() {
+        let text = "ply\nformat ascii 1.0\ncomment a unit square\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+                    property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n\
+                    0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n4 0 1 2 3\n";
+        let path = write_temp("quad.ply", text.as_bytes());
+        let mesh = load_ply(&path, &MeshImportOptions::default()).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3]], "quads are fan triangulated");
+        assert_eq!(mesh.colors.len(), 4);
+        assert_eq!(mesh.colors[1].g, 1.0);
+    }
+
+    #[test]
+    fn reads_binary_little_endian_and_fits() {
+        let mut bytes = b"ply\nformat binary_little_endian 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                          element face 1\nproperty list uchar uint vertex_indices\nend_header\n"
+            .to_vec();
+        for v in [[0.0f32, 0.0, 0.0], [4.0, 0.0, 0.0], [0.0, 2.0, 0.0]] {
+            for c in v {
+                bytes.extend(c.to_le_bytes());
+            }
+        }
+        bytes.push(3);
+        for i in [0u32, 1, 2] {
+            bytes.extend(i.to_le_bytes());
+        }
+        let path = write_temp("tri.ply", &bytes);
+        let options = MeshImportOptions { recenter: Some(Vec3::new(0.0, 0.0, 0.0)), fit_size: Some(2.0), ..MeshImportOptions::default() };
+        let mesh = load_ply(&path, &options).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+        let bound = mesh.bounds();
+        assert_eq!((bound.min.x, bound.max.x), (-1.0, 1.0), "the longest side is scaled to 2 around the new centre");
+        assert_eq!((bound.min.y, bound.max.y), (-0.5, 0.5));
+    }
+
+    #[test]
+    fn rejects_face_with_missing_vertex() {
+        let text = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                    element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 7\n";
+        let path = write_temp("bad_index.ply", text.as_bytes());
+        assert!(load_ply(&path, &MeshImportOptions::default()).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
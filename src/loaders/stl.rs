@@ -0,0 +1,169 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use crate::{
+    hittables::mesh::{Mesh, MeshImportOptions},
+    vec3::Vec3,
+};
+
+use super::invalid;
+
+// ASCII or binary STL. Facets carry their own copies of each corner, so identical positions
+// are welded back together into an indexed mesh. The stored facet normals are ignored.
+pub fn load_stl<P: AsRef<Path>>(path: P, options: &MeshImportOptions) -> io::Result<Mesh> {
+    let bytes = fs::read(path)?;
+
+    // Binary files can also start with "solid", so that only counts as ASCII when it parses into
+    // facets. Binary files may carry padding or metadata past the facets the header counts.
+    let ascii = if bytes.trim_ascii_start().starts_with(b"solid") { Some(read_ascii(&bytes)) } else { None };
+    let corners = match ascii {
+        Some(Ok(corners)) if !corners.is_empty() => corners,
+        _ if binary_fits(&bytes) => read_binary(&bytes),
+        Some(result) => result?,
+        None => return Err(invalid("STL is neither binary nor ASCII")),
+    };
+
+    let mut mesh = Mesh::new();
+    let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+    for tri in corners.chunks_exact(3) {
+        let mut face = [0u32; 3];
+        for (k, p) in tri.iter().enumerate() {
+            let key = [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+            face[k] = *welded.entry(key).or_insert_with(|| {
+                mesh.positions.push(*p);
+                (mesh.positions.len() - 1) as u32
+            });
+        }
+        mesh.indices.push(face);
+    }
+    if mesh.indices.is_empty() {
+        return Err(invalid("STL has no facets"));
+    }
+
+    mesh.apply_import_options(options);
+    return Ok(mesh);
+}
+
+// Room for the 80 byte header, the facet count and that many 50 byte facets
+fn binary_fits(bytes: &[u8]) -> bool {
+    if bytes.len() < 84 {
+        return false;
+    }
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    return count.checked_mul(50).is_some_and(|size| bytes.len() - 84 >= size);
+}
+
+fn read_binary(bytes: &[u8]) -> Vec<Vec3> {
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    let float_at = |at: usize| f32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+
+    let mut corners = Vec::with_capacity(count * 3);
+    for facet in 0..count {
+        // 12 bytes of normal, three 12 byte vertices, 2 bytes of attributes
+        let base = 84 + facet * 50 + 12;
+        for k in 0..3 {
+            let at = base + k * 12;
+            corners.push(Vec3::new(float_at(at), float_at(at + 4), float_at(at + 8)));
+        }
+    }
+    return corners;
+}
+
+fn read_ascii(bytes: &[u8]) -> io::Result<Vec<Vec3>> {
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid("STL is neither binary nor ASCII"))?;
+    if !text.trim_start().starts_with("solid") {
+        return Err(invalid("missing STL solid header"));
+    }
+
+    let mut corners = vec![];
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() == 4 && fields[0] == "vertex" {
+            let mut xyz = [0.0f32; 3];
+            for i in 0..3 {
+                xyz[i] = fields[i + 1].parse().map_err(|_| invalid("bad STL vertex"))?;
+            }
+            corners.push(Vec3::new(xyz[0], xyz[1], xyz[2]));
+        }
+    }
+    if corners.len() % 3 != 0 {
+        return Err(invalid("STL facet with other than three vertices"));
+    }
+    return Ok(corners);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn write_temp(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("stl_test_{}_{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        return path;
+    }
+
+    // Two facets sharing the edge (1, 0, 0) - (0, 1, 0), a square split on its diagonal
+    const SQUARE: [[f32; 3]; 6] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+
+    fn binary(header: &[u8], padding: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; 80];
+        bytes[..header.len()].copy_from_slice(header);
+        bytes.extend(2u32.to_le_bytes());
+        for facet in SQUARE.chunks(3) {
+            bytes.extend([0u8; 12]);
+            for corner in facet {
+                for c in corner {
+                    bytes.extend(c.to_le_bytes());
+                }
+            }
+            bytes.extend([0u8; 2]);
+        }
+        bytes.extend(vec![0u8; padding]);
+        return bytes;
+    }
+
+    fn assert_square(mesh: &Mesh) {
+        assert_eq!(mesh.indices.len(), 2);
+        assert_eq!(mesh.positions.len(), 4, "shared corners are welded");
+    }
+
+    #[test]
+    fn reads_binary_with_trailing_bytes() {
+        let path = write_temp("padded.stl", &binary(b"exported", 16));
+        assert_square(&load_stl(&path, &MeshImportOptions::default()).unwrap());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reads_binary_whose_header_starts_with_solid() {
+        let path = write_temp("solid_header.stl", &binary(b"solid exported by a CAD tool", 0));
+        assert_square(&load_stl(&path, &MeshImportOptions::default()).unwrap());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reads_ascii() {
+        let mut text = String::from("solid square\n");
+        for facet in SQUARE.chunks(3) {
+            text += "  facet normal 0 0 1\n    outer loop\n";
+            for [x, y, z] in facet {
+                text += &format!("      vertex {} {} {}\n", x, y, z);
+            }
+            text += "    endloop\n  endfacet\n";
+        }
+        text += "endsolid square\n";
+        let path = write_temp("ascii.stl", text.as_bytes());
+        assert_square(&load_stl(&path, &MeshImportOptions::default()).unwrap());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_truncated_binary() {
+        let mut bytes = binary(b"", 0);
+        bytes.truncate(bytes.len() - 10);
+        let path = write_temp("truncated.stl", &bytes);
+        assert!(load_stl(&path, &MeshImportOptions::default()).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
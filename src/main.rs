@@ -1,20 +1,37 @@
 #![feature(portable_simd)]
 mod aabb;
+mod animation;
+mod aov;
 mod camera;
 mod color;
+mod denoise;
+mod filter;
 mod hittable;
 mod hittables;
+mod lens;
+mod loaders;
+mod mat4;
 mod material;
 mod mats;
+mod packet;
+mod perlin;
+mod post;
 mod rand_double;
 mod ray;
+mod sampler;
 mod scenes;
+mod stats;
+mod texture;
 mod utils;
 mod vec3;
+mod voxel_grid;
+mod warps;
+mod wavefront;
 
 use std::{
     f32::INFINITY,
     fmt::Write,
+    fs::File,
     ops,
     simd::{self, f32x4, StdFloat},
     sync::{Arc, RwLock},
@@ -25,17 +42,22 @@ use std::{
 use atomic_counter::{AtomicCounter, RelaxedCounter};
 use camera::Camera;
 use color::Color;
+use denoise::Denoiser;
+use filter::{Film, PixelFilter};
 use hittable::{HitRecord, HittableList};
-use humantime::format_duration;
-use image::{Rgb, RgbImage};
+use image::{codecs::gif::{GifEncoder, Repeat}, Delay, DynamicImage, Frame, Rgb, RgbImage};
 use indicatif::{MultiProgress, ProgressBar, ProgressState, ProgressStyle};
+use aov::{Aov, AovFormat, AovPixel, AovSample};
 use mats::MatManager;
 use pad::PadStr;
+use post::{HdrImage, PostEffects};
 use rand::prelude::*;
 use rand::{thread_rng, SeedableRng};
 use rand_chacha::ChaCha20Rng;
-use rand_double::{rand_double, rand_double_range};
+use rand_double::rand_double_range;
 use ray::Ray;
+use sampler::{Sampler, SamplerConfig};
+use stats::{RayKind, Report, ThreadStats};
 use rayon::{
     current_thread_index,
     prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator},
@@ -44,9 +66,10 @@ use rayon::{
 use scenes::cornell_box::{self, CornellBox};
 use vec3::Vec3;
 use thread_priority::*;
+use wavefront::Wavefront;
 
 #[allow(unused_imports)]
-use crate::scenes::{dof_spheres_glass::DofSpheresGlass, random_spheres::RandomSpheres, Scene};
+use crate::scenes::{dof_spheres_glass::DofSpheresGlass, random_spheres::RandomSpheres, smoke_cloud::SmokeCloud, Scene};
 
 #[global_allocator]
 static GLOBAL: jemallocator::Jemalloc = jemallocator::Jemalloc;
@@ -54,13 +77,34 @@ static GLOBAL: jemallocator::Jemalloc = jemallocator::Jemalloc;
 fn ray_color(
     ray: &Ray,
     world: &HittableList,
-    rng: &mut ChaCha20Rng,
+    sampler: &mut dyn Sampler,
     mats: &MatManager,
     depth: u64,
 ) -> f32x4 {
+    // Out of bounces, shade would throw the hit away. Not traced, so not counted either, the same
+    // as the wavefront integrator retiring the path.
+    if depth == 0 {
+        return Color::new(0.0, 0.0, 0.0).to_simd4();
+    }
+    stats::count_ray(RayKind::Bounce);
+    return shade(ray, closest_hit(world, ray), world, sampler, mats, depth);
+}
+
+fn closest_hit(world: &HittableList, ray: &Ray) -> Option<HitRecord> {
     let mut rec: HitRecord = HitRecord::default();
+    return if world.hit(ray, 0.0001, INFINITY, &mut rec) { Some(rec) } else { None };
+}
 
-    if depth <= 0 {
+// ray_color once the ray's closest hit is known, so camera rays can be traced in packets
+fn shade(
+    ray: &Ray,
+    hit: Option<HitRecord>,
+    world: &HittableList,
+    sampler: &mut dyn Sampler,
+    mats: &MatManager,
+    depth: u64,
+) -> f32x4 {
+    if depth == 0 {
         return Color {
             r: 0.0,
             g: 0.0,
@@ -69,17 +113,18 @@ fn ray_color(
         .to_simd4();
     }
 
-    if world.hit(ray, 0.000000001, INFINITY, &mut rec) {
+    if let Some(mut rec) = hit {
         //let target = rec.point + random_in_hemisphere(&rec.normal, rng);
         //let tmp_ray = Ray::new(rec.point, target - rec.point);
         //let next_color = ray_color(&tmp_ray, world, rng, depth - 1);
-        let mut scattered = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0));
+        let mut scattered = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), ray.time);
         let mut attenuation = Color::new(0.0, 0.0, 0.0).to_simd4();
         let mat = mats.get_mat(&rec.material);
-        let emitted = mat.emitted(0.0, 0.0, &Vec3::new(0.0, 0.0, 0.0));
+        mat.perturb_normal(&mut rec);
+        let emitted = mat.emitted(rec.tex_u, rec.tex_v, &rec.point);
 
-        if mat.scatter(ray, &rec, &mut attenuation, &mut scattered, rng) {
-            let next_color = ray_color(&scattered, world, rng, mats, depth - 1);
+        if mat.scatter(ray, &rec, &mut attenuation, &mut scattered, sampler) {
+            let next_color = ray_color(&scattered, world, sampler, mats, depth - 1);
             return emitted + attenuation * next_color;
         } else {
             return emitted;
@@ -96,7 +141,12 @@ fn ray_color(
         //};
     }
 
-    let unit_dir = ray.dir;
+    return background(ray);
+}
+
+fn background(ray: &Ray) -> f32x4 {
+    // Rays aren't unit length, unnormalised the gradient runs past its ends and goes negative
+    let unit_dir = ray.dir.unit_vector();
     let t = 0.5 * (unit_dir.y + 1.0); // y_pos_for_coloring
                                       //return Color {
                                       //    r: 127.5 + t * 127.5,
@@ -111,6 +161,59 @@ fn ray_color(
     .to_simd4();
 }
 
+// shade for camera rays when AOV passes are on. Takes the first two bounces by hand to fill
+// `aov` and split the light by path length, then hands the rest of the path to ray_color.
+fn shade_aovs(
+    ray: &Ray,
+    hit: Option<HitRecord>,
+    world: &HittableList,
+    sampler: &mut dyn Sampler,
+    mats: &MatManager,
+    depth: u64,
+    aov: &mut AovSample,
+) -> f32x4 {
+    let black = Color::new(0.0, 0.0, 0.0).to_simd4();
+    if depth == 0 {
+        return black;
+    }
+    let mut rec = match hit {
+        Some(rec) => rec,
+        None => {
+            aov.emission = background(ray);
+            return aov.emission;
+        }
+    };
+
+    let mat = mats.get_mat(&rec.material);
+    mat.perturb_normal(&mut rec);
+    aov.set_surface(ray, &rec);
+    aov.emission = mat.emitted(rec.tex_u, rec.tex_v, &rec.point);
+
+    let mut scattered = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), ray.time);
+    let mut attenuation = black;
+    if depth <= 1 || !mat.scatter(ray, &rec, &mut attenuation, &mut scattered, sampler) {
+        return aov.emission;
+    }
+    aov.albedo = attenuation;
+
+    let mut rec2: HitRecord = HitRecord::default();
+    stats::count_ray(RayKind::Bounce);
+    if !world.hit(&scattered, 0.0001, INFINITY, &mut rec2) {
+        aov.direct = attenuation * background(&scattered);
+        return aov.emission + aov.direct;
+    }
+    let mat2 = mats.get_mat(&rec2.material);
+    mat2.perturb_normal(&mut rec2);
+    aov.direct = attenuation * mat2.emitted(rec2.tex_u, rec2.tex_v, &rec2.point);
+
+    let mut scattered2 = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), ray.time);
+    let mut attenuation2 = black;
+    if mat2.scatter(&scattered, &rec2, &mut attenuation2, &mut scattered2, sampler) {
+        aov.indirect = attenuation * attenuation2 * ray_color(&scattered2, world, sampler, mats, depth - 2);
+    }
+    return aov.emission + aov.direct + aov.indirect;
+}
+
 fn format_bar(bar: &ProgressBar) {
     bar.set_style(
         ProgressStyle::with_template("{msg} | {spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {percent}% ({eta_precise} @ {per_sec_nice} lines/s)").unwrap()
@@ -129,33 +232,265 @@ fn main() {
     let mut seed: <ChaCha20Rng as SeedableRng>::Seed = Default::default();
     thread_rng().fill(&mut seed);
 
+    let target_width: u32 = 1920;
+    // Scenes that aim at a pixel take the width, e.g. `scenes::bokeh::Bokeh { double_gauss: false, width: target_width }`
     let scene = RandomSpheres {};
     let aspect_ratio: f32 = scene.get_aspect_ratio();
-    let target_width: u32 = 1920;
     let target_height: u32 = (target_width as f32 / aspect_ratio) as u32;
     let samples_per_pixel: u16 = 100;
     let max_depth: u16 = 50;
+    // Reconstruction filter the samples are splatted through, e.g.
+    // `PixelFilter::new(filter::Filter::Mitchell { b: 1.0 / 3.0, c: 1.0 / 3.0 }, 2.0)`. AOVs stay box filtered.
+    let pixel_filter = PixelFilter::default();
+    // Where the random numbers of every sample come from, see sampler.rs, e.g. `SamplerConfig::new(sampler::SamplerKind::Sobol, 0)`.
+    // Sobol gets the same noise level as independent samples with far fewer of them, BlueNoise also
+    // spreads what's left evenly.
+    let mut sampler = SamplerConfig::default();
+    // Traces camera rays in packets of packet::LANES neighbouring pixels, same image either way.
+    // `packet_benchmark` times single rays against packets on the scene and exits instead of rendering.
+    let packet_tracing = false;
+    let packet_benchmark = false;
+    // Renders with the wavefront integrator in wavefront.rs, paths in bulk stages rather than a
+    // recursive call each. Same image, falls back to ray_color when AOV passes are on.
+    let wavefront = false;
     let mprog = Arc::new(MultiProgress::new());
-    let bar = Arc::new(mprog.add(ProgressBar::new(target_height as u64)));
-    format_bar(&bar);
-    bar.set_message("Render Lines");
+
+    // -----------
+    //  Animation
+    // -----------
+    // Renders the scene's camera path to frame_0001.png.. instead of a still. `frame_range` is
+    // inclusive and defaults to the whole path, `write_gif` also assembles output.gif from the frames.
+    let render_animation = false;
+    let frame_range: Option<(u32, u32)> = None;
+    let write_gif = false;
+
+    // ------------
+    //  AOV passes
+    // ------------
+    // Passes written next to the beauty image, see aov.rs. EXR output also keeps the beauty in float.
+    let aov_passes: Vec<Aov> = vec![];
+    let aov_format = AovFormat::Png;
+
+    // ---------
+    //  Denoise
+    // ---------
+    // Filters the linear image guided by albedo, normal and depth at the first hit, see denoise.rs.
+    // Zero strength turns it off. Its feature passes are saved with any AOVs asked for.
+    // `denoise_compare` also writes <stem>_compare.png, noisy on the left and denoised on the right.
+    let denoise_strength: f32 = 0.0;
+    let denoise_compare = false;
+
+    let mut denoiser = Denoiser::default();
+    denoiser.strength = denoise_strength;
+    let mut render_passes = aov_passes.clone();
+    if denoise_strength > 0.0 {
+        render_passes.extend(denoise::FEATURES.iter().filter(|f| !aov_passes.contains(f)));
+    }
+
+    // --------------
+    //  Post effects
+    // --------------
+    // Lens and film effects on the linear image after denoising, see post.rs for the settings.
+    // All off by default, turn one on with e.g. `post.bloom = Some(post::Bloom::default());`
+    #[allow(unused_mut)]
+    let mut post = PostEffects::default();
+
+    // -------
+    //  Stats
+    // -------
+    // Rays, BVH work, path lengths, stage times and peak memory are printed after the render,
+    // `stats_json` also saves them there as JSON, e.g. Some("stats.json").
+    let stats_json: Option<&str> = None;
+
+    let keep_hdr = !render_passes.is_empty() || aov_format == AovFormat::Exr || post.enabled();
+    let write_aovs = !aov_passes.is_empty() || aov_format == AovFormat::Exr;
 
     let mut rng = ChaCha20Rng::from_seed(seed);
+    sampler.seed = rng.next_u32();
+    hittables::voxel_volume::set_seed(sampler.seed as u64);
     let mut mats = MatManager::new();
     let mut world = HittableList { objs: vec![] };
     let mut cam = Camera::default(&mut rng);
     let mut aspect_ratio: f32 = 0.0;
+    let setup_start = Instant::now();
     #[allow(unused_mut)]
     scene.setup(&mut world, &mut cam, &mut mats, &mut aspect_ratio, &mut rng);
+    world.accelerate();
+    let mut report = Report::new();
+    report.stages.push(("scene setup", setup_start.elapsed()));
+    if packet_benchmark {
+        packet::benchmark(&world, &cam, target_width, target_height);
+        return;
+    }
+
+    let smats = Arc::new(mats);
+    let sworld = Arc::new(world);
+
+    if let Some(path) = scene.camera_path().filter(|_| render_animation) {
+        let (first, last) = frame_range.unwrap_or((1, path.frame_count()));
+        let start = Instant::now();
+        let mut frames = vec![];
+        let (mut render_time, mut merge_time) = (Duration::ZERO, Duration::ZERO);
+        for frame in first..=last {
+            let scam = Arc::new(path.camera_at(&cam, frame));
+            let label = format!("Frame {frame}/{last}");
+            let mut out = render_frame(target_width, target_height, samples_per_pixel, max_depth, &pixel_filter, &sampler, packet_tracing, wavefront, &render_passes, keep_hdr, &mprog, &label, &smats, &sworld, &scam);
+            let stem = format!("frame_{frame:04}");
+            render_time += out.render_time;
+            merge_time += out.merge_time;
+            report.add_frame(&out.threads);
+            if denoise_strength > 0.0 {
+                let noisy = denoise_frame(&mut out, &denoiser, target_width, target_height, &render_passes);
+                if denoise_compare {
+                    side_by_side(&noisy, &out.im).save(format!("{stem}_compare.png"));
+                }
+            }
+            if post.enabled() {
+                post_frame(&mut out, &post, target_width, target_height, &render_passes, &mut rng);
+            }
+            out.im.save(format!("{stem}.png"));
+            if write_aovs {
+                aov::save_aovs(&stem, target_width, target_height, &render_passes, aov_format, &out.hdr).unwrap();
+            }
+            if write_gif {
+                frames.push(Frame::from_parts(
+                    DynamicImage::ImageRgb8(out.im).into_rgba8(),
+                    0,
+                    0,
+                    Delay::from_saturating_duration(Duration::from_secs_f32(1.0 / path.fps)),
+                ));
+            }
+        }
+        if write_gif {
+            let mut encoder = GifEncoder::new(File::create("output.gif").unwrap());
+            encoder.set_repeat(Repeat::Infinite);
+            encoder.encode_frames(frames).unwrap();
+        }
+
+        report.stages.push(("rendering", render_time));
+        report.stages.push(("merging lines", merge_time));
+        report.stages.push(("total", start.elapsed()));
+        print_report(&mut report, stats_json);
+        println!("Rendered frames  {first} to {last}");
+        return;
+    }
+
+    let start = Instant::now();
+    let scam = Arc::new(cam);
+    let mut out = render_frame(target_width, target_height, samples_per_pixel, max_depth, &pixel_filter, &sampler, packet_tracing, wavefront, &render_passes, keep_hdr, &mprog, "Render Lines", &smats, &sworld, &scam);
+    let (run_finished, merge_time) = (out.render_time, out.merge_time);
+    let line_concat_finish = run_finished + merge_time;
+    report.add_frame(&out.threads);
+    if denoise_strength > 0.0 {
+        let noisy = denoise_frame(&mut out, &denoiser, target_width, target_height, &render_passes);
+        if denoise_compare {
+            side_by_side(&noisy, &out.im).save("output_compare.png");
+        }
+    }
+    let denoise_finish = start.elapsed();
+    if post.enabled() {
+        post_frame(&mut out, &post, target_width, target_height, &render_passes, &mut rng);
+    }
+    let post_finish = start.elapsed();
+    out.im.save("output.png");
+    if write_aovs {
+        aov::save_aovs("output", target_width, target_height, &render_passes, aov_format, &out.hdr).unwrap();
+    }
+    let save_finish = start.elapsed();
+
+    report.stages.push(("rendering", run_finished));
+    report.stages.push(("merging lines", merge_time));
+    if denoise_strength > 0.0 {
+        report.stages.push(("denoising", denoise_finish - line_concat_finish));
+    }
+    if post.enabled() {
+        report.stages.push(("post effects", post_finish - denoise_finish));
+    }
+    report.stages.push(("saving", save_finish - post_finish));
+    report.stages.push(("total", save_finish));
+    print_report(&mut report, stats_json);
+    println!("File saved as    output.png");
+}
+
+fn print_report(report: &mut Report, json_path: Option<&str>) {
+    report.peak_memory = stats::peak_memory();
+    print!("{}", report.text());
+    if let Some(path) = json_path {
+        std::fs::write(path, report.json()).unwrap();
+        println!("Stats saved as   {path}");
+    }
+}
+
+// Same conversion as raytrace, gamma 2 and clamped to 8 bits
+fn to_rgb8(width: u32, height: u32, rgb: &[f32]) -> RgbImage {
+    let raw = rgb.iter().map(|v| (v.max(0.0).sqrt() * 255.0) as u8).collect();
+    return RgbImage::from_raw(width, height, raw).unwrap();
+}
+
+// Replaces the beauty in the frame's image and HDR buffer, returns the old image
+fn set_beauty(out: &mut RenderedFrame, width: u32, height: u32, passes: &[Aov], rgb: &[f32]) -> RgbImage {
+    for (px, c) in out.hdr.chunks_exact_mut(aov::stride(passes)).zip(rgb.chunks_exact(3)) {
+        px[..3].copy_from_slice(c);
+    }
+    return std::mem::replace(&mut out.im, to_rgb8(width, height, rgb));
+}
+
+// Puts the denoised beauty in the frame, returns the noisy image
+fn denoise_frame(out: &mut RenderedFrame, denoiser: &Denoiser, width: u32, height: u32, passes: &[Aov]) -> RgbImage {
+    let rgb = denoiser.denoise(width, height, passes, &out.hdr);
+    return set_beauty(out, width, height, passes, &rgb);
+}
+
+fn post_frame(out: &mut RenderedFrame, post: &PostEffects, width: u32, height: u32, passes: &[Aov], rng: &mut ChaCha20Rng) {
+    let rgb = out.hdr.chunks_exact(aov::stride(passes)).flat_map(|px| [px[0], px[1], px[2]]).collect();
+    let mut im = HdrImage { width: width as usize, height: height as usize, rgb };
+    post.apply(&mut im, rng);
+    set_beauty(out, width, height, passes, &im.rgb);
+}
+
+fn side_by_side(left: &RgbImage, right: &RgbImage) -> RgbImage {
+    let mut im = RgbImage::new(left.width() + right.width(), left.height().max(right.height()));
+    image::imageops::replace(&mut im, left, 0, 0);
+    image::imageops::replace(&mut im, right, left.width() as i64, 0);
+    return im;
+}
+
+struct RenderedFrame {
+    im: RgbImage,
+    // Linear beauty and AOV channels per pixel when asked for, see aov::stride
+    hdr: Vec<f32>,
+    render_time: Duration,
+    merge_time: Duration,
+    threads: Vec<ThreadStats>,
+}
 
-    let mut smats = Arc::new(mats);
-    let mut sworld = Arc::new(world);
-    let mut scam = Arc::new(cam);
+// Renders one image with a thread per core pulling lines off a shared counter
+fn render_frame(
+    target_width: u32,
+    target_height: u32,
+    samples_per_pixel: u16,
+    max_depth: u16,
+    filter: &PixelFilter,
+    sampler: &SamplerConfig,
+    packet_tracing: bool,
+    wavefront: bool,
+    aovs: &[Aov],
+    keep_hdr: bool,
+    mprog: &MultiProgress,
+    label: &str,
+    smats: &Arc<MatManager>,
+    sworld: &Arc<HittableList>,
+    scam: &Arc<Camera>,
+) -> RenderedFrame {
+    let bar = Arc::new(mprog.add(ProgressBar::new(target_height as u64)));
+    format_bar(&bar);
+    bar.set_message(label.to_string());
 
     let counter = RelaxedCounter::new(0);
+    let film = if filter.is_pixel_box() { None } else { Some(Film::new(target_width as usize, target_height as usize)) };
 
     let start = Instant::now();
-    let mut rets = (0..num_cpus::get()) //target_height)
+    let (lines, threads): (Vec<Vec<RtRet>>, Vec<ThreadStats>) = (0..num_cpus::get()) //target_height)
         .into_par_iter()
         .map(capture_it::capture!(
             [
@@ -163,6 +498,13 @@ fn main() {
                 target_height,
                 samples_per_pixel,
                 max_depth,
+                &filter,
+                &sampler,
+                packet_tracing,
+                wavefront,
+                &film,
+                &aovs,
+                keep_hdr,
                 &bar,
                 &smats,
                 &sworld,
@@ -174,6 +516,13 @@ fn main() {
                 target_height,
                 samples_per_pixel,
                 max_depth,
+                filter,
+                sampler,
+                packet_tracing,
+                wavefront,
+                film,
+                aovs,
+                keep_hdr,
                 counter,
                 bar,
                 smats,
@@ -182,45 +531,39 @@ fn main() {
                 line
             )
         ))
-        .collect::<Vec<Vec<RtRet>>>()
-        .join(&RtRet {
-            im: vec![],
-            i_id: usize::MAX,
-        });
+        .unzip();
+    let mut rets = lines.join(&RtRet {
+        im: vec![],
+        hdr: vec![],
+        i_id: usize::MAX,
+    });
     let run_finished = start.elapsed();
 
     let bar1 = mprog.add(ProgressBar::new(target_height as u64));
     format_bar(&bar1);
     bar1.set_message("Merge Lines");
     let mut im_raw: Vec<u8> = vec![];
+    let mut hdr: Vec<f32> = vec![];
     rets.sort_by_key(|x| x.i_id);
     for mut retv in rets {
         im_raw.append(&mut retv.im);
+        hdr.append(&mut retv.hdr);
         bar1.inc(1);
     }
     let line_concat_finish = start.elapsed();
 
-    let im_raw_len = im_raw.len();
-    let expected = target_width * target_height * 3;
     let im = RgbImage::from_raw(target_width, target_height, im_raw).unwrap();
-    im.save("output.png");
-    let save_finish = start.elapsed();
-
-    let runtime = format_duration(run_finished);
-    let mergetime = format_duration(line_concat_finish - run_finished);
-    let savetime = format_duration(save_finish - line_concat_finish);
-    let totaltime = format_duration(save_finish);
-
-    println!("Rendering took   {runtime}");
-    println!("Merge lines took {mergetime}");
-    println!("Saving took      {savetime}");
-    println!("Total time taken {totaltime}");
-    println!("File saved as    output.png");
+    let mut out = RenderedFrame { im, hdr, render_time: run_finished, merge_time: line_concat_finish - run_finished, threads };
+    if let Some(film) = &film {
+        set_beauty(&mut out, target_width, target_height, aovs, &film.resolve());
+    }
+    return out;
 }
 
 #[derive(Clone)]
 struct RtRet {
     im: Vec<u8>,
+    hdr: Vec<f32>,
     i_id: usize,
 }
 
@@ -229,13 +572,20 @@ fn raytrace(
     target_height: u32,
     samples_per_pixel: u16,
     max_depth: u16,
+    filter: &PixelFilter,
+    sampler: &SamplerConfig,
+    packet_tracing: bool,
+    wavefront: bool,
+    film: &Option<Film>,
+    aovs: &[Aov],
+    keep_hdr: bool,
     y_counter: &RelaxedCounter,
     bar: &Arc<ProgressBar>,
     mats: &MatManager,
     world: &HittableList,
     cam: &Camera,
     threadid: usize
-) -> Vec<RtRet> {
+) -> (Vec<RtRet>, ThreadStats) {
     ThreadPriority::Max.set_for_current().ok();
     let priority = std::thread::current().get_priority().unwrap().to_posix(ThreadSchedulePolicy::Normal(NormalThreadSchedulePolicy::Other)).unwrap();
     println!("Thread {threadid} priority {priority}");
@@ -252,11 +602,21 @@ fn raytrace(
     //scene.setup(&mut world, &mut cam, &mut mats, &mut aspect_ratio, &mut rng);
     //let y_iter = split_vec_into_n_groups_and_get_n((0 as u32..target_height as u32).collect::<Vec<u32>>(), num_cpus::get(), thread_num);
     //let y_offset = y_iter[0].clone();
-    let mut seed1: <ChaCha20Rng as SeedableRng>::Seed = Default::default();
-    thread_rng().fill(&mut seed1);
-    let mut rng = ChaCha20Rng::from_seed(seed1);
+    // A sampler per pixel of a packet, each keeps its own pixel's sequence
+    let group = if packet_tracing { packet::LANES } else { 1 };
+    let mut samplers: Vec<Box<dyn Sampler>> = (0..group)
+        .map(|_| {
+            let mut seed1: <ChaCha20Rng as SeedableRng>::Seed = Default::default();
+            thread_rng().fill(&mut seed1);
+            sampler.build(samples_per_pixel as u32, ChaCha20Rng::from_seed(seed1))
+        })
+        .collect();
+    let mut wavefront = if wavefront && aovs.is_empty() { Some(Wavefront::new(sampler, samples_per_pixel as u32)) } else { None };
 
     let mut ims = vec![];
+    // Drops whatever an earlier frame left on this thread
+    stats::take();
+    let busy_start = Instant::now();
 
     loop {
         // -------
@@ -272,24 +632,71 @@ fn raytrace(
         // ---------------
         //let mut im = RgbImage::new(target_width, /*target_height*/ 1);
         let mut im = vec![];
+        let mut hdr = vec![];
+        let mut block = film.as_ref().map(|film| film.block(y as usize, filter));
 
         // ------------------
         //  RT Without the X
         // ------------------
         let scale = 1.0 / samples_per_pixel as f32;
-        for x in 0..target_width {
-            let mut pix_color = Color {
-                r: 0.0,
-                g: 0.0,
-                b: 0.0,
+        let mut pix_colors = vec![f32x4::splat(0.0); target_width as usize];
+        let mut pix_aovs: Vec<AovPixel> = (0..target_width).map(|_| AovPixel::new()).collect();
+        if let Some(wavefront) = &mut wavefront {
+            wavefront.render_line(y, target_width, target_height, samples_per_pixel, max_depth, packet_tracing, cam, world, mats, |x, sx, sy, sample_color| {
+                pix_colors[x as usize] += sample_color;
+                if let Some(block) = &mut block {
+                    block.splat(filter, sx, sy, [sample_color[0], sample_color[1], sample_color[2]]);
+                }
+            });
+        } else {
+            for x0 in (0..target_width).step_by(group) {
+                let pixels = (target_width - x0).min(group as u32) as usize;
+                for sample in 0..samples_per_pixel {
+                    let mut film_pos = vec![];
+                    let mut rays = vec![];
+                    for (i, sampler) in samplers.iter_mut().take(pixels).enumerate() {
+                        sampler.start_pixel_sample(x0 + i as u32, y, sample as u32);
+                        let (jx, jy) = sampler.get_2d();
+                        let (sx, sy) = ((x0 + i as u32) as f32 + jx, y as f32 + jy);
+                        let u = sx / (target_width - 1) as f32;
+                        let v = 1.0 - (sy / (target_height - 1) as f32);
+                        film_pos.push((sx, sy));
+                        rays.push(cam.generate_ray(u, v, sampler.as_mut()));
+                    }
+                    let hits = if packet_tracing {
+                        packet::closest_hits(world, &rays)
+                    } else {
+                        rays.iter().map(|r| closest_hit(world, r.as_ref()?)).collect()
+                    };
+
+                    for (i, sampler) in samplers.iter_mut().take(pixels).enumerate() {
+                        let mut sample_color = f32x4::splat(0.0);
+                        if let Some(r) = &rays[i] {
+                            let traced = stats::rays_traced();
+                            stats::count_ray(RayKind::Camera);
+                            if aovs.is_empty() {
+                                sample_color = shade(r, hits[i], &world, sampler.as_mut(), &mats, max_depth as u64);
+                            } else {
+                                let mut sample = AovSample::default();
+                                sample_color = shade_aovs(r, hits[i], &world, sampler.as_mut(), &mats, max_depth as u64, &mut sample);
+                                pix_aovs[x0 as usize + i].add(&sample);
+                            }
+                            stats::record_path(stats::rays_traced() - traced);
+                        }
+                        pix_colors[x0 as usize + i] += sample_color;
+                        if let Some(block) = &mut block {
+                            let (sx, sy) = film_pos[i];
+                            block.splat(filter, sx, sy, [sample_color[0], sample_color[1], sample_color[2]]);
+                        }
+                    }
+                }
             }
-            .to_simd4();
-            for _sample in 0..samples_per_pixel {
-                let u = (x as f32 + rand_double(&mut rng)) / (target_width - 1) as f32;
-                let v = 1.0 - ((y as f32 + rand_double(&mut rng)) / (target_height - 1) as f32);
-                let r = cam.get_ray(u, v, &mut rng);
-                let c = ray_color(&r, &world, &mut rng, &mats, max_depth as u64);
-                pix_color += c;
+        }
+
+        for (mut pix_color, pix_aov) in pix_colors.into_iter().zip(pix_aovs.iter()) {
+            if keep_hdr {
+                hdr.extend([pix_color[0] * scale, pix_color[1] * scale, pix_color[2] * scale]);
+                pix_aov.write(aovs, samples_per_pixel, &mut hdr);
             }
             //im.put_pixel(
             //    x,
@@ -311,15 +718,19 @@ fn raytrace(
             //im.push(pix_color[1] as u8);
             //im.push(pix_color[2] as u8);
         }
+        if let (Some(film), Some(block)) = (film, &block) {
+            film.add(block);
+        }
         bar.inc(1);
 
         ims.push(RtRet {
             im: im.iter().map(|&e| e as u8).collect(),
+            hdr,
             i_id: y as usize,
         });
     }
 
     //let mut raw_out = im.into_raw();
     //raw_out.shrink_to_fit();
-    return ims;
+    return (ims, ThreadStats { counters: stats::take(), busy: busy_start.elapsed() });
 }
@@ -0,0 +1,140 @@
+use std::ops::Mul;
+
+use crate::{utils::deg_to_rad, vec3::Vec3};
+
+// Row major 4x4 matrix, points are column vectors (p' = M * p)
+#[derive(Clone, Copy)]
+pub struct Mat4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Mul<Mat4> for Mat4 {
+    type Output = Self;
+    fn mul(self, _rhs: Self) -> Self {
+        let mut out = [[0.0; 4]; 4];
+        for r in 0..4 {
+            for c in 0..4 {
+                out[r][c] = (0..4).map(|k| self.m[r][k] * _rhs.m[k][c]).sum();
+            }
+        }
+        return Mat4 { m: out };
+    }
+}
+
+impl Mat4 {
+    pub fn identity() -> Mat4 {
+        return Mat4 {
+            m: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+    }
+
+    pub fn translate(offset: Vec3) -> Mat4 {
+        let mut out = Self::identity();
+        out.m[0][3] = offset.x;
+        out.m[1][3] = offset.y;
+        out.m[2][3] = offset.z;
+        return out;
+    }
+
+    pub fn scale(factor: Vec3) -> Mat4 {
+        let mut out = Self::identity();
+        out.m[0][0] = factor.x;
+        out.m[1][1] = factor.y;
+        out.m[2][2] = factor.z;
+        return out;
+    }
+
+    // Rotation of `deg` degrees around an arbitrary axis (Rodrigues)
+    pub fn rotate(axis: Vec3, deg: f32) -> Mat4 {
+        let a = axis.unit_vector();
+        let (s, c) = deg_to_rad(deg).sin_cos();
+        let t = 1.0 - c;
+        return Mat4 {
+            m: [
+                [t * a.x * a.x + c, t * a.x * a.y - s * a.z, t * a.x * a.z + s * a.y, 0.0],
+                [t * a.x * a.y + s * a.z, t * a.y * a.y + c, t * a.y * a.z - s * a.x, 0.0],
+                [t * a.x * a.z - s * a.y, t * a.y * a.z + s * a.x, t * a.z * a.z + c, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+    }
+    pub fn rotate_x(deg: f32) -> Mat4 {
+        return Self::rotate(Vec3::newi(1, 0, 0), deg);
+    }
+    pub fn rotate_y(deg: f32) -> Mat4 {
+        return Self::rotate(Vec3::newi(0, 1, 0), deg);
+    }
+    pub fn rotate_z(deg: f32) -> Mat4 {
+        return Self::rotate(Vec3::newi(0, 0, 1), deg);
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut out = [[0.0; 4]; 4];
+        for r in 0..4 {
+            for c in 0..4 {
+                out[r][c] = self.m[c][r];
+            }
+        }
+        return Mat4 { m: out };
+    }
+
+    // Gauss-Jordan with partial pivoting, None for singular matrices
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.m;
+        let mut inv = Self::identity().m;
+
+        for col in 0..4 {
+            let mut pivot = col;
+            for r in (col + 1)..4 {
+                if a[r][col].abs() > a[pivot][col].abs() {
+                    pivot = r;
+                }
+            }
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let d = 1.0 / a[col][col];
+            for c in 0..4 {
+                a[col][c] *= d;
+                inv[col][c] *= d;
+            }
+            for r in 0..4 {
+                if r != col {
+                    let f = a[r][col];
+                    for c in 0..4 {
+                        a[r][c] -= f * a[col][c];
+                        inv[r][c] -= f * inv[col][c];
+                    }
+                }
+            }
+        }
+
+        return Some(Mat4 { m: inv });
+    }
+
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        let m = &self.m;
+        return Vec3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        );
+    }
+
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        return Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        );
+    }
+}
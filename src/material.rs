@@ -1,11 +1,17 @@
 use std::simd::f32x4;
 
-use rand_chacha::ChaCha20Rng;
-
-use crate::{ray::Ray, color::Color, hittable::HitRecord, vec3::Vec3};
+use crate::{ray::Ray, color::Color, hittable::HitRecord, sampler::Sampler, vec3::Vec3};
 
 pub trait Material {
-    fn scatter(&self, ray_in: &Ray, rec: &HitRecord, attenuation: &mut f32x4, scattered: &mut Ray, rng: &mut ChaCha20Rng) -> bool;
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord, attenuation: &mut f32x4, scattered: &mut Ray, sampler: &mut dyn Sampler) -> bool;
+    // Solid angle density scatter() picks `scattered` with, 0 where it isn't a density (mirrors, glass)
+    #[allow(unused_variables)]
+    fn scatter_pdf(&self, ray_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        return 0.0;
+    }
+    // Runs before emitted() and scatter(), normal and bump maps bend rec.shading_normal here
+    #[allow(unused_variables)]
+    fn perturb_normal(&self, rec: &mut HitRecord) {}
     #[allow(unused_variables)]
     fn emitted(&self, u: f32, v: f32, p: &Vec3) -> f32x4 {
         return Color::new(0.0, 0.0, 0.0).to_simd4();
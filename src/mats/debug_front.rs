@@ -1,7 +1,8 @@
 use std::simd::f32x4;
 
 use crate::{
-    color::Color, hittable::HitRecord, material::Material, ray::Ray, utils::random_in_hemisphere,
+    color::Color, hittable::HitRecord, material::Material, ray::Ray, sampler::Sampler, utils::keep_above,
+    warps::{cosine_hemisphere, to_world},
 };
 
 #[derive(Clone, Copy)]
@@ -15,15 +16,11 @@ impl Material for DebugFrontMat {
         rec: &HitRecord,
         attenuation: &mut f32x4,
         scattered: &mut crate::ray::Ray,
-        rng: &mut rand_chacha::ChaCha20Rng,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        let mut scatter_dir = rec.normal + random_in_hemisphere(&rec.normal, rng);
+        let scatter_dir = to_world(cosine_hemisphere(sampler.get_2d()), &rec.shading_normal);
 
-        if scatter_dir.near_zero() {
-            scatter_dir = rec.normal;
-        }
-
-        *scattered = Ray::new(rec.point, scatter_dir);
+        *scattered = Ray::new(rec.point, keep_above(scatter_dir, &rec.normal), ray_in.time);
         *attenuation = if rec.front_face {
             Color::new_01_range(1.0, 0.0, 0.0).to_simd4()
         } else {
@@ -5,7 +5,8 @@ use crate::{
     hittable::HitRecord,
     material::Material,
     ray::Ray,
-    utils::{refract, reflect}, vec3::Vec3, rand_double::rand_double,
+    sampler::Sampler,
+    utils::{keep_above, refract, reflect}, vec3::Vec3,
 };
 
 #[derive(Clone, Copy)]
@@ -21,7 +22,7 @@ impl Material for DielectricMat {
         rec: &HitRecord,
         attenuation: &mut f32x4,
         scattered: &mut crate::ray::Ray,
-        rng: &mut rand_chacha::ChaCha20Rng,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         *attenuation = Color::new_01_range(1.0, 1.0, 1.0).to_simd4();
         let refraction_ratio: f32 = if rec.front_face {
@@ -31,19 +32,20 @@ impl Material for DielectricMat {
         };
 
         let unit_dir = ray_in.dir.unit_vector();
-        let cos_theta = f32::min((-unit_dir).dot_prod(rec.normal), 1.0);
+        let n = rec.shading_normal;
+        let cos_theta = f32::min((-unit_dir).dot_prod(n), 1.0);
         let sin_theta = (1.0 - cos_theta*cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
         let direction: Vec3;
 
-        if cannot_refract || (DielectricMat::reflectance(self, cos_theta, refraction_ratio) > rand_double(rng)) {
-            direction = reflect(&unit_dir, &rec.normal);
+        if cannot_refract || (DielectricMat::reflectance(self, cos_theta, refraction_ratio) > sampler.get_1d()) {
+            direction = keep_above(reflect(&unit_dir, &n), &rec.normal);
         } else {
-            direction = refract(&unit_dir, &rec.normal, refraction_ratio);
+            direction = keep_above(refract(&unit_dir, &n, refraction_ratio), &-rec.normal);
         }
 
-        *scattered = Ray::new(rec.point, direction);
+        *scattered = Ray::new(rec.point, direction, ray_in.time);
         return true;
     }
 }
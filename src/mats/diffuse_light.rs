@@ -1,7 +1,7 @@
 use std::simd::f32x4;
 
 use crate::{
-    color::Color, hittable::HitRecord, material::Material, ray::Ray, utils::random_in_hemisphere,
+    color::Color, hittable::HitRecord, material::Material, ray::Ray, sampler::Sampler,
 };
 
 #[derive(Clone, Copy)]
@@ -17,7 +17,7 @@ impl Material for DiffuseLight {
         rec: &HitRecord,
         attenuation: &mut f32x4,
         scattered: &mut crate::ray::Ray,
-        rng: &mut rand_chacha::ChaCha20Rng,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         return false;
     }
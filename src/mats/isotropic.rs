@@ -0,0 +1,27 @@
+use std::simd::f32x4;
+
+use crate::{
+    color::Color, hittable::HitRecord, material::Material, ray::Ray, sampler::Sampler, warps::uniform_sphere,
+};
+
+// Phase function for participating media, scatters uniformly in every direction
+#[derive(Clone, Copy)]
+pub struct IsotropicMat {
+    pub albedo: Color,
+}
+
+impl Material for IsotropicMat {
+    #[allow(unused_variables)]
+    fn scatter(
+        &self,
+        ray_in: &crate::ray::Ray,
+        rec: &HitRecord,
+        attenuation: &mut f32x4,
+        scattered: &mut crate::ray::Ray,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        *scattered = Ray::new(rec.point, uniform_sphere(sampler.get_2d()), ray_in.time);
+        *attenuation = self.albedo.to_simd4();
+        return true;
+    }
+}
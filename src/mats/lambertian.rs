@@ -1,15 +1,28 @@
 use std::simd::f32x4;
 
 use crate::{
-    color::Color, hittable::HitRecord, material::Material, ray::Ray, utils::random_in_hemisphere,
+    color::Color,
+    hittable::HitRecord,
+    material::Material,
+    ray::Ray,
+    sampler::Sampler,
+    utils::keep_above,
+    vec3::Vec3,
+    warps::{cosine_hemisphere, cosine_hemisphere_pdf, to_world},
 };
 
+// Ideal diffuse surface. A `roughness` above 0 turns it into Oren-Nayar's rough diffuse model with that
+// standard deviation of microfacet slopes in radians, which looks flatter and brighter towards grazing
+// light like clay, plaster or concrete. Around 0.3 to 0.6 for those.
 #[derive(Clone, Copy)]
 pub struct LambertianMat {
     pub albedo: Color,
+    pub roughness: f32,
 }
 
 impl Material for LambertianMat {
+    // Samples the cosine lobe exactly, so the cosine and pdf cancel out of the weight and only the
+    // Oren-Nayar term is left over
     #[allow(unused_variables)]
     fn scatter(
         &self,
@@ -17,16 +30,42 @@ impl Material for LambertianMat {
         rec: &HitRecord,
         attenuation: &mut f32x4,
         scattered: &mut crate::ray::Ray,
-        rng: &mut rand_chacha::ChaCha20Rng,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        let mut scatter_dir = rec.normal + random_in_hemisphere(&rec.normal, rng);
+        let scatter_dir = to_world(cosine_hemisphere(sampler.get_2d()), &rec.shading_normal);
 
-        if scatter_dir.near_zero() {
-            scatter_dir = rec.normal;
-        }
-
-        *scattered = Ray::new(rec.point, scatter_dir);
+        *scattered = Ray::new(rec.point, keep_above(scatter_dir, &rec.normal), ray_in.time);
         *attenuation = self.albedo.to_simd4();
+        if self.roughness > 0.0 {
+            let to_eye = -ray_in.dir.unit_vector();
+            *attenuation *= f32x4::splat(self.oren_nayar(&rec.shading_normal, &to_eye, &scatter_dir));
+        }
         return true;
     }
+
+    #[allow(unused_variables)]
+    fn scatter_pdf(&self, ray_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        return cosine_hemisphere_pdf(rec.shading_normal.dot_prod(scattered.dir.unit_vector()));
+    }
+}
+
+impl LambertianMat {
+    // Qualitative Oren-Nayar, the rough BRDF over the Lambertian one. Both directions point away
+    // from the surface.
+    fn oren_nayar(&self, n: &Vec3, wi: &Vec3, wo: &Vec3) -> f32 {
+        let sigma2 = self.roughness * self.roughness;
+        let a = 1.0 - 0.5 * sigma2 / (sigma2 + 0.33);
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let (cos_i, cos_o) = (n.dot_prod(*wi).clamp(0.0, 1.0), n.dot_prod(*wo).clamp(0.0, 1.0));
+        let (sin_i, sin_o) = ((1.0 - cos_i * cos_i).sqrt(), (1.0 - cos_o * cos_o).sqrt());
+        if sin_i < 1e-4 || sin_o < 1e-4 {
+            return a;
+        }
+        // Cosine of the azimuth between them from their projections onto the surface
+        let cos_phi = ((*wi - cos_i * *n).dot_prod(*wo - cos_o * *n) / (sin_i * sin_o)).max(0.0);
+        // sin(alpha) tan(beta), alpha the larger and beta the smaller of the two angles to the normal
+        let sin_alpha_tan_beta = sin_i * sin_o / cos_i.max(cos_o).max(1e-4);
+        return a + b * cos_phi * sin_alpha_tan_beta;
+    }
 }
@@ -1,7 +1,7 @@
 use std::simd::f32x4;
 
 use crate::{
-    color::Color, hittable::HitRecord, material::Material, ray::Ray, utils::{reflect, random_in_unit_sphere},
+    color::Color, hittable::HitRecord, material::Material, ray::Ray, sampler::Sampler, utils::{keep_above, reflect}, warps::uniform_ball,
 };
 
 #[derive(Clone, Copy)]
@@ -18,10 +18,11 @@ impl Material for MetalMat {
         rec: &HitRecord,
         attenuation: &mut f32x4,
         scattered: &mut crate::ray::Ray,
-        rng: &mut rand_chacha::ChaCha20Rng,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        let reflected = reflect(&ray_in.dir.unit_vector(), &rec.normal);
-        *scattered = Ray::new(rec.point, reflected + self.fuzz * random_in_unit_sphere(rng));
+        let reflected = reflect(&ray_in.dir.unit_vector(), &rec.shading_normal);
+        let dir = reflected + self.fuzz * uniform_ball(sampler.get_2d(), sampler.get_1d());
+        *scattered = Ray::new(rec.point, keep_above(dir, &rec.normal), ray_in.time);
         *attenuation = self.albedo.to_simd4();
         return true;
     }
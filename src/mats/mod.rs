@@ -3,6 +3,10 @@ pub mod metal;
 pub mod dielectric;
 pub mod diffuse_light;
 pub mod debug_front;
+pub mod isotropic;
+pub mod vertex_color;
+pub mod pbr;
+pub mod normal_map;
 
 use std::collections::HashMap;
 use crate::{material::Material, color::Color};
@@ -22,6 +26,7 @@ impl MatManager {
                 g: 0.5,
                 b: 0.5,
             },
+            roughness: 0.0,
         }));
         return tmp;
     }
@@ -0,0 +1,124 @@
+use std::{simd::f32x4, sync::Arc};
+
+use crate::{
+    hittable::HitRecord,
+    material::Material,
+    ray::Ray,
+    sampler::Sampler,
+    texture::ImageTexture,
+    vec3::Vec3,
+};
+
+// Tangent space normal map. The map's +x runs along dp/du and +y up the image (decreasing v),
+// the OpenGL and glTF layout; set `flip_green` for DirectX style maps.
+pub fn apply_normal_map(rec: &mut HitRecord, map: &ImageTexture, strength: f32, flip_green: bool) {
+    let n = rec.shading_normal;
+    if rec.dpdu.length_squared() == 0.0 {
+        return;
+    }
+    let t = (rec.dpdu - n * n.dot_prod(rec.dpdu)).unit_vector();
+    let mut b = n.cross_prod(t);
+    if b.dot_prod(rec.dpdv) > 0.0 {
+        b = -b;
+    }
+    if flip_green {
+        b = -b;
+    }
+
+    let texel = map.sample(rec.tex_u, rec.tex_v);
+    let (x, y, z) = (2.0 * texel[0] - 1.0, 2.0 * texel[1] - 1.0, 2.0 * texel[2] - 1.0);
+    let mapped = (t * (x * strength) + b * (y * strength) + n * z).unit_vector();
+    // A map can tip the normal past the silhouette, keep the unmapped one there
+    if mapped.dot_prod(rec.normal) > 0.0 {
+        rec.shading_normal = mapped;
+    }
+}
+
+// Bump map from the red channel of a height texture, `scale` is the height of a white texel in
+// world units. Displaces the surface derivatives along the normal and rebuilds the normal from them.
+pub fn apply_bump_map(rec: &mut HitRecord, height: &ImageTexture, scale: f32) {
+    let n = rec.shading_normal;
+    if rec.dpdu.length_squared() == 0.0 || rec.dpdv.length_squared() == 0.0 {
+        return;
+    }
+    let du = 1.0 / height.width as f32;
+    let dv = 1.0 / height.height as f32;
+    let h = height.sample(rec.tex_u, rec.tex_v)[0];
+    let dhdu = scale * (height.sample(rec.tex_u + du, rec.tex_v)[0] - h) / du;
+    let dhdv = scale * (height.sample(rec.tex_u, rec.tex_v + dv)[0] - h) / dv;
+
+    let dpdu = rec.dpdu + n * dhdu;
+    let dpdv = rec.dpdv + n * dhdv;
+    let mut bumped = dpdu.cross_prod(dpdv);
+    if bumped.length_squared() == 0.0 {
+        return;
+    }
+    // dp/du x dp/dv only points out for right handed parameterisations, measure the flip on the
+    // unbumped frame so the bumps don't invert
+    if rec.dpdu.cross_prod(rec.dpdv).dot_prod(n) < 0.0 {
+        bumped = -bumped;
+    }
+    let bumped = bumped.unit_vector();
+    if bumped.dot_prod(rec.normal) > 0.0 {
+        rec.shading_normal = bumped;
+    }
+}
+
+// Wraps another material and bends its shading normal with a tangent space normal map
+pub struct NormalMapMat {
+    pub inner: Box<dyn Material>,
+    pub map: Arc<ImageTexture>,
+    pub strength: f32,
+    pub flip_green: bool,
+}
+
+// Wraps another material and bends its shading normal with a height map
+pub struct BumpMapMat {
+    pub inner: Box<dyn Material>,
+    pub height: Arc<ImageTexture>,
+    pub scale: f32,
+}
+
+impl Material for NormalMapMat {
+    fn perturb_normal(&self, rec: &mut HitRecord) {
+        self.inner.perturb_normal(rec);
+        apply_normal_map(rec, &self.map, self.strength, self.flip_green);
+    }
+
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut f32x4,
+        scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        return self.inner.scatter(ray_in, rec, attenuation, scattered, sampler);
+    }
+
+    fn emitted(&self, u: f32, v: f32, p: &Vec3) -> f32x4 {
+        return self.inner.emitted(u, v, p);
+    }
+}
+
+impl Material for BumpMapMat {
+    fn perturb_normal(&self, rec: &mut HitRecord) {
+        self.inner.perturb_normal(rec);
+        apply_bump_map(rec, &self.height, self.scale);
+    }
+
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut f32x4,
+        scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        return self.inner.scatter(ray_in, rec, attenuation, scattered, sampler);
+    }
+
+    fn emitted(&self, u: f32, v: f32, p: &Vec3) -> f32x4 {
+        return self.inner.emitted(u, v, p);
+    }
+}
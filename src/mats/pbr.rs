@@ -0,0 +1,116 @@
+use std::{simd::f32x4, sync::Arc};
+
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::Material,
+    mats::normal_map::apply_normal_map,
+    ray::Ray,
+    sampler::Sampler,
+    texture::ImageTexture,
+    utils::{keep_above, reflect},
+    vec3::Vec3,
+    warps::{cosine_hemisphere, to_world, uniform_ball},
+};
+
+// glTF style metallic-roughness material. Factors multiply the matching texture when one is set,
+// roughness is read from the green channel and metallic from blue as the spec lays them out.
+pub struct PbrMat {
+    pub base_color: f32x4,
+    pub base_color_tex: Option<Arc<ImageTexture>>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub metallic_roughness_tex: Option<Arc<ImageTexture>>,
+    pub emissive: Color,
+    pub emissive_tex: Option<Arc<ImageTexture>>,
+    pub normal_tex: Option<Arc<ImageTexture>>,
+    pub normal_scale: f32,
+}
+
+impl PbrMat {
+    // Vertex colors (COLOR_0) multiply the base color as well
+    fn base_color(&self, rec: &HitRecord) -> f32x4 {
+        let mut color = match &self.base_color_tex {
+            Some(tex) => self.base_color * tex.sample(rec.tex_u, rec.tex_v),
+            None => self.base_color,
+        };
+        if let Some(vert_color) = rec.vert_color {
+            color = color * vert_color.to_simd4();
+        }
+        return color * f32x4::from_array([1.0, 1.0, 1.0, 0.0]);
+    }
+
+    fn metallic_roughness(&self, u: f32, v: f32) -> (f32, f32) {
+        return match &self.metallic_roughness_tex {
+            Some(tex) => {
+                let texel = tex.sample(u, v);
+                (self.metallic * texel[2], self.roughness * texel[1])
+            }
+            None => (self.metallic, self.roughness),
+        };
+    }
+}
+
+impl Material for PbrMat {
+    fn perturb_normal(&self, rec: &mut HitRecord) {
+        if let Some(tex) = &self.normal_tex {
+            apply_normal_map(rec, tex, self.normal_scale, false);
+        }
+    }
+
+    // Picks one lobe per bounce: metal reflection with probability `metallic`, otherwise a
+    // Schlick weighted dielectric coat over a diffuse base
+    fn scatter(
+        &self,
+        ray_in: &crate::ray::Ray,
+        rec: &HitRecord,
+        attenuation: &mut f32x4,
+        scattered: &mut crate::ray::Ray,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let base = self.base_color(rec);
+        let (metallic, roughness) = self.metallic_roughness(rec.tex_u, rec.tex_v);
+        let fuzz = roughness * roughness;
+        let unit_dir = ray_in.dir.unit_vector();
+
+        let glossy = |sampler: &mut dyn Sampler| -> Option<Vec3> {
+            let dir = reflect(&unit_dir, &rec.shading_normal) + fuzz * uniform_ball(sampler.get_2d(), sampler.get_1d());
+            if dir.dot_prod(rec.normal) > 0.0 { Some(dir) } else { None }
+        };
+
+        if sampler.get_1d() < metallic {
+            return match glossy(sampler) {
+                Some(dir) => {
+                    *scattered = Ray::new(rec.point, dir, ray_in.time);
+                    *attenuation = base;
+                    true
+                }
+                None => false,
+            };
+        }
+
+        let cos_theta = f32::min((-unit_dir).dot_prod(rec.shading_normal), 1.0);
+        let fresnel = 0.04 + 0.96 * (1.0 - cos_theta).powf(5.0);
+        if sampler.get_1d() < fresnel {
+            if let Some(dir) = glossy(sampler) {
+                *scattered = Ray::new(rec.point, dir, ray_in.time);
+                *attenuation = Color::new_01_range(1.0, 1.0, 1.0).to_simd4();
+                return true;
+            }
+        }
+
+        let scatter_dir = to_world(cosine_hemisphere(sampler.get_2d()), &rec.shading_normal);
+        *scattered = Ray::new(rec.point, keep_above(scatter_dir, &rec.normal), ray_in.time);
+        *attenuation = base;
+        return true;
+    }
+
+    #[allow(unused_variables)]
+    fn emitted(&self, u: f32, v: f32, p: &Vec3) -> f32x4 {
+        let emissive = self.emissive.to_simd4();
+        return match &self.emissive_tex {
+            Some(tex) => emissive * tex.sample(u, v),
+            None => emissive,
+        };
+    }
+}
@@ -0,0 +1,30 @@
+use std::simd::f32x4;
+
+use crate::{
+    color::Color, hittable::HitRecord, material::Material, ray::Ray, sampler::Sampler, utils::keep_above,
+    warps::{cosine_hemisphere, to_world},
+};
+
+// Lambertian whose albedo comes from interpolated vertex colors, `fallback` where a mesh has none
+#[derive(Clone, Copy)]
+pub struct VertexColorMat {
+    pub fallback: Color,
+}
+
+impl Material for VertexColorMat {
+    #[allow(unused_variables)]
+    fn scatter(
+        &self,
+        ray_in: &crate::ray::Ray,
+        rec: &HitRecord,
+        attenuation: &mut f32x4,
+        scattered: &mut crate::ray::Ray,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let scatter_dir = to_world(cosine_hemisphere(sampler.get_2d()), &rec.shading_normal);
+
+        *scattered = Ray::new(rec.point, keep_above(scatter_dir, &rec.normal), ray_in.time);
+        *attenuation = rec.vert_color.unwrap_or(self.fallback).to_simd4();
+        return true;
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    f32::{
+        consts::{FRAC_PI_2, FRAC_PI_4, PI},
+        INFINITY,
+    },
+    simd::{cmp::{SimdPartialEq, SimdPartialOrd}, num::SimdFloat, Mask, Select, Simd, StdFloat},
+    time::Instant,
+};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    camera::Camera,
+    hittable::{HitRecord, Hittable, HittableList},
+    ray::Ray,
+    sampler::SamplerConfig,
+    vec3::Vec3,
+};
+
+// Rays per packet, picked at compile time from the widest float vectors the target has: 8 with AVX2
+// (build with -C target-cpu=native or target-feature=+avx2), 4 for SSE and NEON
+#[cfg(target_feature = "avx2")]
+pub const LANES: usize = 8;
+#[cfg(not(target_feature = "avx2"))]
+pub const LANES: usize = 4;
+
+pub type Wide = Simd<f32, LANES>;
+pub type WideMask = Mask<i32, LANES>;
+
+// Rays as structure of arrays, one lane each. `active` has the lanes that were given a ray.
+pub struct RayPacket {
+    pub rays: [Ray; LANES],
+    pub orig: [Wide; 3],
+    pub dir: [Wide; 3],
+    pub inv_dir: [Wide; 3],
+    pub active: WideMask,
+}
+
+// The closest hit of every lane so far. `trace_len` starts at the far end of the search and is
+// pulled in with every hit, so later tests only look for closer ones.
+pub struct PacketHit {
+    pub trace_len: Wide,
+    pub hit: WideMask,
+    pub recs: [HitRecord; LANES],
+}
+
+impl RayPacket {
+    pub fn new(rays: &[Option<Ray>]) -> RayPacket {
+        assert!(rays.len() <= LANES, "more rays than lanes");
+        let empty = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let mut lanes = [empty; LANES];
+        let mut active = [false; LANES];
+        for (i, ray) in rays.iter().enumerate() {
+            if let Some(ray) = ray {
+                lanes[i] = *ray;
+                active[i] = true;
+            }
+        }
+        let wide = |f: fn(&Ray) -> f32| Wide::from_array(lanes.map(|r| f(&r)));
+        let dir = [wide(|r| r.dir.x), wide(|r| r.dir.y), wide(|r| r.dir.z)];
+        return RayPacket {
+            rays: lanes,
+            orig: [wide(|r| r.orig.x), wide(|r| r.orig.y), wide(|r| r.orig.z)],
+            inv_dir: dir.map(|d| Wide::splat(1.0) / d),
+            dir,
+            active: WideMask::from_array(active),
+        };
+    }
+}
+
+impl PacketHit {
+    pub fn new(trace_len_max: f32) -> PacketHit {
+        return PacketHit {
+            trace_len: Wide::splat(trace_len_max),
+            hit: WideMask::splat(false),
+            recs: [HitRecord::default(); LANES],
+        };
+    }
+
+    pub fn set(&mut self, lane: usize, rec: HitRecord) {
+        self.trace_len[lane] = rec.trace_len;
+        self.hit.set(lane, true);
+        self.recs[lane] = rec;
+    }
+
+    pub fn get(&self, lane: usize) -> Option<HitRecord> {
+        return if self.hit.test(lane) { Some(self.recs[lane]) } else { None };
+    }
+}
+
+// Runs the single ray hit() on the lanes in `lanes`, for shapes without a wide test and to turn the
+// lanes a wide test let through into the exact same records single rays would get
+pub fn hit_lanes<H: Hittable + ?Sized>(obj: &H, packet: &RayPacket, lanes: WideMask, trace_len_min: f32, hits: &mut PacketHit) -> WideMask {
+    let mut found = WideMask::splat(false);
+    for lane in 0..LANES {
+        if !lanes.test(lane) {
+            continue;
+        }
+        let mut rec = HitRecord::default();
+        if obj.hit(&packet.rays[lane], trace_len_min, hits.trace_len[lane], &mut rec) {
+            hits.set(lane, rec);
+            found.set(lane, true);
+        }
+    }
+    return found;
+}
+
+// Closest hit of up to LANES rays, traced together
+pub fn closest_hits(world: &HittableList, rays: &[Option<Ray>]) -> Vec<Option<HitRecord>> {
+    let packet = RayPacket::new(rays);
+    let mut hits = PacketHit::new(INFINITY);
+    world.hit_packet(&packet, 0.0001, &mut hits);
+    return (0..rays.len()).map(|lane| hits.get(lane)).collect();
+}
+
+pub fn dot(a: &[Wide; 3], b: &[Wide; 3]) -> Wide {
+    return a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+}
+
+pub fn splat(v: &Vec3) -> [Wide; 3] {
+    return [Wide::splat(v.x), Wide::splat(v.y), Wide::splat(v.z)];
+}
+
+// Lane `lane` of a wide vector
+pub fn lane(v: &[Wide; 3], lane: usize) -> Vec3 {
+    return Vec3::new(v[0][lane], v[1][lane], v[2][lane]);
+}
+
+// The Cephes single precision polynomials, within a couple of ulp of the scalar functions.
+// asin for |x| <= 0.5 and atan for 0 <= x <= 1, the callers fold the rest of the range onto them.
+fn asin_small(x: Wide) -> Wide {
+    let z = x * x;
+    let p = (((Wide::splat(4.216_32e-2) * z + Wide::splat(2.418_131e-2)) * z + Wide::splat(4.547_002_6e-2)) * z
+        + Wide::splat(7.495_300_3e-2))
+        * z
+        + Wide::splat(1.666_675_2e-1);
+    return p * z * x + x;
+}
+
+fn atan_unit(x: Wide) -> Wide {
+    // Past tan(pi / 8) the angle is taken from pi / 4 instead
+    let big = x.simd_gt(Wide::splat(0.414_213_57));
+    let x = big.select((x - Wide::splat(1.0)) / (x + Wide::splat(1.0)), x);
+    let z = x * x;
+    let p = ((Wide::splat(8.053_744_5e-2) * z - Wide::splat(1.387_768_6e-1)) * z + Wide::splat(1.997_771_1e-1)) * z
+        - Wide::splat(3.333_295e-1);
+    return big.select(Wide::splat(FRAC_PI_4), Wide::splat(0.0)) + p * z * x + x;
+}
+
+pub fn acos(x: Wide) -> Wide {
+    let x = x.simd_clamp(Wide::splat(-1.0), Wide::splat(1.0));
+    // acos(x) = 2 asin(sqrt((1 - x) / 2)) away from 0, where the polynomial would be too far out
+    let big = x.abs().simd_gt(Wide::splat(0.5));
+    let half = ((Wide::splat(1.0) - x.abs()) * Wide::splat(0.5)).sqrt();
+    let r = asin_small(big.select(half, x));
+    let two_r = r + r;
+    let far = x.simd_lt(Wide::splat(0.0)).select(Wide::splat(PI) - two_r, two_r);
+    return big.select(far, Wide::splat(FRAC_PI_2) - r);
+}
+
+// Same quadrants and signed zeros as f32::atan2
+pub fn atan2(y: Wide, x: Wide) -> Wide {
+    let (ay, ax) = (y.abs(), x.abs());
+    let (lo, hi) = (ay.simd_min(ax), ay.simd_max(ax));
+    let ratio = hi.simd_eq(Wide::splat(0.0)).select(Wide::splat(0.0), lo / hi);
+    let mut angle = atan_unit(ratio);
+    angle = ay.simd_gt(ax).select(Wide::splat(FRAC_PI_2) - angle, angle);
+    angle = x.is_sign_negative().select(Wide::splat(PI) - angle, angle);
+    return angle.copysign(y);
+}
+
+// Times the closest hit search for every camera ray of a width x height image, one ray at a time and
+// then in packets of LANES neighbouring pixels, and again for shadow rays from the hits towards a
+// point 10 units above the camera. Single threaded. Both ways have to find the same hits.
+pub fn benchmark(world: &HittableList, cam: &Camera, width: u32, height: u32) {
+    let mut sampler = SamplerConfig::default().build(1, ChaCha20Rng::seed_from_u64(0));
+    let mut camera_rays = vec![];
+    for y in 0..height {
+        for x in 0..width {
+            let (u, v) = ((x as f32 + 0.5) / width as f32, 1.0 - (y as f32 + 0.5) / height as f32);
+            camera_rays.push(cam.generate_ray(u, v, sampler.as_mut()));
+        }
+    }
+    let camera_hits = time_rays("camera", world, &camera_rays, width as usize);
+
+    let shadow_rays: Vec<Option<Ray>> = camera_rays
+        .iter()
+        .zip(camera_hits.iter())
+        .map(|(ray, hit)| {
+            let (ray, rec) = (ray.as_ref()?, hit.as_ref()?);
+            let light = ray.orig + Vec3::new(0.0, 10.0, 0.0);
+            Some(Ray::new(rec.point, light - rec.point, ray.time))
+        })
+        .collect();
+    time_rays("shadow", world, &shadow_rays, width as usize);
+}
+
+// Traces `rays` both ways, packets never straddling rows of `row` rays. Returns the single ray hits.
+fn time_rays(kind: &str, world: &HittableList, rays: &[Option<Ray>], row: usize) -> Vec<Option<HitRecord>> {
+    let count = rays.iter().filter(|r| r.is_some()).count();
+    let start = Instant::now();
+    let singles: Vec<Option<HitRecord>> = rays
+        .iter()
+        .map(|ray| {
+            let mut rec = HitRecord::default();
+            let ray = ray.as_ref()?;
+            if world.hit(ray, 0.0001, INFINITY, &mut rec) { Some(rec) } else { None }
+        })
+        .collect();
+    let single_time = start.elapsed().as_secs_f64();
+
+    let start = Instant::now();
+    let mut packed = Vec::with_capacity(rays.len());
+    for line in rays.chunks(row) {
+        for chunk in line.chunks(LANES) {
+            packed.extend(closest_hits(world, chunk));
+        }
+    }
+    let packet_time = start.elapsed().as_secs_f64();
+
+    let mismatched = singles
+        .iter()
+        .zip(packed.iter())
+        .filter(|(a, b)| match (a, b) {
+            (Some(a), Some(b)) => a.trace_len != b.trace_len || a.object_id != b.object_id,
+            (None, None) => false,
+            _ => true,
+        })
+        .count();
+    let mrays = |secs: f64| count as f64 / secs / 1e6;
+    println!("{kind:<7} rays {count:>9}   single {:>7.2} Mrays/s   packets of {LANES} {:>7.2} Mrays/s   speedup {:.2}x", mrays(single_time), mrays(packet_time), single_time / packet_time);
+    if mismatched > 0 {
+        println!("{kind:<7} rays: {mismatched} hits differ between single rays and packets");
+    }
+    return singles;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_trig_matches_scalar() {
+        for i in 0..=4000 {
+            let x = -1.0 + i as f32 / 2000.0;
+            let wide = acos(Wide::splat(x))[0];
+            assert!((wide - x.acos()).abs() < 1e-6, "acos({}) = {}, not {}", x, wide, x.acos());
+        }
+        let mut coords: Vec<f32> = (-40..=40).map(|i| i as f32 / 8.0).collect();
+        coords.extend([-0.0, 1e-20, -1e-20, 1e20, -1e20]);
+        for &y in &coords {
+            for &x in &coords {
+                let (wide, scalar) = (atan2(Wide::splat(y), Wide::splat(x))[0], y.atan2(x));
+                assert!((wide - scalar).abs() < 1e-6, "atan2({}, {}) = {}, not {}", y, x, wide, scalar);
+                assert_eq!(wide.is_sign_negative(), scalar.is_sign_negative(), "atan2({}, {})", y, x);
+            }
+        }
+    }
+}
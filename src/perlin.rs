@@ -0,0 +1,84 @@
+use rand::Rng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{utils::random_in_unit_sphere, vec3::Vec3};
+
+const POINT_COUNT: usize = 256;
+
+pub struct Perlin {
+    ranvec: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(rng: &mut ChaCha20Rng) -> Perlin {
+        let mut ranvec = Vec::with_capacity(POINT_COUNT);
+        for _ in 0..POINT_COUNT {
+            ranvec.push(random_in_unit_sphere(rng).unit_vector());
+        }
+
+        return Perlin {
+            ranvec,
+            perm_x: Self::generate_perm(rng),
+            perm_y: Self::generate_perm(rng),
+            perm_z: Self::generate_perm(rng),
+        };
+    }
+
+    fn generate_perm(rng: &mut ChaCha20Rng) -> Vec<usize> {
+        let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+        for i in (1..POINT_COUNT).rev() {
+            let target = rng.gen_range(0..=i);
+            p.swap(i, target);
+        }
+        return p;
+    }
+
+    // Gradient noise in roughly [-1, 1]
+    pub fn noise(&self, p: &Vec3) -> f32 {
+        let u = p.x - p.x.floor();
+        let v = p.y - p.y.floor();
+        let w = p.z - p.z.floor();
+        let i = p.x.floor() as i64;
+        let j = p.y.floor() as i64;
+        let k = p.z.floor() as i64;
+
+        let mut accum = 0.0;
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let c = self.ranvec[self.perm_x[((i + di) & 255) as usize]
+                        ^ self.perm_y[((j + dj) & 255) as usize]
+                        ^ self.perm_z[((k + dk) & 255) as usize]];
+                    let weight = Vec3::new(u - di as f32, v - dj as f32, w - dk as f32);
+                    let (fi, fj, fk) = (di as f32, dj as f32, dk as f32);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * c.dot_prod(weight);
+                }
+            }
+        }
+
+        return accum;
+    }
+
+    // Octave sum of noise, each octave at double frequency and half weight
+    pub fn turbulence(&self, p: &Vec3, octaves: u32) -> f32 {
+        let mut accum = 0.0;
+        let mut tmp = *p;
+        let mut weight = 1.0;
+        for _ in 0..octaves {
+            accum += weight * self.noise(&tmp);
+            weight *= 0.5;
+            tmp = tmp * 2.0;
+        }
+        return accum.abs();
+    }
+}
@@ -0,0 +1,299 @@
+use std::f32::consts::PI;
+
+use rand_chacha::ChaCha20Rng;
+use rayon::prelude::{IndexedParallelIterator, ParallelIterator, ParallelSliceMut};
+
+use crate::{aov::luminance, rand_double::rand_double, utils::deg_to_rad};
+
+// Lens and film effects on the linear image, in the order a photon meets them: chromatic
+// aberration and vignetting in the lens, bloom and glare from scatter and diffraction, then grain
+// on the film. Every effect is off until it is set.
+pub struct PostEffects {
+    pub chromatic_aberration: Option<ChromaticAberration>,
+    pub vignette: Option<Vignette>,
+    pub bloom: Option<Bloom>,
+    pub glare: Option<Glare>,
+    pub grain: Option<Grain>,
+}
+
+// Lateral colour fringing, red is magnified and blue shrunk about the centre by `amount`
+#[derive(Clone, Copy)]
+pub struct ChromaticAberration {
+    pub amount: f32,
+}
+
+// Natural cos^4 falloff. `strength` is the tangent of the field angle at the corners, so 0.4 is
+// roughly a 40mm lens on full frame.
+#[derive(Clone, Copy)]
+pub struct Vignette {
+    pub strength: f32,
+}
+
+// Light scattered in the glass and on the sensor. Moves `intensity` of the energy above `threshold`
+// into a halo made of Gaussians `radius`, 2, 4 and 8 times `radius` wide (a fraction of the image width).
+// Threshold 0 scatters everything like real glass, energy is kept either way.
+#[derive(Clone, Copy)]
+pub struct Bloom {
+    pub intensity: f32,
+    pub radius: f32,
+    pub threshold: f32,
+}
+
+// Diffraction star from the aperture blades. `streaks` rays leave every pixel above `threshold`,
+// the first at `angle` degrees, each fading to 1% over `length` times the image width.
+#[derive(Clone, Copy)]
+pub struct Glare {
+    pub intensity: f32,
+    pub threshold: f32,
+    pub streaks: u32,
+    pub angle: f32,
+    pub length: f32,
+}
+
+// Monochrome film grain, shot noise like so it shows most in the shadows. `size` is in pixels.
+#[derive(Clone, Copy)]
+pub struct Grain {
+    pub amount: f32,
+    pub size: f32,
+}
+
+impl ChromaticAberration {
+    pub fn default() -> ChromaticAberration {
+        return ChromaticAberration { amount: 0.002 };
+    }
+}
+
+impl Vignette {
+    pub fn default() -> Vignette {
+        return Vignette { strength: 0.5 };
+    }
+}
+
+impl Bloom {
+    pub fn default() -> Bloom {
+        return Bloom { intensity: 0.05, radius: 0.004, threshold: 0.0 };
+    }
+}
+
+impl Glare {
+    pub fn default() -> Glare {
+        return Glare { intensity: 0.1, threshold: 2.0, streaks: 6, angle: 15.0, length: 0.08 };
+    }
+}
+
+impl Grain {
+    pub fn default() -> Grain {
+        return Grain { amount: 0.02, size: 1.5 };
+    }
+}
+
+// Linear RGB, three floats per pixel in rows from the top
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub rgb: Vec<f32>,
+}
+
+impl HdrImage {
+    // Bilinear lookup in pixel coordinates. Outside the frame repeats the edge, what's just out of
+    // shot is most likely more of the same and a flat image stays flat through every effect.
+    fn sample(&self, x: f32, y: f32, c: usize) -> f32 {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let texel = |xi: f32, yi: f32| {
+            let xi = xi.clamp(0.0, (self.width - 1) as f32) as usize;
+            let yi = yi.clamp(0.0, (self.height - 1) as f32) as usize;
+            return self.rgb[(yi * self.width + xi) * 3 + c];
+        };
+        return (1.0 - fy) * ((1.0 - fx) * texel(x0, y0) + fx * texel(x0 + 1.0, y0))
+            + fy * ((1.0 - fx) * texel(x0, y0 + 1.0) + fx * texel(x0 + 1.0, y0 + 1.0));
+    }
+
+    // Energy above `threshold` luminance, the colour kept
+    fn above(&self, threshold: f32) -> HdrImage {
+        let mut rgb = self.rgb.clone();
+        for px in rgb.chunks_exact_mut(3) {
+            let lum = luminance(px[0], px[1], px[2]);
+            let keep = if lum > threshold { (lum - threshold) / lum } else { 0.0 };
+            px.iter_mut().for_each(|v| *v *= keep);
+        }
+        return HdrImage { width: self.width, height: self.height, rgb };
+    }
+}
+
+impl PostEffects {
+    pub fn default() -> PostEffects {
+        return PostEffects { chromatic_aberration: None, vignette: None, bloom: None, glare: None, grain: None };
+    }
+
+    pub fn enabled(&self) -> bool {
+        return self.chromatic_aberration.is_some()
+            || self.vignette.is_some()
+            || self.bloom.is_some()
+            || self.glare.is_some()
+            || self.grain.is_some();
+    }
+
+    pub fn apply(&self, im: &mut HdrImage, rng: &mut ChaCha20Rng) {
+        if let Some(ca) = &self.chromatic_aberration {
+            ca.apply(im);
+        }
+        if let Some(vignette) = &self.vignette {
+            vignette.apply(im);
+        }
+        if let Some(bloom) = &self.bloom {
+            bloom.apply(im);
+        }
+        if let Some(glare) = &self.glare {
+            glare.apply(im);
+        }
+        if let Some(grain) = &self.grain {
+            grain.apply(im, rng);
+        }
+    }
+}
+
+impl ChromaticAberration {
+    fn apply(&self, im: &mut HdrImage) {
+        let (cx, cy) = (im.width as f32 / 2.0, im.height as f32 / 2.0);
+        let src = HdrImage { width: im.width, height: im.height, rgb: im.rgb.clone() };
+        for (i, px) in im.rgb.chunks_exact_mut(3).enumerate() {
+            let (x, y) = ((i % im.width) as f32 + 0.5 - cx, (i / im.width) as f32 + 0.5 - cy);
+            // A channel magnified by m shows at x the scene from x / m
+            let (r, b) = (1.0 + self.amount, 1.0 - self.amount);
+            px[0] = src.sample(cx + x / r, cy + y / r, 0);
+            px[2] = src.sample(cx + x / b, cy + y / b, 2);
+        }
+    }
+}
+
+impl Vignette {
+    fn apply(&self, im: &mut HdrImage) {
+        let (cx, cy) = (im.width as f32 / 2.0, im.height as f32 / 2.0);
+        let half_diagonal = (cx * cx + cy * cy).sqrt();
+        for (i, px) in im.rgb.chunks_exact_mut(3).enumerate() {
+            let (x, y) = ((i % im.width) as f32 + 0.5 - cx, (i / im.width) as f32 + 0.5 - cy);
+            let tan_theta = self.strength * (x * x + y * y).sqrt() / half_diagonal;
+            // cos^4 written with the tangent
+            let cos2 = 1.0 / (1.0 + tan_theta * tan_theta);
+            px.iter_mut().for_each(|v| *v *= cos2 * cos2);
+        }
+    }
+}
+
+impl Bloom {
+    fn apply(&self, im: &mut HdrImage) {
+        let bright = im.above(self.threshold);
+        let base_sigma = self.radius * im.width as f32;
+        let weights = [0.4, 0.3, 0.2, 0.1];
+        let mut halo = vec![0.0; im.rgb.len()];
+        for (level, weight) in weights.iter().enumerate() {
+            let blurred = gaussian_blur(&bright, base_sigma * (1 << level) as f32);
+            for (h, b) in halo.iter_mut().zip(blurred.iter()) {
+                *h += weight * b;
+            }
+        }
+        for ((v, b), h) in im.rgb.iter_mut().zip(bright.rgb.iter()).zip(halo.iter()) {
+            *v += self.intensity * (h - b);
+        }
+    }
+}
+
+impl Glare {
+    // Kawase's streak filter: pass p takes 4 taps b^p pixels apart weighted a^(b^p s), so after n
+    // passes every offset t < 4^n has been summed once with weight a^t.
+    fn apply(&self, im: &mut HdrImage) {
+        let bright = im.above(self.threshold);
+        let length = (self.length * im.width as f32).max(1.0);
+        let falloff = 0.01f32.powf(1.0 / length);
+        let passes = ((length.log2() / 2.0).ceil() as u32).clamp(1, 6);
+        let norm: f32 = (0..4u32.pow(passes)).map(|t| falloff.powi(t as i32)).sum();
+
+        let mut star = vec![0.0; im.rgb.len()];
+        for k in 0..self.streaks {
+            let theta = deg_to_rad(self.angle) + 2.0 * PI * k as f32 / self.streaks as f32;
+            let (dx, dy) = (theta.cos(), -theta.sin());
+            let mut streak = HdrImage { width: im.width, height: im.height, rgb: bright.rgb.clone() };
+            for pass in 0..passes {
+                let spacing = 4u32.pow(pass) as f32;
+                let mut next = vec![0.0; im.rgb.len()];
+                next.par_chunks_exact_mut(3).enumerate().for_each(|(i, px)| {
+                    let (x, y) = ((i % im.width) as f32 + 0.5, (i / im.width) as f32 + 0.5);
+                    for s in 0..4 {
+                        let offset = spacing * s as f32;
+                        let w = falloff.powf(offset);
+                        for c in 0..3 {
+                            px[c] += w * streak.sample(x - dx * offset, y - dy * offset, c);
+                        }
+                    }
+                });
+                streak.rgb = next;
+            }
+            for (s, v) in star.iter_mut().zip(streak.rgb.iter()) {
+                *s += v / (norm * self.streaks as f32);
+            }
+        }
+        for ((v, b), s) in im.rgb.iter_mut().zip(bright.rgb.iter()).zip(star.iter()) {
+            *v += self.intensity * (s - b);
+        }
+    }
+}
+
+impl Grain {
+    fn apply(&self, im: &mut HdrImage, rng: &mut ChaCha20Rng) {
+        let size = self.size.max(1.0);
+        let (gw, gh) = ((im.width as f32 / size).ceil() as usize + 1, (im.height as f32 / size).ceil() as usize + 1);
+        // Box-Muller normals, one per grain
+        let noise: Vec<f32> = (0..gw * gh)
+            .map(|_| {
+                let (u1, u2) = (rand_double(rng).max(1e-7), rand_double(rng));
+                (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+            })
+            .collect();
+        let grains = HdrImage { width: gw, height: gh, rgb: noise.iter().flat_map(|n| [*n, 0.0, 0.0]).collect() };
+
+        for (i, px) in im.rgb.chunks_exact_mut(3).enumerate() {
+            let (x, y) = ((i % im.width) as f32 + 0.5, (i / im.width) as f32 + 0.5);
+            let n = grains.sample(x / size + 0.5, y / size + 0.5, 0);
+            let lum = luminance(px[0], px[1], px[2]).max(0.01);
+            let gain = (1.0 + self.amount * n / lum.sqrt()).max(0.0);
+            px.iter_mut().for_each(|v| *v *= gain);
+        }
+    }
+}
+
+// Three box blurs per axis, close enough to a Gaussian of `sigma` pixels and linear in the image size
+fn gaussian_blur(im: &HdrImage, sigma: f32) -> Vec<f32> {
+    let radius = (((4.0 * sigma * sigma + 1.0).sqrt() - 1.0) / 2.0).round() as usize;
+    let mut rgb = im.rgb.clone();
+    if radius == 0 {
+        return rgb;
+    }
+    for _ in 0..3 {
+        box_blur(&mut rgb, im.width, im.height, radius, 3, im.width * 3);
+        box_blur(&mut rgb, im.height, im.width, radius, im.width * 3, 3);
+    }
+    return rgb;
+}
+
+// Sliding window mean along `len` elements `step` apart, for each of `lines` lines `line_step` apart.
+// Edges repeat the border pixel.
+fn box_blur(rgb: &mut [f32], len: usize, lines: usize, radius: usize, step: usize, line_step: usize) {
+    let mut line = vec![0.0; len];
+    let r = radius as isize;
+    let scale = 1.0 / (2 * radius + 1) as f32;
+    for l in 0..lines {
+        for c in 0..3 {
+            let at = |i: isize| l * line_step + i.clamp(0, len as isize - 1) as usize * step + c;
+            let mut sum: f32 = (-r..=r).map(|i| rgb[at(i)]).sum();
+            for i in 0..len as isize {
+                line[i as usize] = sum * scale;
+                sum += rgb[at(i + r + 1)] - rgb[at(i - r)];
+            }
+            for i in 0..len {
+                rgb[l * line_step + i * step + c] = line[i];
+            }
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use rand::prelude::*;
 use rand_chacha::ChaCha20Rng;
 
+// 23 random bits as the mantissa of a float in [1, 2), minus one. Every value is on the 2^-23 grid and
+// equally likely, and 1.0 can't come out the way rounding a division by u64::MAX could give it.
 pub fn rand_double(rng: &mut ChaCha20Rng) -> f32 {
-    return (*rng).next_u64() as f32/u64::MAX as f32;
+    return f32::from_bits(0x3f80_0000 | (rng.next_u32() >> 9)) - 1.0;
 }
 
 pub fn rand_double_range(rng: &mut ChaCha20Rng, min: f32, max: f32) -> f32 {
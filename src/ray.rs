@@ -3,7 +3,8 @@ use crate::vec3::Vec3;
 #[derive(Clone, Copy)]
 pub struct Ray {
     pub orig: Vec3,
-    pub dir: Vec3
+    pub dir: Vec3,
+    pub time: f32
 }
 
 impl Ray {
@@ -11,10 +12,11 @@ impl Ray {
         return self.orig + t*self.dir;
     }
 
-    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+    pub fn new(origin: Vec3, direction: Vec3, time: f32) -> Ray {
         return Ray {
             orig: origin,
-            dir: direction
+            dir: direction,
+            time: time
         };
     }
 }
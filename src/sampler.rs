@@ -0,0 +1,437 @@
+use std::sync::OnceLock;
+
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::rand_double::rand_double;
+
+// Where every random number of a camera sample comes from. Samples are drawn in the same order for
+// every sample of a pixel, pixel jitter, lens, time and then per bounce whatever the material asks for,
+// and the n-th draw is one dimension of the sequence. The low-discrepancy samplers spread each
+// dimension (or pair of dimensions) evenly over a pixel's samples instead of leaving clumps and holes.
+pub trait Sampler {
+    // Starts sample `index` of pixel (x, y), the dimensions count up from 0 again
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32);
+    fn get_1d(&mut self) -> f32;
+    fn get_2d(&mut self) -> (f32, f32);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SamplerKind {
+    // Plain random numbers, what the renderer has always done
+    Independent,
+    // Jittered strata per dimension, shuffled so dimensions don't line up
+    Stratified,
+    // Halton sequence with random digit permutations per pixel
+    Halton,
+    // Owen scrambled Sobol pairs, shuffled so every pair of dimensions gets its own points
+    Sobol,
+    // The same Owen scrambled Sobol points in every pixel, each pixel shifted by a blue noise mask.
+    // The error of neighbouring pixels is then negatively correlated and reads as fine blue noise.
+    BlueNoise,
+}
+
+// The sampler each render thread builds. The seed picks the scrambles, keep it between frames for
+// the same noise pattern every frame.
+#[derive(Clone, Copy)]
+pub struct SamplerConfig {
+    pub kind: SamplerKind,
+    pub seed: u32,
+}
+
+impl SamplerConfig {
+    pub fn default() -> SamplerConfig {
+        return SamplerConfig { kind: SamplerKind::Independent, seed: 0 };
+    }
+
+    pub fn new(kind: SamplerKind, seed: u32) -> SamplerConfig {
+        return SamplerConfig { kind, seed };
+    }
+
+    pub fn build(&self, samples_per_pixel: u32, rng: ChaCha20Rng) -> Box<dyn Sampler> {
+        let state = SampleState { seed: self.seed, pixel: 0, index: 0, dim: 0, rng };
+        return match self.kind {
+            SamplerKind::Independent => Box::new(IndependentSampler { state }),
+            SamplerKind::Stratified => Box::new(StratifiedSampler { state, samples: samples_per_pixel.max(1) }),
+            SamplerKind::Halton => Box::new(HaltonSampler { state }),
+            SamplerKind::Sobol => Box::new(SobolSampler { state }),
+            SamplerKind::BlueNoise => Box::new(BlueNoiseSampler { state, x: 0, y: 0, mask: blue_noise() }),
+        };
+    }
+}
+
+// Bookkeeping shared by every sampler
+struct SampleState {
+    seed: u32,
+    // Hash of the pixel coordinates and the seed
+    pixel: u32,
+    index: u32,
+    dim: u32,
+    rng: ChaCha20Rng,
+}
+
+impl SampleState {
+    fn start(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel = hash(hash(x ^ hash(y)) ^ self.seed);
+        self.index = index;
+        self.dim = 0;
+    }
+
+    // Seed for the next dimension of this pixel
+    fn next_dim(&mut self) -> u32 {
+        self.dim += 1;
+        return hash(self.pixel ^ hash(self.dim));
+    }
+}
+
+struct IndependentSampler {
+    state: SampleState,
+}
+
+struct StratifiedSampler {
+    state: SampleState,
+    samples: u32,
+}
+
+struct HaltonSampler {
+    state: SampleState,
+}
+
+struct SobolSampler {
+    state: SampleState,
+}
+
+struct BlueNoiseSampler {
+    state: SampleState,
+    x: u32,
+    y: u32,
+    mask: &'static [f32],
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.state.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        return rand_double(&mut self.state.rng);
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        return (rand_double(&mut self.state.rng), rand_double(&mut self.state.rng));
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.state.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let seed = self.state.next_dim();
+        let stratum = permute(self.state.index, self.samples, seed);
+        return (stratum as f32 + rand_double(&mut self.state.rng)) / self.samples as f32;
+    }
+
+    // A grid as close to square as the sample count allows, some cells stay empty when it isn't square
+    fn get_2d(&mut self) -> (f32, f32) {
+        let seed = self.state.next_dim();
+        let nx = (self.samples as f32).sqrt().ceil() as u32;
+        let ny = (self.samples + nx - 1) / nx;
+        let cell = permute(self.state.index, nx * ny, seed);
+        let rng = &mut self.state.rng;
+        return (((cell % nx) as f32 + rand_double(rng)) / nx as f32, ((cell / nx) as f32 + rand_double(rng)) / ny as f32);
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.state.start(x, y, index);
+    }
+
+    // Past the prime table the bases are large enough that the sequence is barely better than random
+    fn get_1d(&mut self) -> f32 {
+        let dim = self.state.dim as usize;
+        let seed = self.state.next_dim();
+        if dim >= PRIMES.len() {
+            return rand_double(&mut self.state.rng);
+        }
+        return scrambled_radical_inverse(PRIMES[dim], self.state.index, seed);
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        return (self.get_1d(), self.get_1d());
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.state.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let seed = self.state.next_dim();
+        return owen_sobol(self.state.index, seed).0;
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let seed = self.state.next_dim();
+        return owen_sobol(self.state.index, seed);
+    }
+}
+
+impl BlueNoiseSampler {
+    // Every dimension reads the mask at its own toroidal offset so the dimensions aren't correlated
+    fn shift(&self, dim_seed: u32, component: u32) -> f32 {
+        let h = hash(dim_seed ^ component);
+        let (ox, oy) = (h % MASK_SIZE as u32, (h >> 16) % MASK_SIZE as u32);
+        let (x, y) = ((self.x + ox) as usize % MASK_SIZE, (self.y + oy) as usize % MASK_SIZE);
+        return self.mask[y * MASK_SIZE + x];
+    }
+
+    // Seed that only depends on the dimension, so every pixel gets the same points
+    fn next_dim(&mut self) -> u32 {
+        self.state.dim += 1;
+        return hash(self.state.seed ^ hash(self.state.dim));
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.state.start(x, y, index);
+        (self.x, self.y) = (x, y);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let seed = self.next_dim();
+        return (owen_sobol(self.state.index, seed).0 + self.shift(seed, 0)).fract();
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let seed = self.next_dim();
+        let (u, v) = owen_sobol(self.state.index, seed);
+        return ((u + self.shift(seed, 0)).fract(), (v + self.shift(seed, 1)).fract());
+    }
+}
+
+// Integer hash with good avalanche (the murmur3 finaliser)
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x85eb_ca6b);
+    x ^= x >> 13;
+    x = x.wrapping_mul(0xc2b2_ae35);
+    x ^= x >> 16;
+    return x;
+}
+
+fn to_unit(x: u32) -> f32 {
+    return (x >> 8) as f32 / (1u32 << 24) as f32;
+}
+
+// Kensler, "Correlated Multi-Jittered Sampling": element `i` of the permutation of 0..n picked by `seed`
+fn permute(mut i: u32, n: u32, seed: u32) -> u32 {
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    i %= n;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            // Reduced first, a sum that wrapped past u32::MAX would no longer be a rotation mod n
+            return i.wrapping_add(seed % n) % n;
+        }
+    }
+}
+
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107,
+    109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199, 211, 223, 227, 229,
+    233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307, 311,
+];
+
+// Digits of `index` in `base` mirrored around the point, each digit position shuffled by its own
+// permutation. The zero digits past the last one of `index` are shuffled too, or every value would
+// start out small, but they're just random digits so one random number stands in for all of them.
+fn scrambled_radical_inverse(base: u32, mut index: u32, seed: u32) -> f32 {
+    let inv_base = 1.0 / base as f64;
+    let (mut value, mut scale, mut position) = (0.0, inv_base, 0);
+    while index > 0 {
+        let digit = permute(index % base, base, hash(seed ^ position));
+        value += digit as f64 * scale;
+        index /= base;
+        scale *= inv_base;
+        position += 1;
+    }
+    value += to_unit(hash(seed ^ position)) as f64 * scale * base as f64;
+    return (value as f32).min(1.0 - f32::EPSILON / 2.0);
+}
+
+// Direction numbers of the second Sobol dimension, the first is the bit reversed index
+const SOBOL_DIRECTIONS: [u32; 32] = sobol_directions();
+
+const fn sobol_directions() -> [u32; 32] {
+    let mut v = [0; 32];
+    let mut m: u32 = 1;
+    let mut k = 0;
+    while k < 32 {
+        v[k] = m << (31 - k);
+        m ^= m << 1;
+        k += 1;
+    }
+    return v;
+}
+
+// Laine and Karras' hash, only ever carries from lower bits into higher ones
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    return x;
+}
+
+// Owen scrambling: every bit is flipped depending on the bits above it
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    return laine_karras_permutation(x.reverse_bits(), seed).reverse_bits();
+}
+
+// Burley, "Practical Hash-based Owen Scrambling". Scrambling the index shuffles the order of the
+// points, so pairs with different seeds are independent while each stays a (0, 2)-sequence.
+fn owen_sobol(index: u32, seed: u32) -> (f32, f32) {
+    let index = nested_uniform_scramble(index, seed);
+    let x = index.reverse_bits();
+    let mut y = 0;
+    for (k, v) in SOBOL_DIRECTIONS.iter().enumerate() {
+        if index >> k & 1 != 0 {
+            y ^= v;
+        }
+    }
+    return (to_unit(nested_uniform_scramble(x, hash(seed ^ 1))), to_unit(nested_uniform_scramble(y, hash(seed ^ 2))));
+}
+
+const MASK_SIZE: usize = 64;
+
+// Tileable blue noise ranks in [0, 1), made on first use
+fn blue_noise() -> &'static [f32] {
+    static MASK: OnceLock<Vec<f32>> = OnceLock::new();
+    return MASK.get_or_init(void_and_cluster);
+}
+
+// Ulichney's void-and-cluster method. Energy is a Gaussian splat of every set pixel on the torus, the
+// tightest cluster is the set pixel with the most and the largest void the empty pixel with the least.
+// Pixels are ranked by removing clusters from a well spread starting pattern, then filling voids.
+fn void_and_cluster() -> Vec<f32> {
+    let n = MASK_SIZE * MASK_SIZE;
+    let sigma = 1.5f32;
+    let kernel: Vec<f32> = (0..n)
+        .map(|i| {
+            let wrap = |d: usize| d.min(MASK_SIZE - d) as f32;
+            let (dx, dy) = (wrap(i % MASK_SIZE), wrap(i / MASK_SIZE));
+            (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let splat = |energy: &mut [f32], p: usize, sign: f32| {
+        let (px, py) = (p % MASK_SIZE, p / MASK_SIZE);
+        for (i, e) in energy.iter_mut().enumerate() {
+            let dx = (i % MASK_SIZE + MASK_SIZE - px) % MASK_SIZE;
+            let dy = (i / MASK_SIZE + MASK_SIZE - py) % MASK_SIZE;
+            *e += sign * kernel[dy * MASK_SIZE + dx];
+        }
+    };
+    let extreme = |energy: &[f32], set: &[bool], want: bool, cmp: fn(f32, f32) -> bool| {
+        let mut best: Option<usize> = None;
+        for i in 0..n {
+            if set[i] == want && best.map_or(true, |b| cmp(energy[i], energy[b])) {
+                best = Some(i);
+            }
+        }
+        return best.unwrap();
+    };
+    let tightest_cluster = |energy: &[f32], set: &[bool]| extreme(energy, set, true, |a, b| a > b);
+    let largest_void = |energy: &[f32], set: &[bool]| extreme(energy, set, false, |a, b| a < b);
+
+    // Starting pattern, a tenth of the pixels at random then moved from clusters into voids until settled
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let mut set = vec![false; n];
+    let mut energy = vec![0.0; n];
+    let mut ones = 0;
+    while ones < n / 10 {
+        let p = rng.next_u32() as usize % n;
+        if !set[p] {
+            set[p] = true;
+            splat(&mut energy, p, 1.0);
+            ones += 1;
+        }
+    }
+    loop {
+        let cluster = tightest_cluster(&energy, &set);
+        set[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        let void = largest_void(&energy, &set);
+        set[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0; n];
+    let (mut set_down, mut energy_down) = (set.clone(), energy.clone());
+    for r in (0..ones).rev() {
+        let cluster = tightest_cluster(&energy_down, &set_down);
+        set_down[cluster] = false;
+        splat(&mut energy_down, cluster, -1.0);
+        rank[cluster] = r;
+    }
+    for r in ones..n {
+        let void = largest_void(&energy, &set);
+        set[void] = true;
+        splat(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+    return rank.iter().map(|r| (*r as f32 + 0.5) / n as f32).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permute_is_a_bijection() {
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        for n in [1, 2, 3, 5, 7, 16, 31, 100, 1000, 4097] {
+            let mut seeds: Vec<u32> = (0..50).map(|_| rng.next_u32()).collect();
+            seeds.extend([0, 1, u32::MAX - 1, u32::MAX]);
+            for seed in seeds {
+                let mut seen = vec![false; n as usize];
+                for i in 0..n {
+                    let p = permute(i, n, seed);
+                    assert!(p < n, "permute({}, {}, {}) = {} is out of range", i, n, seed, p);
+                    assert!(!seen[p as usize], "permute(_, {}, {}) hits {} twice", n, seed, p);
+                    seen[p as usize] = true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use super::Scene;
+use crate::{
+    camera::{Camera, Sensor},
+    color::Color,
+    hittable::HittableList,
+    hittables::sphere::Sphere,
+    lens::LensSystem,
+    mats::{diffuse_light::DiffuseLight, lambertian::LambertianMat, metal::MetalMat, MatManager},
+    rand_double::{rand_double, rand_double_range},
+    vec3::Vec3,
+};
+use rand_chacha::ChaCha20Rng;
+
+// 85mm f/1.8 portrait lens with a six blade aperture, autofocused on the subject in the frame centre.
+// The small lights far behind turn into hexagonal bokeh. With `double_gauss` the same shot goes
+// through a traced 50mm f/2 double Gauss instead, showing its cat's eye bokeh and vignetting.
+#[derive(Clone, Copy)]
+pub struct Bokeh {
+    pub double_gauss: bool,
+    // Render width in pixels, the autofocus aims through the centre pixel of the frame
+    pub width: u32,
+}
+
+impl Scene for Bokeh {
+    fn setup(&self, world: &mut HittableList, cam: &mut Camera, mats: &mut MatManager, aspect_ratio: &mut f32, rng: &mut ChaCha20Rng) {
+        let ground_mat = mats.gen_mat(Box::new(LambertianMat {
+            albedo: Color::new_01_range(0.3, 0.3, 0.35),
+            roughness: 0.0,
+        }));
+        let subject_mat = mats.gen_mat(Box::new(MetalMat {
+            albedo: Color::new_01_range(0.9, 0.7, 0.4),
+            fuzz: 0.15,
+        }));
+
+        world.add(Sphere::new_box(Vec3::new(0.0, -1000.0, 0.0), 1000.0, &ground_mat));
+        world.add(Sphere::new_box(Vec3::new(0.0, 0.15, 0.0), 0.15, &subject_mat));
+
+        for _ in 0..60 {
+            let emit = Color::new_01_range(
+                rand_double_range(rng, 4.0, 12.0),
+                rand_double_range(rng, 4.0, 10.0),
+                rand_double_range(rng, 2.0, 8.0),
+            );
+            let light_mat = mats.gen_mat(Box::new(DiffuseLight { emit }));
+            let center = Vec3::new(
+                rand_double_range(rng, -5.0, 5.0),
+                rand_double_range(rng, 0.05, 1.0),
+                -10.0 - 6.0 * rand_double(rng),
+            );
+            world.add(Sphere::new_box(center, 0.05, &light_mat));
+        }
+
+        *aspect_ratio = self.get_aspect_ratio();
+        let look_from = Vec3::new(0.0, 0.35, 3.0);
+        let look_to = Vec3::new(0.0, 0.15, 0.0);
+        let lens_cam = if self.double_gauss {
+            let lens = LensSystem::double_gauss_50mm();
+            Camera::new_lens(look_from, look_to, Vec3::newi(0, 1, 0), lens, Sensor::FULL_FRAME, 3.0, rng)
+        } else {
+            None
+        };
+        if let Some(lens_cam) = lens_cam {
+            *cam = lens_cam;
+        } else {
+            if self.double_gauss {
+                println!("The double Gauss lens can't focus at 3m, falling back to the thin lens camera");
+            }
+            *cam = Camera::new_physical(look_from, look_to, Vec3::newi(0, 1, 0), Sensor::FULL_FRAME, 85.0, 1.8, 1.0, rng);
+            cam.blades = 6;
+            cam.blade_rotation = 15.0;
+        }
+
+        let height = (self.width as f32 / *aspect_ratio) as u32;
+        cam.focus_on_pixel(world, self.width / 2, height / 2, self.width, height);
+    }
+
+    fn get_aspect_ratio(&self) -> f32 {
+        return Sensor::FULL_FRAME.aspect_ratio();
+    }
+}
@@ -8,7 +8,7 @@ pub struct CornellBox {}
 impl Scene for CornellBox {
     fn setup(&self, world: &mut HittableList, cam: &mut Camera, mats: &mut MatManager, aspect_ratio: &mut f32, rng: &mut ChaCha20Rng) {
         let front_debug_mat = mats.gen_mat(Box::new(DebugFrontMat{}));
-        let black_lambert = mats.gen_mat(Box::new(LambertianMat{albedo:Color::new(0.0, 0.0, 0.0)}));
+        let black_lambert = mats.gen_mat(Box::new(LambertianMat{albedo:Color::new(0.0, 0.0, 0.0), roughness:0.0}));
 
         //world.add(Box::new(Triangle {
         //    v0: Vec3::new(0.0, 0.0, 0.0),
@@ -32,6 +32,8 @@ impl Scene for CornellBox {
             v0: Vec3::new(0.0, 0.0, 0.0),
             v1: Vec3::new(1.0, 0.0, 0.0),
             v2: Vec3::new(0.0, 0.0, 1.0),
+            normals: None,
+            double_sided: true,
             material: front_debug_mat
         }));
         //world.add(Box::new(Sphere {
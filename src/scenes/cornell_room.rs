@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use super::Scene;
+use rand_chacha::ChaCha20Rng;
+use crate::{camera::Camera, hittable::HittableList, mats::{MatManager, lambertian::LambertianMat, diffuse_light::DiffuseLight}, color::Color, hittables::{cuboid::Cuboid, instance::Instance, rect::AaRect}, mat4::Mat4, vec3::Vec3};
+
+// The standard Cornell box, walls from rects and the two blocks as rotated cuboid instances
+#[derive(Clone, Copy)]
+pub struct CornellRoom {}
+
+impl Scene for CornellRoom {
+    fn setup(&self, world: &mut HittableList, cam: &mut Camera, mats: &mut MatManager, aspect_ratio: &mut f32, rng: &mut ChaCha20Rng) {
+        let red = mats.gen_mat(Box::new(LambertianMat{albedo:Color::new_01_range(0.65, 0.05, 0.05), roughness:0.0}));
+        let white = mats.gen_mat(Box::new(LambertianMat{albedo:Color::new_01_range(0.73, 0.73, 0.73), roughness:0.0}));
+        let green = mats.gen_mat(Box::new(LambertianMat{albedo:Color::new_01_range(0.12, 0.45, 0.15), roughness:0.0}));
+        let light = mats.gen_mat(Box::new(DiffuseLight{emit:Color::new_01_range(15.0, 15.0, 15.0)}));
+
+        world.add(AaRect::yz(0.0, 555.0, 0.0, 555.0, 555.0, &green));
+        world.add(AaRect::yz(0.0, 555.0, 0.0, 555.0, 0.0, &red));
+        world.add(AaRect::xz(213.0, 343.0, 227.0, 332.0, 554.0, &light));
+        world.add(AaRect::xz(0.0, 555.0, 0.0, 555.0, 0.0, &white));
+        world.add(AaRect::xz(0.0, 555.0, 0.0, 555.0, 555.0, &white));
+        world.add(AaRect::xy(0.0, 555.0, 0.0, 555.0, 555.0, &white));
+
+        let tall = Arc::new(Cuboid::new(Vec3::newi(0, 0, 0), Vec3::newi(165, 330, 165), &white));
+        world.add(Instance::new_box(tall, Mat4::translate(Vec3::newi(265, 0, 295)) * Mat4::rotate_y(15.0)));
+        let short = Arc::new(Cuboid::new(Vec3::newi(0, 0, 0), Vec3::newi(165, 165, 165), &white));
+        world.add(Instance::new_box(short, Mat4::translate(Vec3::newi(130, 0, 65)) * Mat4::rotate_y(-18.0)));
+
+        *aspect_ratio = Self::get_aspect_ratio(self);
+        let look_from = Vec3::newi(278, 278, -800);
+        let look_to = Vec3::newi(278, 278, 0);
+        let view_up = Vec3::newi(0, 1, 0);
+        let focus_dist = (look_from - look_to).length();
+        let aperture = 0.0;
+        let vert_fov = 40.0;
+
+        *cam = Camera::new(
+            look_from,
+            look_to,
+            view_up,
+            vert_fov,
+            *aspect_ratio,
+            aperture,
+            focus_dist,
+            rng
+        );
+    }
+
+    fn get_aspect_ratio(&self) -> f32 {
+        return 1.0;
+    }
+}
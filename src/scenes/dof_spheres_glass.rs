@@ -1,6 +1,8 @@
+use std::sync::Arc;
+
 use super::Scene;
 use rand_chacha::ChaCha20Rng;
-use crate::{camera::Camera, hittable::HittableList, mats::{MatManager, lambertian::LambertianMat, dielectric::DielectricMat, metal::MetalMat}, color::Color, hittables::sphere::Sphere, vec3::Vec3};
+use crate::{camera::Camera, hittable::HittableList, mats::{MatManager, lambertian::LambertianMat, dielectric::DielectricMat, metal::MetalMat}, color::Color, hittables::{csg::Csg, sphere::Sphere}, vec3::Vec3};
 
 #[derive(Clone, Copy)]
 pub struct DofSpheresGlass {}
@@ -13,6 +15,7 @@ impl Scene for DofSpheresGlass {
                 g: 125.0 / 255.0,
                 b: 70.0 / 255.0,
             },
+            roughness: 0.0,
         }));
         let sphere_center_mat: i64 = mats.gen_mat(Box::new(LambertianMat {
             albedo: Color {
@@ -20,6 +23,7 @@ impl Scene for DofSpheresGlass {
                 g: 0.3,
                 b: 0.3,
             },
+            roughness: 0.0,
         }));
         let sphere_left_mat: i64 = mats.gen_mat(Box::new(DielectricMat { refract_index: 1.5 }));
         let sphere_right_mat: i64 = mats.gen_mat(Box::new(MetalMat {
@@ -36,25 +40,14 @@ impl Scene for DofSpheresGlass {
             100.0,
             &ground_mat,
         ));
-        world.add(Sphere::new_box(
-            Vec3::new(-1.0, 0.0, -1.0),
-            0.5,
-            &sphere_left_mat,
-        ));
-        world.add(Sphere::new_box(
-            Vec3::new(-1.0, 0.0, -1.0),
-            -0.45,
-            &sphere_left_mat,
-        ));
-        world.add(Sphere::new_box(
-            Vec3::new(-1.0, 0.0, -1.0),
-            0.3,
-            &sphere_left_mat,
+        // Two nested glass shells, carved out with CSG rather than negative radius spheres
+        world.add(Csg::difference(
+            Arc::new(Sphere::new(Vec3::new(-1.0, 0.0, -1.0), 0.5, &sphere_left_mat)),
+            Arc::new(Sphere::new(Vec3::new(-1.0, 0.0, -1.0), 0.45, &sphere_left_mat)),
         ));
-        world.add(Sphere::new_box(
-            Vec3::new(-1.0, 0.0, -1.0),
-            -0.25,
-            &sphere_left_mat,
+        world.add(Csg::difference(
+            Arc::new(Sphere::new(Vec3::new(-1.0, 0.0, -1.0), 0.3, &sphere_left_mat)),
+            Arc::new(Sphere::new(Vec3::new(-1.0, 0.0, -1.0), 0.25, &sphere_left_mat)),
         ));
         world.add(Sphere::new_box(
             Vec3::new(-1.0, 0.0, -1.0),
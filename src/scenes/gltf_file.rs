@@ -0,0 +1,41 @@
+use super::Scene;
+use crate::{
+    camera::Camera,
+    hittable::HittableList,
+    loaders::gltf::{gltf_aspect_ratio, load_gltf, GltfImportOptions},
+    mats::MatManager,
+};
+use rand_chacha::ChaCha20Rng;
+
+// Whole scene from a .gltf/.glb file, e.g. a Blender export with "Punctual Lights" enabled
+#[derive(Clone, Copy)]
+pub struct GltfFile {
+    pub path: &'static str,
+    pub light_scale: f32,
+}
+
+impl Scene for GltfFile {
+    fn setup(&self, world: &mut HittableList, cam: &mut Camera, mats: &mut MatManager, aspect_ratio: &mut f32, rng: &mut ChaCha20Rng) {
+        let mut options = GltfImportOptions::default();
+        options.light_scale = self.light_scale;
+
+        let scene = match load_gltf(self.path, world, mats, &options, rng) {
+            Ok(scene) => scene,
+            Err(e) => {
+                // Whatever was added before the error goes, half a scene is more confusing than none
+                println!("Failed to load {}: {}, rendering an empty scene", self.path, e);
+                world.objs.clear();
+                *aspect_ratio = self.get_aspect_ratio();
+                return;
+            }
+        };
+        if let Some(camera) = scene.camera {
+            *cam = camera;
+        }
+        *aspect_ratio = scene.aspect_ratio.unwrap_or(self.get_aspect_ratio());
+    }
+
+    fn get_aspect_ratio(&self) -> f32 {
+        return gltf_aspect_ratio(self.path).unwrap_or(16.0 / 9.0);
+    }
+}
@@ -0,0 +1,64 @@
+use super::Scene;
+use crate::{
+    camera::Camera,
+    color::Color,
+    hittable::HittableList,
+    hittables::{mesh::MeshImportOptions, sphere::Sphere},
+    loaders::{ply::load_ply, stl::load_stl},
+    mats::{lambertian::LambertianMat, vertex_color::VertexColorMat, MatManager},
+    vec3::Vec3,
+};
+use rand_chacha::ChaCha20Rng;
+
+// A .ply or .stl model scaled to fit a 2 unit box and stood on a ground plane, e.g. a scan or a CAD
+// export. PLY vertex colors are used when the file has them.
+#[derive(Clone, Copy)]
+pub struct MeshFile {
+    pub path: &'static str,
+    // Interpolated vertex normals for files that only have flat facets, STL never has normals
+    pub smooth: bool,
+}
+
+impl Scene for MeshFile {
+    fn setup(&self, world: &mut HittableList, cam: &mut Camera, mats: &mut MatManager, aspect_ratio: &mut f32, rng: &mut ChaCha20Rng) {
+        let ground_mat = mats.gen_mat(Box::new(LambertianMat {
+            albedo: Color::new_01_range(0.5, 0.5, 0.5),
+            roughness: 0.0,
+        }));
+        world.add(Sphere::new_box(Vec3::new(0.0, -1000.0, 0.0), 1000.0, &ground_mat));
+
+        *aspect_ratio = Self::get_aspect_ratio(self);
+        let look_from = Vec3::new(0.0, 2.0, 5.0);
+        let look_to = Vec3::new(0.0, 1.0, 0.0);
+        let view_up = Vec3::newi(0, 1, 0);
+        let focus_dist = (look_from - look_to).length();
+        *cam = Camera::new(look_from, look_to, view_up, 40.0, *aspect_ratio, 0.0, focus_dist, rng);
+
+        let options = MeshImportOptions { fit_size: Some(2.0), smooth: self.smooth, ..MeshImportOptions::default() };
+        let loaded = if self.path.to_ascii_lowercase().ends_with(".stl") {
+            load_stl(self.path, &options)
+        } else {
+            load_ply(self.path, &options)
+        };
+        let mut mesh = match loaded {
+            Ok(mesh) => mesh,
+            Err(e) => {
+                println!("Failed to load {}: {}, rendering the ground only", self.path, e);
+                return;
+            }
+        };
+
+        // Resting on the ground, centred under the camera's target
+        let bound = mesh.bounds();
+        let lift = Vec3::new(-(bound.min.x + bound.max.x) / 2.0, -bound.min.y, -(bound.min.z + bound.max.z) / 2.0);
+        for p in mesh.positions.iter_mut() {
+            *p = *p + lift;
+        }
+        let mat = if mesh.colors.is_empty() {
+            mats.gen_mat(Box::new(LambertianMat { albedo: Color::new_01_range(0.7, 0.5, 0.3), roughness: 0.0 }))
+        } else {
+            mats.gen_mat(Box::new(VertexColorMat { fallback: Color::new_01_range(0.7, 0.5, 0.3) }))
+        };
+        world.add(mesh.into_bvh(&mat));
+    }
+}
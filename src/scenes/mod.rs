@@ -1,9 +1,17 @@
 pub mod dof_spheres_glass;
 pub mod random_spheres;
 pub mod cornell_box;
+pub mod cornell_room;
+pub mod smoke_cloud;
+pub mod primitives;
+pub mod sdf_shapes;
+pub mod gltf_file;
+pub mod bokeh;
+pub mod motion_blur;
+pub mod mesh_file;
 
 use rand_chacha::ChaCha20Rng;
-use crate::{camera::Camera, hittable::HittableList, mats::MatManager};
+use crate::{animation::CameraPath, camera::Camera, hittable::HittableList, mats::MatManager};
 
 pub trait Scene {
     fn setup(&self, world: &mut HittableList, camera: &mut Camera, mats: &mut MatManager, aspect_ratio: &mut f32, rng: &mut ChaCha20Rng);
@@ -11,4 +19,9 @@ pub trait Scene {
     fn get_aspect_ratio(&self) -> f32 {
         return 16.0 / 9.0;
     }
+
+    // Scenes with a camera move return it here, the frame-range render re-aims the camera set up above
+    fn camera_path(&self) -> Option<CameraPath> {
+        return None;
+    }
 }
\ No newline at end of file
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use super::Scene;
+use crate::{
+    camera::Camera,
+    color::Color,
+    hittable::HittableList,
+    hittables::{
+        cuboid::Cuboid,
+        instance::{MotionInstance, TransformKey},
+        moving_sphere::MovingSphere,
+        sphere::Sphere,
+    },
+    mats::{lambertian::LambertianMat, metal::MetalMat, MatManager},
+    vec3::Vec3,
+};
+use rand_chacha::ChaCha20Rng;
+
+// Bouncing spheres next to a cube spinning and sliding while the shutter is open
+#[derive(Clone, Copy)]
+pub struct MotionBlur {}
+
+impl Scene for MotionBlur {
+    fn setup(&self, world: &mut HittableList, cam: &mut Camera, mats: &mut MatManager, aspect_ratio: &mut f32, rng: &mut ChaCha20Rng) {
+        let ground_mat = mats.gen_mat(Box::new(LambertianMat {
+            albedo: Color::new_01_range(0.5, 0.5, 0.5),
+            roughness: 0.0,
+        }));
+        let ball_mats = [
+            Color::new_01_range(0.8, 0.3, 0.3),
+            Color::new_01_range(0.3, 0.8, 0.3),
+            Color::new_01_range(0.3, 0.3, 0.8),
+        ]
+        .map(|albedo| mats.gen_mat(Box::new(LambertianMat { albedo, roughness: 0.0 })));
+        let steel_mat = mats.gen_mat(Box::new(MetalMat {
+            albedo: Color::new_01_range(0.7, 0.7, 0.75),
+            fuzz: 0.2,
+        }));
+
+        world.add(Sphere::new_box(Vec3::new(0.0, -1000.0, 0.0), 1000.0, &ground_mat));
+        // Each ball falls a bit further than the last over the shutter interval
+        for (i, mat) in ball_mats.iter().enumerate() {
+            let x = -3.0 + 1.5 * i as f32;
+            let drop = 0.4 + 0.4 * i as f32;
+            world.add(MovingSphere::new_box(Vec3::new(x, 0.5 + drop, 0.0), Vec3::new(x, 0.5, 0.0), 0.0, 1.0, 0.5, mat));
+        }
+
+        let cube: Arc<Cuboid> = Arc::new(Cuboid::new(Vec3::new(-0.6, -0.6, -0.6), Vec3::new(0.6, 0.6, 0.6), &steel_mat));
+        let key = |time: f32, x: f32, turn: f32| TransformKey {
+            time,
+            translate: Vec3::new(x, 0.6, 0.0),
+            rotate: Vec3::new(0.0, turn, 0.0),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        };
+        world.add(MotionInstance::new_box(cube, vec![key(0.0, 1.8, 0.0), key(1.0, 2.6, 60.0)]));
+
+        *aspect_ratio = Self::get_aspect_ratio(self);
+        let look_from = Vec3::new(0.0, 2.0, 8.0);
+        let look_to = Vec3::new(0.0, 0.7, 0.0);
+        let view_up = Vec3::newi(0, 1, 0);
+        let focus_dist = (look_from - look_to).length();
+        let aperture = 0.0;
+        let vert_fov = 40.0;
+
+        *cam = Camera::new(
+            look_from,
+            look_to,
+            view_up,
+            vert_fov,
+            *aspect_ratio,
+            aperture,
+            focus_dist,
+            rng
+        );
+        cam.set_shutter(0.0, 1.0);
+    }
+}
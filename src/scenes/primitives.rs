@@ -0,0 +1,66 @@
+use super::Scene;
+use crate::{
+    animation::CameraPath,
+    camera::Camera,
+    color::Color,
+    hittable::HittableList,
+    hittables::{cone::Cone, cylinder::Cylinder, disk::Disk, plane::Plane, sphere::Sphere, torus::Torus},
+    mats::{dielectric::DielectricMat, diffuse_light::DiffuseLight, lambertian::LambertianMat, metal::MetalMat, MatManager},
+    vec3::Vec3,
+};
+use rand_chacha::ChaCha20Rng;
+
+#[derive(Clone, Copy)]
+pub struct Primitives {}
+
+impl Scene for Primitives {
+    fn setup(&self, world: &mut HittableList, cam: &mut Camera, mats: &mut MatManager, aspect_ratio: &mut f32, rng: &mut ChaCha20Rng) {
+        let ground_mat = mats.gen_mat(Box::new(LambertianMat {
+            albedo: Color::new_01_range(0.5, 0.5, 0.5),
+            roughness: 0.0,
+        }));
+        let clay_mat = mats.gen_mat(Box::new(LambertianMat {
+            albedo: Color::new_01_range(0.8, 0.4, 0.3),
+            roughness: 0.0,
+        }));
+        let gold_mat = mats.gen_mat(Box::new(MetalMat {
+            albedo: Color::new_01_range(0.8, 0.6, 0.2),
+            fuzz: 0.1,
+        }));
+        let glass_mat = mats.gen_mat(Box::new(DielectricMat { refract_index: 1.5 }));
+        let light_mat = mats.gen_mat(Box::new(DiffuseLight {
+            emit: Color::new_01_range(4.0, 4.0, 4.0),
+        }));
+
+        world.add(Plane::new_box(Vec3::newi(0, 0, 0), Vec3::newi(0, 1, 0), &ground_mat));
+        world.add(Cylinder::new_box(Vec3::new(-3.0, 0.0, 0.0), 0.7, 1.6, &clay_mat));
+        world.add(Cone::new_box(Vec3::new(-1.0, 0.0, 0.0), 0.7, 1.8, &gold_mat));
+        world.add(Torus::new_box(Vec3::new(1.2, 0.35, 0.0), 0.8, 0.35, &clay_mat));
+        world.add(Sphere::new_box(Vec3::new(3.2, 0.8, 0.0), 0.8, &glass_mat));
+        world.add(Disk::new_box(Vec3::new(0.0, 5.0, 1.0), Vec3::newi(0, -1, 0), 2.5, &light_mat));
+
+        *aspect_ratio = Self::get_aspect_ratio(self);
+        let look_from = Vec3::new(0.0, 3.0, 9.0);
+        let look_to = Vec3::new(0.0, 0.7, 0.0);
+        let view_up = Vec3::newi(0, 1, 0);
+        let focus_dist = (look_from - look_to).length();
+        let aperture = 0.0;
+        let vert_fov = 40.0;
+
+        *cam = Camera::new(
+            look_from,
+            look_to,
+            view_up,
+            vert_fov,
+            *aspect_ratio,
+            aperture,
+            focus_dist,
+            rng
+        );
+    }
+
+    // Four second orbit at the still's height and distance
+    fn camera_path(&self) -> Option<CameraPath> {
+        return Some(CameraPath::turntable(Vec3::new(0.0, 0.7, 0.0), 9.0, 2.3, 40.0, 4.0, 24.0));
+    }
+}
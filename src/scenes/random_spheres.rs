@@ -25,6 +25,7 @@ impl Scene for RandomSpheres {
         //let mut world = HittableList { objs: vec![] };
         let ground_mat: i64 = mats.gen_mat(Box::new(LambertianMat {
             albedo: Color::new_01_range(0.5, 0.5, 0.5),
+            roughness: 0.0,
         }));
         world.add(Sphere::new_box(
             Vec3::new(0.0, -1000.0, 0.0),
@@ -35,6 +36,7 @@ impl Scene for RandomSpheres {
         let mat1: i64 = mats.gen_mat(Box::new(DielectricMat { refract_index: 1.5 }));
         let mat2: i64 = mats.gen_mat(Box::new(LambertianMat {
             albedo: Color::new_01_range(0.4, 0.2, 0.1),
+            roughness: 0.0,
         }));
         let mat3: i64 = mats.gen_mat(Box::new(MetalMat {
             albedo: Color {
@@ -65,15 +67,7 @@ impl Scene for RandomSpheres {
                     b as f32 + 0.9 * rand_double(rng),
                 );
 
-                if (center
-                    - Vec3 {
-                        x: 4.0,
-                        y: 0.2,
-                        z: 0.0
-                    })
-                .length()
-                    > 0.9
-                {
+                if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                     let mut inner_sphere = false;
                     let sphere_mat = mats.gen_mat(if choose_mat < 0.7 {
                         Box::new(LambertianMat {
@@ -82,6 +76,7 @@ impl Scene for RandomSpheres {
                                 rand_double(rng) * rand_double(rng),
                                 rand_double(rng) * rand_double(rng),
                             ),
+                            roughness: 0.0,
                         })
                     } else if choose_mat < 0.85 {
                         Box::new(MetalMat {
@@ -0,0 +1,87 @@
+use super::Scene;
+use crate::{
+    aabb::AABB,
+    camera::Camera,
+    color::Color,
+    hittable::HittableList,
+    hittables::{sdf::{SdfNode, SdfShape}, sphere::Sphere},
+    mats::{diffuse_light::DiffuseLight, lambertian::LambertianMat, metal::MetalMat, MatManager},
+    vec3::Vec3,
+};
+use rand_chacha::ChaCha20Rng;
+
+#[derive(Clone, Copy)]
+pub struct SdfShapes {}
+
+impl Scene for SdfShapes {
+    fn setup(&self, world: &mut HittableList, cam: &mut Camera, mats: &mut MatManager, aspect_ratio: &mut f32, rng: &mut ChaCha20Rng) {
+        let ground_mat = mats.gen_mat(Box::new(LambertianMat {
+            albedo: Color::new_01_range(0.5, 0.5, 0.5),
+            roughness: 0.0,
+        }));
+        let clay_mat = mats.gen_mat(Box::new(LambertianMat {
+            albedo: Color::new_01_range(0.75, 0.45, 0.35),
+            roughness: 0.5,
+        }));
+        let steel_mat = mats.gen_mat(Box::new(MetalMat {
+            albedo: Color::new_01_range(0.8, 0.8, 0.85),
+            fuzz: 0.05,
+        }));
+        let light_mat = mats.gen_mat(Box::new(DiffuseLight {
+            emit: Color::new_01_range(6.0, 6.0, 6.0),
+        }));
+
+        world.add(Sphere::new_box(Vec3::new(0.0, -1000.0, 0.0), 1000.0, &ground_mat));
+        world.add(Sphere::new_box(Vec3::new(0.0, 8.0, 4.0), 2.0, &light_mat));
+
+        // Twisted column
+        let column = SdfNode::cuboid(Vec3::new(0.4, 1.2, 0.4)).twist(1.2).translate(Vec3::new(-2.2, 1.2, 0.0));
+        world.add(SdfShape::new_box(
+            column,
+            AABB { min: Vec3::new(-2.9, 0.0, -0.7), max: Vec3::new(-1.5, 2.4, 0.7) },
+            0.6,
+            &clay_mat,
+        ));
+
+        // Blobby smooth union of spheres with a bumpy skin
+        let blob = SdfNode::sphere(0.7)
+            .smooth_union(SdfNode::sphere(0.5).translate(Vec3::new(0.6, 0.6, 0.0)), 0.3)
+            .smooth_union(SdfNode::sphere(0.45).translate(Vec3::new(-0.5, 0.5, 0.3)), 0.3)
+            .displace(0.03, 12.0)
+            .translate(Vec3::new(0.0, 0.8, 0.0));
+        world.add(SdfShape::new_box(
+            blob,
+            AABB { min: Vec3::new(-1.1, 0.0, -0.8), max: Vec3::new(1.2, 2.0, 0.9) },
+            0.8,
+            &clay_mat,
+        ));
+
+        // Grid of tori, repetition clipped to the bounding box
+        let rings = SdfNode::torus(0.25, 0.07).repeat(Vec3::new(0.7, 0.0, 0.7)).translate(Vec3::new(2.45, 0.07, 0.0));
+        world.add(SdfShape::new_box(
+            rings,
+            AABB { min: Vec3::new(1.75, 0.0, -1.05), max: Vec3::new(3.15, 0.15, 1.05) },
+            1.0,
+            &steel_mat,
+        ));
+
+        *aspect_ratio = Self::get_aspect_ratio(self);
+        let look_from = Vec3::new(0.0, 3.0, 7.0);
+        let look_to = Vec3::new(0.0, 0.8, 0.0);
+        let view_up = Vec3::newi(0, 1, 0);
+        let focus_dist = (look_from - look_to).length();
+        let aperture = 0.0;
+        let vert_fov = 40.0;
+
+        *cam = Camera::new(
+            look_from,
+            look_to,
+            view_up,
+            vert_fov,
+            *aspect_ratio,
+            aperture,
+            focus_dist,
+            rng
+        );
+    }
+}
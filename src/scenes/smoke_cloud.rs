@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use super::Scene;
+use crate::{
+    aabb::AABB,
+    camera::Camera,
+    color::Color,
+    hittable::HittableList,
+    hittables::{sphere::Sphere, voxel_volume::VoxelVolume},
+    mats::{diffuse_light::DiffuseLight, isotropic::IsotropicMat, lambertian::LambertianMat, MatManager},
+    perlin::Perlin,
+    vec3::Vec3,
+    voxel_grid::VoxelGrid,
+};
+use rand_chacha::ChaCha20Rng;
+
+#[derive(Clone, Copy)]
+pub struct SmokeCloud {}
+
+impl Scene for SmokeCloud {
+    fn setup(&self, world: &mut HittableList, cam: &mut Camera, mats: &mut MatManager, aspect_ratio: &mut f32, rng: &mut ChaCha20Rng) {
+        let ground_mat = mats.gen_mat(Box::new(LambertianMat {
+            albedo: Color::new_01_range(0.4, 0.45, 0.4),
+            roughness: 0.0,
+        }));
+        let light_mat = mats.gen_mat(Box::new(DiffuseLight {
+            emit: Color::new_01_range(8.0, 7.5, 7.0),
+        }));
+        let cloud_mat = mats.gen_mat(Box::new(IsotropicMat {
+            albedo: Color::new_01_range(0.9, 0.9, 0.9),
+        }));
+
+        world.add(Sphere::new_box(Vec3::new(0.0, -1000.0, 0.0), 1000.0, &ground_mat));
+        world.add(Sphere::new_box(Vec3::new(-4.0, 7.0, 3.0), 1.5, &light_mat));
+
+        // Swap for VoxelGrid::load_vgrid / load_raw to render a simulated cache instead
+        let noise = Perlin::new(rng);
+        let grid = Arc::new(VoxelGrid::from_noise(96, 64, 96, &noise, 4.0, 5, 0.9));
+        world.add(VoxelVolume::new_box(
+            grid,
+            AABB {
+                min: Vec3::new(-3.0, 0.5, -3.0),
+                max: Vec3::new(3.0, 4.5, 3.0),
+            },
+            6.0,
+            &cloud_mat,
+        ));
+
+        *aspect_ratio = Self::get_aspect_ratio(self);
+        let look_from = Vec3::newi(0, 3, 14);
+        let look_to = Vec3::new(0.0, 2.5, 0.0);
+        let view_up = Vec3::newi(0, 1, 0);
+        let focus_dist = (look_from - look_to).length();
+        let aperture = 0.0;
+        let vert_fov = 35.0;
+
+        *cam = Camera::new(
+            look_from,
+            look_to,
+            view_up,
+            vert_fov,
+            *aspect_ratio,
+            aperture,
+            focus_dist,
+            rng
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::{cell::RefCell, fmt::Write, fs, time::Duration};
+
+// Counters kept per render thread, bumped from wherever the work happens and collected with take()
+// when the thread is done. Nothing is shared, so counting costs next to nothing.
+#[derive(Clone, Default)]
+pub struct Counters {
+    pub camera_rays: u64,
+    pub bounce_rays: u64,
+    // Always zero until the renderer samples lights directly
+    pub shadow_rays: u64,
+    pub bvh_nodes: u64,
+    pub primitive_tests: u64,
+    // Paths by how many rays they traced
+    pub path_lengths: Vec<u64>,
+    // Time in the wavefront integrator's stages, see WAVEFRONT_STAGES
+    pub wavefront: [Duration; 4],
+}
+
+#[derive(Clone, Copy)]
+pub enum RayKind {
+    Camera,
+    Bounce,
+    #[allow(dead_code)]
+    Shadow,
+}
+
+pub const WAVEFRONT_STAGES: [&str; 4] = ["generation", "extension", "shading", "compaction"];
+
+thread_local! {
+    static COUNTERS: RefCell<Counters> = RefCell::new(Counters::default());
+}
+
+pub fn count_ray(kind: RayKind) {
+    COUNTERS.with_borrow_mut(|c| match kind {
+        RayKind::Camera => c.camera_rays += 1,
+        RayKind::Bounce => c.bounce_rays += 1,
+        RayKind::Shadow => c.shadow_rays += 1,
+    });
+}
+
+pub fn count_bvh_node() {
+    COUNTERS.with_borrow_mut(|c| c.bvh_nodes += 1);
+}
+
+pub fn count_primitive_test() {
+    COUNTERS.with_borrow_mut(|c| c.primitive_tests += 1);
+}
+
+// For the wide tests, one per lane
+pub fn count_primitive_tests(lanes: u64) {
+    COUNTERS.with_borrow_mut(|c| c.primitive_tests += lanes);
+}
+
+// Rays this thread has traced so far, the difference across a sample is its path length
+pub fn rays_traced() -> u64 {
+    return COUNTERS.with_borrow(|c| c.rays());
+}
+
+pub fn record_path(rays: u64) {
+    COUNTERS.with_borrow_mut(|c| {
+        let len = rays as usize;
+        if c.path_lengths.len() <= len {
+            c.path_lengths.resize(len + 1, 0);
+        }
+        c.path_lengths[len] += 1;
+    });
+}
+
+pub fn add_wavefront_time(stage: usize, time: Duration) {
+    COUNTERS.with_borrow_mut(|c| c.wavefront[stage] += time);
+}
+
+// This thread's counters, which start again from zero
+pub fn take() -> Counters {
+    return COUNTERS.with_borrow_mut(std::mem::take);
+}
+
+impl Counters {
+    pub fn rays(&self) -> u64 {
+        return self.camera_rays + self.bounce_rays + self.shadow_rays;
+    }
+
+    pub fn paths(&self) -> u64 {
+        return self.path_lengths.iter().sum();
+    }
+
+    pub fn add(&mut self, other: &Counters) {
+        self.camera_rays += other.camera_rays;
+        self.bounce_rays += other.bounce_rays;
+        self.shadow_rays += other.shadow_rays;
+        self.bvh_nodes += other.bvh_nodes;
+        self.primitive_tests += other.primitive_tests;
+        if self.path_lengths.len() < other.path_lengths.len() {
+            self.path_lengths.resize(other.path_lengths.len(), 0);
+        }
+        for (a, b) in self.path_lengths.iter_mut().zip(other.path_lengths.iter()) {
+            *a += b;
+        }
+        for (a, b) in self.wavefront.iter_mut().zip(other.wavefront.iter()) {
+            *a += *b;
+        }
+    }
+}
+
+// One render thread's counters and how long it was busy for
+#[derive(Clone)]
+pub struct ThreadStats {
+    pub counters: Counters,
+    pub busy: Duration,
+}
+
+// Everything a render did, printed with text() or saved with json() for tracking across builds
+pub struct Report {
+    pub threads: Vec<ThreadStats>,
+    // Wall clock time of each step of main(), in order
+    pub stages: Vec<(&'static str, Duration)>,
+    pub peak_memory: Option<u64>,
+}
+
+impl Report {
+    pub fn new() -> Report {
+        return Report { threads: vec![], stages: vec![], peak_memory: None };
+    }
+
+    // Adds a frame's threads to the ones before, by position
+    pub fn add_frame(&mut self, threads: &[ThreadStats]) {
+        for (i, thread) in threads.iter().enumerate() {
+            if i == self.threads.len() {
+                self.threads.push(ThreadStats { counters: Counters::default(), busy: Duration::ZERO });
+            }
+            self.threads[i].counters.add(&thread.counters);
+            self.threads[i].busy += thread.busy;
+        }
+    }
+
+    pub fn totals(&self) -> Counters {
+        let mut total = Counters::default();
+        for thread in &self.threads {
+            total.add(&thread.counters);
+        }
+        return total;
+    }
+
+    fn per_ray(&self, count: u64) -> f64 {
+        let rays = self.totals().rays();
+        return if rays > 0 { count as f64 / rays as f64 } else { 0.0 };
+    }
+
+    fn average_path_length(&self) -> f64 {
+        let total = self.totals();
+        return if total.paths() > 0 { total.rays() as f64 / total.paths() as f64 } else { 0.0 };
+    }
+
+    pub fn text(&self) -> String {
+        let total = self.totals();
+        let mut out = String::new();
+        writeln!(out, "Rays             {} camera, {} bounce, {} shadow, {} total", total.camera_rays, total.bounce_rays, total.shadow_rays, total.rays()).unwrap();
+        for (i, thread) in self.threads.iter().enumerate() {
+            let rate = thread.counters.rays() as f64 / thread.busy.as_secs_f64().max(1e-9) / 1e6;
+            writeln!(out, "  Thread {i:<3}     {rate:.3} Mrays/s over {:.3}s", thread.busy.as_secs_f64()).unwrap();
+        }
+        writeln!(out, "Paths            {}, {:.3} rays on average", total.paths(), self.average_path_length()).unwrap();
+        writeln!(out, "BVH nodes        {} visited, {:.2} per ray", total.bvh_nodes, self.per_ray(total.bvh_nodes)).unwrap();
+        writeln!(out, "Primitive tests  {}, {:.2} per ray", total.primitive_tests, self.per_ray(total.primitive_tests)).unwrap();
+        for (name, time) in &self.stages {
+            writeln!(out, "{:<16} {:.3}s", capitalise(name), time.as_secs_f64()).unwrap();
+        }
+        if total.wavefront.iter().any(|t| !t.is_zero()) {
+            for (name, time) in WAVEFRONT_STAGES.iter().zip(total.wavefront.iter()) {
+                writeln!(out, "  Wavefront {:<10} {:.3}s over all threads", name, time.as_secs_f64()).unwrap();
+            }
+        }
+        if let Some(bytes) = self.peak_memory {
+            writeln!(out, "Peak memory      {:.1} MiB", bytes as f64 / (1024.0 * 1024.0)).unwrap();
+        }
+
+        writeln!(out, "Path lengths").unwrap();
+        let most = total.path_lengths.iter().copied().max().unwrap_or(0).max(1);
+        for (len, count) in total.path_lengths.iter().enumerate().filter(|(_, c)| **c > 0) {
+            let bar = "#".repeat(((count * 50 + most - 1) / most) as usize);
+            writeln!(out, "  {len:>4} rays {count:>12} {bar}").unwrap();
+        }
+        return out;
+    }
+
+    pub fn json(&self) -> String {
+        let total = self.totals();
+        let mut out = String::new();
+        write!(out, "{{\n  \"rays\": {{\"camera\": {}, \"bounce\": {}, \"shadow\": {}, \"total\": {}}},\n", total.camera_rays, total.bounce_rays, total.shadow_rays, total.rays()).unwrap();
+        let threads: Vec<String> = self
+            .threads
+            .iter()
+            .map(|t| {
+                let secs = t.busy.as_secs_f64();
+                format!("{{\"rays\": {}, \"seconds\": {:.6}, \"rays_per_second\": {:.1}}}", t.counters.rays(), secs, t.counters.rays() as f64 / secs.max(1e-9))
+            })
+            .collect();
+        write!(out, "  \"threads\": [{}],\n", threads.join(", ")).unwrap();
+        write!(out, "  \"paths\": {},\n  \"average_path_length\": {:.6},\n", total.paths(), self.average_path_length()).unwrap();
+        write!(out, "  \"bvh_nodes_visited\": {},\n  \"bvh_nodes_per_ray\": {:.6},\n", total.bvh_nodes, self.per_ray(total.bvh_nodes)).unwrap();
+        write!(out, "  \"primitive_tests\": {},\n  \"primitive_tests_per_ray\": {:.6},\n", total.primitive_tests, self.per_ray(total.primitive_tests)).unwrap();
+        let stages: Vec<String> = self.stages.iter().map(|(name, time)| format!("\"{}\": {:.6}", name, time.as_secs_f64())).collect();
+        write!(out, "  \"stage_seconds\": {{{}}},\n", stages.join(", ")).unwrap();
+        let wavefront: Vec<String> = WAVEFRONT_STAGES.iter().zip(total.wavefront.iter()).map(|(name, time)| format!("\"{}\": {:.6}", name, time.as_secs_f64())).collect();
+        write!(out, "  \"wavefront_stage_seconds\": {{{}}},\n", wavefront.join(", ")).unwrap();
+        match self.peak_memory {
+            Some(bytes) => write!(out, "  \"peak_memory_bytes\": {},\n", bytes).unwrap(),
+            None => write!(out, "  \"peak_memory_bytes\": null,\n").unwrap(),
+        }
+        let lengths: Vec<String> = total.path_lengths.iter().map(|c| c.to_string()).collect();
+        write!(out, "  \"path_length_histogram\": [{}]\n}}\n", lengths.join(", ")).unwrap();
+        return out;
+    }
+}
+
+fn capitalise(name: &str) -> String {
+    let mut chars = name.chars();
+    return match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    };
+}
+
+// High water mark of the resident set from /proc, None where there's no procfs
+pub fn peak_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    return Some(kib * 1024);
+}
@@ -0,0 +1,81 @@
+use std::{path::Path, simd::f32x4};
+
+// Linear float RGBA image, sampled bilinearly. v = 0 is the top row.
+pub struct ImageTexture {
+    pub width: u32,
+    pub height: u32,
+    pub texels: Vec<f32x4>,
+    // What lies outside [0, 1] along u and v, repeat unless set otherwise
+    pub wrap_u: Wrap,
+    pub wrap_v: Wrap,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl Wrap {
+    // Texel index `i` of a row or column `n` long moved inside it
+    fn apply(&self, i: i64, n: i64) -> usize {
+        return match self {
+            Wrap::Repeat => i.rem_euclid(n),
+            Wrap::MirroredRepeat => {
+                let p = i.rem_euclid(2 * n);
+                if p < n { p } else { 2 * n - 1 - p }
+            }
+            Wrap::ClampToEdge => i.clamp(0, n - 1),
+        } as usize;
+    }
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        return c / 12.92;
+    }
+    return ((c + 0.055) / 1.055).powf(2.4);
+}
+
+impl ImageTexture {
+    // `channels` normalized values per texel in [0, 1]. Colour data is stored sRGB encoded,
+    // data textures (roughness, normals) are not, hence the flag. Alpha is never converted.
+    pub fn from_normalized(width: u32, height: u32, channels: usize, values: &[f32], srgb: bool) -> ImageTexture {
+        let decode = |c: f32| if srgb { srgb_to_linear(c) } else { c };
+        let texels = values
+            .chunks_exact(channels)
+            .map(|px| match channels {
+                1 => f32x4::from_array([decode(px[0]), decode(px[0]), decode(px[0]), 1.0]),
+                2 => f32x4::from_array([decode(px[0]), decode(px[0]), decode(px[0]), px[1]]),
+                3 => f32x4::from_array([decode(px[0]), decode(px[1]), decode(px[2]), 1.0]),
+                _ => f32x4::from_array([decode(px[0]), decode(px[1]), decode(px[2]), px[3]]),
+            })
+            .collect();
+        return ImageTexture { width, height, texels, wrap_u: Wrap::Repeat, wrap_v: Wrap::Repeat };
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P, srgb: bool) -> image::ImageResult<ImageTexture> {
+        let im = image::open(path)?.into_rgba32f();
+        let (width, height) = im.dimensions();
+        return Ok(Self::from_normalized(width, height, 4, im.as_raw(), srgb));
+    }
+
+    fn texel(&self, x: i64, y: i64) -> f32x4 {
+        let x = self.wrap_u.apply(x, self.width as i64);
+        let y = self.wrap_v.apply(y, self.height as i64);
+        return self.texels[x + y * self.width as usize];
+    }
+
+    pub fn sample(&self, u: f32, v: f32) -> f32x4 {
+        let fx = u * self.width as f32 - 0.5;
+        let fy = v * self.height as f32 - 0.5;
+        let (x0, y0) = (fx.floor(), fy.floor());
+        let (tx, ty) = (f32x4::splat(fx - x0), f32x4::splat(fy - y0));
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = self.texel(x0, y0) + (self.texel(x0 + 1, y0) - self.texel(x0, y0)) * tx;
+        let bottom = self.texel(x0, y0 + 1) + (self.texel(x0 + 1, y0 + 1) - self.texel(x0, y0 + 1)) * tx;
+        return top + (bottom - top) * ty;
+    }
+}
@@ -1,7 +1,7 @@
 use std::f32::consts::PI;
 
 use rand_chacha::ChaCha20Rng;
-use crate::{vec3::Vec3, rand_double::rand_double_range};
+use crate::{rand_double::rand_double, vec3::Vec3, warps};
 
 #[allow(dead_code)]
 pub fn deg_to_rad(deg: f32) -> f32 {
@@ -9,33 +9,19 @@ pub fn deg_to_rad(deg: f32) -> f32 {
 }
 
 pub fn random_in_unit_sphere(rng: &mut ChaCha20Rng) -> Vec3 {
-    loop {
-        let p = Vec3::rand_range(rng, -1.0, 1.0);
-        if p.length_squared() >= 1.0 {
-            continue;
-        };
-        return p;
-    }
-}
-pub fn random_in_unit_disk(rng: &mut ChaCha20Rng) -> Vec3 {
-    loop {
-        let p = Vec3::new(rand_double_range(rng, -1.0, 1.0), rand_double_range(rng, -1.0, 1.0), 0.0);
-        if p.length_squared() >= 1.0 {
-            continue;
-        };
-        return p;
-    }
+    return warps::uniform_ball((rand_double(rng), rand_double(rng)), rand_double(rng));
 }
 //fn random_unit_vector(rng: &mut ChaCha20Rng) -> Vec3 {
 //    return random_in_unit_sphere(rng).unit_vector();
 //}
-pub fn random_in_hemisphere(normal: &Vec3, rng: &mut ChaCha20Rng) -> Vec3 {
-    let in_unit_sphere = random_in_unit_sphere(rng);
-    if in_unit_sphere.dot_prod(normal.clone()) > 0.0 {
-        return in_unit_sphere;
-    } else {
-        return -in_unit_sphere;
+
+// Mirrors a direction that a shading normal sent below the geometric surface back above it
+pub fn keep_above(dir: Vec3, geo_normal: &Vec3) -> Vec3 {
+    let d = dir.dot_prod(*geo_normal);
+    if d >= 0.0 {
+        return dir;
     }
+    return dir - 2.0 * d * (*geo_normal);
 }
 
 pub fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
@@ -1,180 +1,123 @@
-use std::{ops::{Add, Div, Mul, Neg, Sub}, simd::f32x4};
+use std::{
+    ops::{Add, Deref, DerefMut, Div, Mul, Neg, Sub},
+    simd::{f32x4, num::SimdFloat, simd_swizzle},
+};
 
 use rand_chacha::ChaCha20Rng;
 
 use crate::rand_double::{rand_double, rand_double_range};
 
+// Stored as a SIMD vector so every operation is a single vector instruction. The fourth lane is
+// always zero, so sums over all lanes (dot, length) come out the same as over three.
 #[derive(Copy, Clone)]
-pub struct Vec3 {
+#[repr(transparent)]
+pub struct Vec3(f32x4);
+
+// The components as fields, `v.x` reads and writes the vector's first lane through Deref
+#[repr(C)]
+pub struct Xyz {
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
+
+impl Deref for Vec3 {
+    type Target = Xyz;
+    fn deref(&self) -> &Xyz {
+        // f32x4 is four f32s in a row, the first three are laid out like Xyz
+        return unsafe { &*(self as *const Vec3 as *const Xyz) };
+    }
+}
+impl DerefMut for Vec3 {
+    fn deref_mut(&mut self) -> &mut Xyz {
+        return unsafe { &mut *(self as *mut Vec3 as *mut Xyz) };
+    }
+}
+
 impl Add<Vec3> for Vec3 {
     type Output = Self;
     fn add(self, _rhs: Self) -> Self {
-        Self {
-            x: self.x + _rhs.x,
-            y: self.y + _rhs.y,
-            z: self.z + _rhs.z,
-        }
+        return Vec3(self.0 + _rhs.0);
     }
 }
 impl Mul<Vec3> for Vec3 {
     type Output = Self;
     fn mul(self, _rhs: Self) -> Self {
-        //Self {
-        //    x: self.x * _rhs.x,
-        //    y: self.y * _rhs.y,
-        //    z: self.z * _rhs.z,
-        //}
-        return Vec3::from_simd4(
-            self.to_simd4() * _rhs.to_simd4()
-        );
+        return Vec3(self.0 * _rhs.0);
     }
 }
 impl Mul<i64> for Vec3 {
     type Output = Self;
     fn mul(self, _rhs: i64) -> Self {
-        //Self {
-        //    x: self.x * _rhs as f32,
-        //    y: self.y * _rhs as f32,
-        //    z: self.z * _rhs as f32,
-        //}
-        return Vec3::from_simd4(
-            self.to_simd4() * f32x4::from_array([_rhs as f32, _rhs as f32, _rhs as f32, 1.0])
-        );
+        return Vec3::from_simd4(self.0 * f32x4::splat(_rhs as f32));
     }
 }
 impl Mul<f32> for Vec3 {
     type Output = Self;
     fn mul(self, _rhs: f32) -> Self {
-        //Self {
-        //    x: self.x * _rhs,
-        //    y: self.y * _rhs,
-        //    z: self.z * _rhs,
-        //}
-        return Vec3::from_simd4(
-            self.to_simd4() * f32x4::from_array([_rhs, _rhs, _rhs, 1.0])
-        );
+        return Vec3::from_simd4(self.0 * f32x4::splat(_rhs));
     }
 }
 impl Div<Vec3> for Vec3 {
     type Output = Self;
     fn div(self, _rhs: Self) -> Self {
-        //Self {
-        //    x: self.x / _rhs.x,
-        //    y: self.y / _rhs.y,
-        //    z: self.z / _rhs.z,
-        //}
-        return Vec3::from_simd4(
-            self.to_simd4() / _rhs.to_simd4()
-        );
+        return Vec3::from_simd4(self.0 / _rhs.0);
     }
 }
 impl Div<i64> for Vec3 {
     type Output = Self;
     fn div(self, _rhs: i64) -> Self {
-        //Self {
-        //    x: self.x / _rhs as f32,
-        //    y: self.y / _rhs as f32,
-        //    z: self.z / _rhs as f32,
-        //}
-        return Vec3::from_simd4(
-            self.to_simd4() / f32x4::from_array([_rhs as f32, _rhs as f32, _rhs as f32, 1.0])
-        );
+        return Vec3::from_simd4(self.0 / f32x4::splat(_rhs as f32));
     }
 }
 impl Div<f32> for Vec3 {
     type Output = Self;
     fn div(self, _rhs: f32) -> Self {
-        //Self {
-        //    x: self.x / _rhs,
-        //    y: self.y / _rhs,
-        //    z: self.z / _rhs,
-        //}
-        return Vec3::from_simd4(
-            self.to_simd4() / f32x4::from_array([_rhs, _rhs, _rhs, 1.0])
-        );
+        return Vec3::from_simd4(self.0 / f32x4::splat(_rhs));
     }
 }
 impl Sub<Vec3> for Vec3 {
     type Output = Self;
     fn sub(self, _rhs: Self) -> Self {
-        Self {
-            x: self.x - _rhs.x,
-            y: self.y - _rhs.y,
-            z: self.z - _rhs.z,
-        }
+        return Vec3(self.0 - _rhs.0);
     }
 }
 impl Neg for Vec3 {
     type Output = Self;
     fn neg(self) -> Self {
-        Self {
-            x: -self.x,
-            y: -self.y,
-            z: -self.z,
-        }
+        return Vec3(-self.0);
     }
 }
 
 impl Mul<Vec3> for i64 {
     type Output = Vec3;
     fn mul(self, _rhs: Vec3) -> Vec3 {
-        //Vec3 {
-        //    x: _rhs.x * self as f32,
-        //    y: _rhs.y * self as f32,
-        //    z: _rhs.z * self as f32,
-        //}
-        return Vec3::from_simd4(
-            _rhs.to_simd4() * f32x4::from_array([self as f32, self as f32, self as f32, 1.0])
-        );
+        return _rhs * self;
     }
 }
 impl Mul<Vec3> for f32 {
     type Output = Vec3;
     fn mul(self, _rhs: Vec3) -> Vec3 {
-        //Vec3 {
-        //    x: _rhs.x * self,
-        //    y: _rhs.y * self,
-        //    z: _rhs.z * self,
-        //}
-        return Vec3::from_simd4(
-            _rhs.to_simd4() * f32x4::from_array([self, self, self, 1.0])
-        );
+        return _rhs * self;
     }
 }
+// Both divide the vector by the number, whichever side it's written on
 impl Div<Vec3> for i64 {
     type Output = Vec3;
     fn div(self, _rhs: Vec3) -> Vec3 {
-        //Vec3 {
-        //    x: _rhs.x / self as f32,
-        //    y: _rhs.y / self as f32,
-        //    z: _rhs.z / self as f32,
-        //}
-        return Vec3::from_simd4(
-            _rhs.to_simd4() / f32x4::from_array([self as f32, self as f32, self as f32, 1.0])
-        );
+        return _rhs / self;
     }
 }
 impl Div<Vec3> for f32 {
     type Output = Vec3;
     fn div(self, _rhs: Vec3) -> Vec3 {
-        //Vec3 {
-        //    x: _rhs.x / self,
-        //    y: _rhs.y / self,
-        //    z: _rhs.z / self,
-        //}
-        return Vec3::from_simd4(
-            _rhs.to_simd4() / f32x4::from_array([self, self, self, 1.0])
-        );
+        return _rhs / self;
     }
 }
 
 impl Vec3 {
     pub fn length_squared(&self) -> f32 {
-        return (self.to_simd4() * self.to_simd4())[0..3].iter().sum()
+        return (self.0 * self.0).reduce_sum();
     }
     pub fn length(&self) -> f32 {
         return self.length_squared().sqrt();
@@ -185,52 +128,33 @@ impl Vec3 {
     }
 
     pub fn dot_prod(&self, other: Vec3) -> f32 {
-        //return self.x * other.x + self.y * other.y + self.z * other.z;
-        return (self.to_simd4() * other.to_simd4())[0..3].iter().sum()
+        return (self.0 * other.0).reduce_sum();
     }
     pub fn cross_prod(&self, other: Vec3) -> Vec3 {
-        //return Vec3 {
-        //    x: self.y * other.z - self.x * other.y,
-        //    y: self.z * other.x - self.x * other.z,
-        //    z: self.x * other.y - self.y * other.x,
-        //};
-
-        return Self::from_simd4(
-            f32x4::from_array([self.y, self.z, self.x, 0.0]) *
-                f32x4::from_array([other.z, other.x, other.y, 0.0]) -
-                f32x4::from_array([self.x, self.x, self.y, 0.0]) *
-                f32x4::from_array([other.y, other.z, other.x, 0.0])
-        )
+        let a = simd_swizzle!(self.0, [1, 2, 0, 3]) * simd_swizzle!(other.0, [2, 0, 1, 3]);
+        let b = simd_swizzle!(self.0, [2, 0, 1, 3]) * simd_swizzle!(other.0, [1, 2, 0, 3]);
+        return Vec3(a - b);
     }
 
     pub fn new(_x: f32, _y: f32, _z: f32) -> Vec3 {
-        return Vec3 {
-            x: _x,
-            y: _y,
-            z: _z,
-        };
+        return Vec3(f32x4::from_array([_x, _y, _z, 0.0]));
     }
     pub fn newi(_x: i64, _y: i64, _z: i64) -> Vec3 {
-        return Vec3 {
-            x: _x as f32,
-            y: _y as f32,
-            z: _z as f32,
-        };
+        return Vec3::new(_x as f32, _y as f32, _z as f32);
+    }
+    pub fn splat(v: f32) -> Vec3 {
+        return Vec3::new(v, v, v);
     }
 
     pub fn rand(rng: &mut ChaCha20Rng) -> Vec3 {
-        return Vec3 {
-            x: rand_double(rng),
-            y: rand_double(rng),
-            z: rand_double(rng),
-        };
+        return Vec3::new(rand_double(rng), rand_double(rng), rand_double(rng));
     }
     pub fn rand_range(rng: &mut ChaCha20Rng, min: f32, max: f32) -> Vec3 {
-        return Vec3 {
-            x: rand_double_range(rng, min, max),
-            y: rand_double_range(rng, min, max),
-            z: rand_double_range(rng, min, max),
-        };
+        return Vec3::new(
+            rand_double_range(rng, min, max),
+            rand_double_range(rng, min, max),
+            rand_double_range(rng, min, max),
+        );
     }
 
     pub fn near_zero(&self) -> bool {
@@ -239,18 +163,21 @@ impl Vec3 {
     }
 
     pub fn idx(&self, i: i8) -> f32 {
-        let i = i % 3;
-        if i == 0 {return self.x;}
-        else if i == 1 {return self.y;}
-        else {return self.z;}
+        return self.0[(i % 3) as usize];
     }
 
+    pub fn min(&self, other: Vec3) -> Vec3 {
+        return Vec3(self.0.simd_min(other.0));
+    }
+    pub fn max(&self, other: Vec3) -> Vec3 {
+        return Vec3(self.0.simd_max(other.0));
+    }
 
     pub fn to_simd4(&self) -> f32x4 {
-        return f32x4::from_array([self.x, self.y, self.z, 1.0])
+        return self.0;
     }
+    // Clears the fourth lane, scaling by infinities or dividing by zero can leave a NaN there
     pub fn from_simd4(xyz_: f32x4) -> Self {
-        let [x,y,z, _] = xyz_.as_array().to_owned();
-        Self { x, y, z }
+        return Vec3(simd_swizzle!(xyz_, f32x4::splat(0.0), [0, 1, 2, 4]));
     }
 }
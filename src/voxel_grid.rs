@@ -0,0 +1,136 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+};
+
+use crate::{perlin::Perlin, vec3::Vec3};
+
+#[derive(Clone, Copy)]
+pub enum RawFormat {
+    U8,
+    F32Le,
+}
+
+// Dense density grid, stored x-fastest then y then z
+pub struct VoxelGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub data: Vec<f32>,
+    pub max_density: f32,
+}
+
+impl VoxelGrid {
+    pub fn new(nx: usize, ny: usize, nz: usize, data: Vec<f32>) -> VoxelGrid {
+        assert!(nx > 0 && ny > 0 && nz > 0, "voxel grid needs at least one voxel along each axis");
+        assert_eq!(data.len(), nx * ny * nz, "voxel grid size mismatch");
+        let max_density = data.iter().cloned().fold(0.0, f32::max);
+        return VoxelGrid { nx, ny, nz, data, max_density };
+    }
+
+    // Headerless dump, dimensions supplied by the caller. U8 voxels are mapped to [0, 1].
+    pub fn load_raw<P: AsRef<Path>>(path: P, nx: usize, ny: usize, nz: usize, format: RawFormat) -> io::Result<VoxelGrid> {
+        let mut bytes = vec![];
+        File::open(path)?.read_to_end(&mut bytes)?;
+        let data = Self::decode(&bytes, Self::voxel_count(nx, ny, nz)?, format)?;
+        return Ok(Self::new(nx, ny, nz, data));
+    }
+
+    // Simple voxel file: an ASCII header line "VGRID nx ny nz" followed by little endian f32 voxels
+    pub fn load_vgrid<P: AsRef<Path>>(path: P) -> io::Result<VoxelGrid> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        if fields.len() != 4 || fields[0] != "VGRID" {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "missing VGRID header"));
+        }
+        let mut dims = [0usize; 3];
+        for i in 0..3 {
+            dims[i] = fields[i + 1]
+                .parse()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad VGRID dimension"))?;
+        }
+
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        let data = Self::decode(&bytes, Self::voxel_count(dims[0], dims[1], dims[2])?, RawFormat::F32Le)?;
+        return Ok(Self::new(dims[0], dims[1], dims[2], data));
+    }
+
+    // Dimensions from a file or the caller, new() would panic on a zero one
+    fn voxel_count(nx: usize, ny: usize, nz: usize) -> io::Result<usize> {
+        if nx == 0 || ny == 0 || nz == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "voxel grid dimensions must be at least 1"));
+        }
+        return nx
+            .checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "voxel grid dimensions too large"));
+    }
+
+    fn decode(bytes: &[u8], count: usize, format: RawFormat) -> io::Result<Vec<f32>> {
+        let stride = match format {
+            RawFormat::U8 => 1,
+            RawFormat::F32Le => 4,
+        };
+        if bytes.len() < count * stride {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "voxel data shorter than grid"));
+        }
+
+        return Ok(match format {
+            RawFormat::U8 => bytes[..count].iter().map(|&b| b as f32 / 255.0).collect(),
+            RawFormat::F32Le => bytes[..count * 4]
+                .chunks_exact(4)
+                .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]).max(0.0))
+                .collect(),
+        });
+    }
+
+    // Bakes turbulence into a grid, faded out towards the edges of the unit cube so it reads as a cloud
+    pub fn from_noise(nx: usize, ny: usize, nz: usize, noise: &Perlin, frequency: f32, octaves: u32, coverage: f32) -> VoxelGrid {
+        let mut data = Vec::with_capacity(nx * ny * nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let p = Vec3::new(
+                        (x as f32 + 0.5) / nx as f32,
+                        (y as f32 + 0.5) / ny as f32,
+                        (z as f32 + 0.5) / nz as f32,
+                    );
+                    let falloff = 1.0 - ((p - Vec3::new(0.5, 0.5, 0.5)).length() * 2.0).min(1.0);
+                    let density = noise.turbulence(&(p * frequency), octaves) + coverage - 1.0;
+                    data.push((density * falloff).max(0.0));
+                }
+            }
+        }
+        return Self::new(nx, ny, nz, data);
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f32 {
+        return self.data[x + self.nx * (y + self.ny * z)];
+    }
+
+    // Trilinear lookup, p in grid space [0, 1]^3 with voxel centres at (i + 0.5) / n
+    pub fn sample(&self, p: &Vec3) -> f32 {
+        let fx = (p.x * self.nx as f32 - 0.5).clamp(0.0, (self.nx - 1) as f32);
+        let fy = (p.y * self.ny as f32 - 0.5).clamp(0.0, (self.ny - 1) as f32);
+        let fz = (p.z * self.nz as f32 - 0.5).clamp(0.0, (self.nz - 1) as f32);
+        let (x0, y0, z0) = (fx as usize, fy as usize, fz as usize);
+        let (x1, y1, z1) = (
+            (x0 + 1).min(self.nx - 1),
+            (y0 + 1).min(self.ny - 1),
+            (z0 + 1).min(self.nz - 1),
+        );
+        let (tx, ty, tz) = (fx - x0 as f32, fy - y0 as f32, fz - z0 as f32);
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let c00 = lerp(self.voxel(x0, y0, z0), self.voxel(x1, y0, z0), tx);
+        let c10 = lerp(self.voxel(x0, y1, z0), self.voxel(x1, y1, z0), tx);
+        let c01 = lerp(self.voxel(x0, y0, z1), self.voxel(x1, y0, z1), tx);
+        let c11 = lerp(self.voxel(x0, y1, z1), self.voxel(x1, y1, z1), tx);
+        return lerp(lerp(c00, c10, ty), lerp(c01, c11, ty), tz);
+    }
+}
@@ -0,0 +1,292 @@
+use std::f32::consts::PI;
+
+use crate::vec3::Vec3;
+
+// Maps from uniform numbers in [0, 1)^2 to other distributions, in closed form so every warp takes a
+// fixed number of dimensions from a sampler and keeps its strata together. Directions are in a
+// frame with +z up, each warp has a pdf (per solid angle for directions, per area otherwise) to
+// weight by, and the tests below check the two against each other.
+
+pub fn uniform_sphere(u: (f32, f32)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    return Vec3::new(r * phi.cos(), r * phi.sin(), z);
+}
+
+pub fn uniform_sphere_pdf() -> f32 {
+    return 1.0 / (4.0 * PI);
+}
+
+pub fn uniform_hemisphere(u: (f32, f32)) -> Vec3 {
+    let z = u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    return Vec3::new(r * phi.cos(), r * phi.sin(), z);
+}
+
+pub fn uniform_hemisphere_pdf() -> f32 {
+    return 1.0 / (2.0 * PI);
+}
+
+// Malley's method, a point on the disk lifted up onto the hemisphere
+pub fn cosine_hemisphere(u: (f32, f32)) -> Vec3 {
+    let d = concentric_disk(u);
+    let z = (1.0 - d.x * d.x - d.y * d.y).max(0.0).sqrt();
+    return Vec3::new(d.x, d.y, z);
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+    return cos_theta.max(0.0) / PI;
+}
+
+// Shirley and Chiu's concentric map of the square onto the unit disk, keeps strata compact
+pub fn concentric_disk(u: (f32, f32)) -> Vec3 {
+    let (a, b) = (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+    let (r, theta) = if a.abs() > b.abs() { (a, PI / 4.0 * (b / a)) } else { (b, PI / 2.0 - PI / 4.0 * (a / b)) };
+    return Vec3::new(r * theta.cos(), r * theta.sin(), 0.0);
+}
+
+pub fn concentric_disk_pdf() -> f32 {
+    return 1.0 / PI;
+}
+
+// Barycentrics (b0, b1) uniform over a triangle, b2 = 1 - b0 - b1
+pub fn uniform_triangle(u: (f32, f32)) -> (f32, f32) {
+    let s = u.0.sqrt();
+    return (1.0 - s, u.1 * s);
+}
+
+// Per unit of barycentric area, divide by twice the triangle's area for world space
+pub fn uniform_triangle_pdf() -> f32 {
+    return 2.0;
+}
+
+// Uniform in the unit ball, a direction on the sphere and a radius
+pub fn uniform_ball(u: (f32, f32), r: f32) -> Vec3 {
+    return r.cbrt() * uniform_sphere(u);
+}
+
+// Uniform point in a regular polygon inscribed in the unit circle, one corner at `rotation` radians.
+// Every wedge from the centre has the same area, so the first number picks one and what's left of
+// it goes on sampling the triangle.
+pub fn uniform_polygon(sides: u32, rotation: f32, u: (f32, f32)) -> Vec3 {
+    let scaled = u.0 * sides as f32;
+    let wedge = (scaled as u32).min(sides - 1);
+    let step = 2.0 * PI / sides as f32;
+    let a0 = rotation + wedge as f32 * step;
+    let (c0, c1) = (Vec3::new(a0.cos(), a0.sin(), 0.0), Vec3::new((a0 + step).cos(), (a0 + step).sin(), 0.0));
+
+    let (mut r1, mut r2) = ((scaled - wedge as f32).min(1.0), u.1);
+    if r1 + r2 > 1.0 {
+        r1 = 1.0 - r1;
+        r2 = 1.0 - r2;
+    }
+    return r1 * c0 + r2 * c1;
+}
+
+// Turns a +z up direction into one around the unit vector `n` (Duff et al., "Building an Orthonormal
+// Basis, Revisited")
+pub fn to_world(v: Vec3, n: &Vec3) -> Vec3 {
+    let sign = 1.0f32.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    let t = Vec3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+    let bt = Vec3::new(b, sign + n.y * n.y * a, -n.y);
+    return v.x * t + v.y * bt + v.z * (*n);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+    use crate::rand_double::rand_double;
+
+    // Longitude of a direction as a fraction of the circle
+    fn turn(v: &Vec3) -> f32 {
+        return (v.y.atan2(v.x) / (2.0 * PI)).rem_euclid(1.0);
+    }
+
+    // Pearson's chi-square test of a warp against its pdf, a histogram of a million samples over 32x32
+    // cells. Cells expecting fewer than 5 samples are pooled. Samples are mapped into the unit square the
+    // histogram covers, and `density` is the warp's pdf times the Jacobian of that mapping. Returns the p-value.
+    fn chi_square(sample: fn((f32, f32)) -> (f32, f32), density: fn(f32, f32) -> f64) -> f64 {
+        const RES: usize = 32;
+        const SAMPLES: usize = 1_000_000;
+        const SUBDIV: usize = 8;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let mut observed = vec![0.0f64; RES * RES];
+        for _ in 0..SAMPLES {
+            let (x, y) = sample((rand_double(&mut rng), rand_double(&mut rng)));
+            let (cx, cy) = (((x * RES as f32) as usize).min(RES - 1), ((y * RES as f32) as usize).min(RES - 1));
+            observed[cy * RES + cx] += 1.0;
+        }
+        // Midpoint rule over each cell
+        let expected: Vec<f64> = (0..RES * RES)
+            .map(|cell| {
+                let (cx, cy) = (cell % RES, cell / RES);
+                let mut sum = 0.0;
+                for i in 0..SUBDIV * SUBDIV {
+                    let x = (cx as f64 + ((i % SUBDIV) as f64 + 0.5) / SUBDIV as f64) / RES as f64;
+                    let y = (cy as f64 + ((i / SUBDIV) as f64 + 0.5) / SUBDIV as f64) / RES as f64;
+                    sum += density(x as f32, y as f32);
+                }
+                sum / (SUBDIV * SUBDIV * RES * RES) as f64 * SAMPLES as f64
+            })
+            .collect();
+
+        let mut cells: Vec<(f64, f64)> = expected.into_iter().zip(observed).collect();
+        cells.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (mut chi2, mut dof) = (0.0, 0);
+        let (mut pooled_expected, mut pooled_observed) = (0.0, 0.0);
+        for (e, o) in cells {
+            if e == 0.0 && o > 0.0 {
+                // Samples where the pdf is zero
+                chi2 = f64::INFINITY;
+            } else if e < 5.0 || pooled_expected > 0.0 && pooled_expected < 5.0 {
+                pooled_expected += e;
+                pooled_observed += o;
+            } else {
+                chi2 += (o - e) * (o - e) / e;
+                dof += 1;
+            }
+        }
+        if pooled_expected > 0.0 {
+            chi2 += (pooled_observed - pooled_expected).powi(2) / pooled_expected;
+            dof += 1;
+        }
+        dof -= 1;
+
+        return if chi2.is_finite() { gamma_q(dof as f64 / 2.0, chi2 / 2.0) } else { 0.0 };
+    }
+
+    // 1% significance level shared between the five warps
+    const ALPHA: f64 = 0.01 / 5.0;
+
+    #[test]
+    fn uniform_sphere_matches_pdf() {
+        let p = chi_square(
+            |u| {
+                let v = uniform_sphere(u);
+                ((v.z + 1.0) / 2.0, turn(&v))
+            },
+            |_, _| (uniform_sphere_pdf() * 4.0 * PI) as f64,
+        );
+        assert!(p > ALPHA, "p = {}", p);
+    }
+
+    #[test]
+    fn uniform_hemisphere_matches_pdf() {
+        let p = chi_square(
+            |u| {
+                let v = uniform_hemisphere(u);
+                (v.z, turn(&v))
+            },
+            |_, _| (uniform_hemisphere_pdf() * 2.0 * PI) as f64,
+        );
+        assert!(p > ALPHA, "p = {}", p);
+    }
+
+    #[test]
+    fn cosine_hemisphere_matches_pdf() {
+        let p = chi_square(
+            |u| {
+                let v = cosine_hemisphere(u);
+                (v.z, turn(&v))
+            },
+            |z, _| (cosine_hemisphere_pdf(z) * 2.0 * PI) as f64,
+        );
+        assert!(p > ALPHA, "p = {}", p);
+    }
+
+    #[test]
+    fn concentric_disk_matches_pdf() {
+        let p = chi_square(
+            |u| {
+                let v = concentric_disk(u);
+                (v.x * v.x + v.y * v.y, turn(&v))
+            },
+            |_, _| (concentric_disk_pdf() * PI) as f64,
+        );
+        assert!(p > ALPHA, "p = {}", p);
+    }
+
+    // (b0 + b1, b1 / (b0 + b1)) covers the triangle without the seam of a grid over the barycentrics
+    #[test]
+    fn uniform_triangle_matches_pdf() {
+        let p = chi_square(
+            |u| {
+                let (b0, b1) = uniform_triangle(u);
+                let s = b0 + b1;
+                (s, if s > 0.0 { b1 / s } else { 0.0 })
+            },
+            |s, _| (uniform_triangle_pdf() * s) as f64,
+        );
+        assert!(p > ALPHA, "p = {}", p);
+    }
+
+    // Regularised upper incomplete gamma function Q(a, x), the chi-square tail probability with 2a degrees
+    // of freedom at 2x. Series below a + 1 and Lentz's continued fraction above (Numerical Recipes 6.2).
+    fn gamma_q(a: f64, x: f64) -> f64 {
+        if x <= 0.0 {
+            return 1.0;
+        }
+        let prefix = (-x + a * x.ln() - ln_gamma(a)).exp();
+        if x < a + 1.0 {
+            let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+            while term.abs() > sum.abs() * 1e-12 {
+                n += 1.0;
+                term *= x / n;
+                sum += term;
+            }
+            return 1.0 - sum * prefix;
+        }
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let (mut c, mut d) = (1.0 / tiny, 1.0 / b);
+        let mut h = d;
+        for i in 1..1000 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            d = if d.abs() < tiny { tiny } else { d };
+            c = b + an / c;
+            c = if c.abs() < tiny { tiny } else { c };
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-12 {
+                break;
+            }
+        }
+        return prefix * h;
+    }
+
+    // Lanczos approximation, g = 7
+    fn ln_gamma(x: f64) -> f64 {
+        const COEFFS: [f64; 9] = [
+            0.999_999_999_999_809_9,
+            676.520_368_121_885_1,
+            -1_259.139_216_722_402_8,
+            771.323_428_777_653_1,
+            -176.615_029_162_140_6,
+            12.507_343_278_686_905,
+            -0.138_571_095_265_720_12,
+            9.984_369_578_019_572e-6,
+            1.505_632_735_149_311_6e-7,
+        ];
+        let x = x - 1.0;
+        let mut sum = COEFFS[0];
+        for (i, c) in COEFFS.iter().enumerate().skip(1) {
+            sum += c / (x + i as f64);
+        }
+        let t = x + 7.5;
+        return 0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln();
+    }
+}
@@ -0,0 +1,203 @@
+use std::{simd::f32x4, time::Instant};
+
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    background,
+    camera::Camera,
+    closest_hit,
+    hittable::{HitRecord, HittableList},
+    mats::MatManager,
+    packet::{self, LANES},
+    ray::Ray,
+    sampler::{Sampler, SamplerConfig},
+    stats::{self, RayKind},
+    vec3::Vec3,
+};
+
+// Paths kept in flight per thread. Big enough that every material gets a long run of hits to shade.
+const WAVE_SIZE: usize = 1 << 14;
+
+// Path tracing in stages instead of one recursive call per sample. A queue of paths goes through
+// extension (closest hit of every path's ray, in packets when packet tracing is on), then shading,
+// where the hits are sorted by material and each material scatters its whole run, then compaction,
+// which drops the finished paths and refills the queue with new camera paths. Every path draws the
+// same dimensions from its sampler in the same order as ray_color, so the image is the same.
+//
+// The renderer doesn't sample lights directly, so there are no shadow rays to trace in bulk yet.
+// Light sampling would add a stage between shading and the next extension that tests the shadow
+// rays shading queued up.
+pub struct Wavefront {
+    paths: Vec<PathState>,
+    hits: Vec<Option<HitRecord>>,
+    // Samplers of the slots that are free, a path takes one for its whole life
+    free: Vec<Box<dyn Sampler>>,
+}
+
+struct PathState {
+    ray: Ray,
+    throughput: f32x4,
+    radiance: f32x4,
+    // Bounces left, ray_color's depth
+    depth: u64,
+    // Rays traced so far
+    rays: u64,
+    x: u32,
+    film_x: f32,
+    film_y: f32,
+    sampler: Box<dyn Sampler>,
+}
+
+impl Wavefront {
+    pub fn new(sampler: &SamplerConfig, samples_per_pixel: u32) -> Wavefront {
+        let free = (0..WAVE_SIZE)
+            .map(|_| {
+                let mut seed: <ChaCha20Rng as SeedableRng>::Seed = Default::default();
+                thread_rng().fill(&mut seed);
+                sampler.build(samples_per_pixel, ChaCha20Rng::from_seed(seed))
+            })
+            .collect();
+        return Wavefront { paths: Vec::with_capacity(WAVE_SIZE), hits: Vec::with_capacity(WAVE_SIZE), free };
+    }
+
+    // Every sample of line y. `splat` gets each finished sample with its pixel and film position.
+    pub fn render_line(
+        &mut self,
+        y: u32,
+        target_width: u32,
+        target_height: u32,
+        samples_per_pixel: u16,
+        max_depth: u16,
+        packet_tracing: bool,
+        cam: &Camera,
+        world: &HittableList,
+        mats: &MatManager,
+        mut splat: impl FnMut(u32, f32, f32, f32x4),
+    ) {
+        let total = target_width as usize * samples_per_pixel as usize;
+        let mut next = 0;
+        loop {
+            // Generation, camera paths into the free slots
+            let generation_start = Instant::now();
+            while next < total && !self.free.is_empty() {
+                let (x, sample) = ((next / samples_per_pixel as usize) as u32, (next % samples_per_pixel as usize) as u32);
+                next += 1;
+                let mut sampler = self.free.pop().unwrap();
+                sampler.start_pixel_sample(x, y, sample);
+                let (jx, jy) = sampler.get_2d();
+                let (sx, sy) = (x as f32 + jx, y as f32 + jy);
+                let u = sx / (target_width - 1) as f32;
+                let v = 1.0 - (sy / (target_height - 1) as f32);
+                match cam.generate_ray(u, v, sampler.as_mut()) {
+                    Some(ray) if max_depth > 0 => self.paths.push(PathState {
+                        ray,
+                        throughput: f32x4::splat(1.0),
+                        radiance: f32x4::splat(0.0),
+                        depth: max_depth as u64,
+                        rays: 0,
+                        x,
+                        film_x: sx,
+                        film_y: sy,
+                        sampler,
+                    }),
+                    _ => {
+                        splat(x, sx, sy, f32x4::splat(0.0));
+                        self.free.push(sampler);
+                    }
+                }
+            }
+            stats::add_wavefront_time(0, generation_start.elapsed());
+            if self.paths.is_empty() {
+                break;
+            }
+
+            let start = Instant::now();
+            self.extend(world, packet_tracing);
+            stats::add_wavefront_time(1, start.elapsed());
+            let start = Instant::now();
+            self.shade(mats);
+            stats::add_wavefront_time(2, start.elapsed());
+            let start = Instant::now();
+            self.compact(&mut splat);
+            stats::add_wavefront_time(3, start.elapsed());
+        }
+    }
+
+    fn extend(&mut self, world: &HittableList, packet_tracing: bool) {
+        for path in &mut self.paths {
+            stats::count_ray(if path.rays == 0 { RayKind::Camera } else { RayKind::Bounce });
+            path.rays += 1;
+        }
+        self.hits.clear();
+        if packet_tracing {
+            for chunk in self.paths.chunks(LANES) {
+                let rays: Vec<Option<Ray>> = chunk.iter().map(|p| Some(p.ray)).collect();
+                self.hits.extend(packet::closest_hits(world, &rays));
+            }
+        } else {
+            self.hits.extend(self.paths.iter().map(|p| closest_hit(world, &p.ray)));
+        }
+    }
+
+    // Misses pick up the sky and finish. Hits are shaded a material at a time, a path finishes
+    // when its material absorbs or it runs out of bounces.
+    fn shade(&mut self, mats: &MatManager) {
+        let mut order: Vec<usize> = (0..self.paths.len()).collect();
+        order.sort_unstable_by_key(|&i| self.hits[i].map_or(i64::MIN, |rec| rec.material));
+
+        let mut start = 0;
+        while start < order.len() {
+            let id = self.hits[order[start]].map(|rec| rec.material);
+            let mut end = start;
+            while end < order.len() && self.hits[order[end]].map(|rec| rec.material) == id {
+                end += 1;
+            }
+            match id {
+                None => {
+                    for &i in &order[start..end] {
+                        let path = &mut self.paths[i];
+                        path.radiance += path.throughput * background(&path.ray);
+                        path.depth = 0;
+                    }
+                }
+                Some(id) => {
+                    let mat = mats.get_mat(&id);
+                    for &i in &order[start..end] {
+                        let path = &mut self.paths[i];
+                        let mut rec = self.hits[i].unwrap();
+                        mat.perturb_normal(&mut rec);
+                        path.radiance += path.throughput * mat.emitted(rec.tex_u, rec.tex_v, &rec.point);
+
+                        let mut scattered = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), path.ray.time);
+                        let mut attenuation = f32x4::splat(0.0);
+                        if mat.scatter(&path.ray, &rec, &mut attenuation, &mut scattered, path.sampler.as_mut()) {
+                            path.throughput *= attenuation;
+                            path.ray = scattered;
+                            path.depth -= 1;
+                        } else {
+                            path.depth = 0;
+                        }
+                    }
+                }
+            }
+            start = end;
+        }
+    }
+
+    // Keeps the paths still going in their order, so neighbouring rays stay together
+    fn compact(&mut self, splat: &mut impl FnMut(u32, f32, f32, f32x4)) {
+        let mut kept = 0;
+        for i in 0..self.paths.len() {
+            if self.paths[i].depth > 0 {
+                self.paths.swap(kept, i);
+                kept += 1;
+            }
+        }
+        for path in self.paths.drain(kept..) {
+            stats::record_path(path.rays);
+            splat(path.x, path.film_x, path.film_y, path.radiance);
+            self.free.push(path.sampler);
+        }
+    }
+}